@@ -110,7 +110,7 @@ async fn main() {
         tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
     }
 
-    results.sort_by(|a, b| a.1.cmp(&b.1));
+    results.sort_by_key(|r| r.1);
 
     println!("\n{:-<80}", "");
     println!("\n📊 GROQ Results (sorted by speed):\n");
@@ -115,7 +115,7 @@ async fn main() {
     }
 
     // Sort by time
-    results.sort_by(|a, b| a.1.cmp(&b.1));
+    results.sort_by_key(|r| r.1);
 
     println!("\n{:-<80}", "");
     println!("\n📊 Results (sorted by speed):\n");
@@ -1,6 +1,6 @@
 //! Run with: cargo run --bin cleanup
 
-use mongodb::{bson::doc, Client};
+use mongodb::Client;
 use std::env;
 
 #[tokio::main]
@@ -0,0 +1,153 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::AppError;
+
+/// API key presented by the caller via `X-API-Key` or `Authorization: Bearer`.
+#[derive(Debug, Clone)]
+pub struct ApiKey(pub Option<String>);
+
+impl ApiKey {
+    /// Stable identifier for the key that is safe to store (a truncated SHA-256).
+    /// Callers without a key share the `anonymous` identity.
+    pub fn id(&self) -> String {
+        match &self.0 {
+            Some(key) => {
+                let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+                digest[..16].to_string()
+            }
+            None => "anonymous".to_string(),
+        }
+    }
+
+    /// Owner id that sessions and transcriptions are filtered by, so keys
+    /// only see what they created. `None` (everything visible) while auth is
+    /// disabled and for the admin key.
+    pub fn owner_scope(&self, config: &Config) -> Option<String> {
+        let is_admin = self.0.is_some() && self.0.as_deref() == config.admin_api_key.as_deref();
+        if config.api_keys.is_empty() || is_admin {
+            return None;
+        }
+        Some(self.id())
+    }
+
+    /// Whether the key may call protected routes. Always true when no
+    /// `API_KEYS` are configured. The admin key is accepted too.
+    pub fn is_authorized(&self, config: &Config) -> bool {
+        if config.api_keys.is_empty() {
+            return true;
+        }
+
+        match &self.0 {
+            Some(key) => {
+                config.api_keys.iter().any(|k| k == key)
+                    || config.admin_api_key.as_deref() == Some(key.as_str())
+            }
+            None => false,
+        }
+    }
+
+    /// Admin routes require the caller to present `ADMIN_API_KEY`.
+    /// They are disabled entirely when it isn't configured.
+    pub fn require_admin(&self, config: &Config) -> Result<(), AppError> {
+        let admin_key = config.admin_api_key.as_deref().ok_or_else(|| {
+            AppError::new(StatusCode::FORBIDDEN, "admin_disabled", "Admin endpoints are disabled")
+        })?;
+
+        match &self.0 {
+            Some(key) if key == admin_key => Ok(()),
+            Some(_) => Err(AppError::new(StatusCode::FORBIDDEN, "forbidden", "Invalid admin key")),
+            None => Err(AppError::new(StatusCode::UNAUTHORIZED, "unauthorized", "Missing API key")),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ApiKey {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header_key = parts
+            .headers
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string());
+
+        let bearer_key = parts
+            .headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|v| v.trim().to_string());
+
+        Ok(ApiKey(header_key.or(bearer_key).filter(|k| !k.is_empty())))
+    }
+}
+
+/// Header carrying a caller-supplied stable id for the end user
+pub const END_USER_HEADER: &str = "x-end-user-id";
+
+/// End-user identity forwarded to providers as the OpenAI `user` field, so
+/// provider-side abuse detection can attribute requests. Built from the API
+/// key id, suffixed with the `X-End-User-Id` header when one is sent.
+/// `None` for anonymous callers that don't send the header.
+#[derive(Debug, Clone)]
+pub struct EndUser(pub Option<String>);
+
+impl EndUser {
+    /// Longest client-supplied id that is forwarded
+    const MAX_CLIENT_ID_CHARS: usize = 64;
+
+    pub fn new(api_key: &ApiKey, client_id: Option<&str>) -> Self {
+        let client_id = client_id
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.chars().take(Self::MAX_CLIENT_ID_CHARS).collect::<String>());
+
+        let user = match (&api_key.0, client_id) {
+            (Some(_), Some(client_id)) => Some(format!("{}:{}", api_key.id(), client_id)),
+            (Some(_), None) => Some(api_key.id()),
+            (None, client_id) => client_id,
+        };
+
+        Self(user)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for EndUser {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let api_key = ApiKey::from_request_parts(parts, state).await?;
+        let client_id = parts
+            .headers
+            .get(END_USER_HEADER)
+            .and_then(|v| v.to_str().ok());
+
+        Ok(Self::new(&api_key, client_id))
+    }
+}
+
+/// Middleware for protected routes: rejects callers without a valid key
+pub async fn require_api_key(
+    State(config): State<Arc<Config>>,
+    api_key: ApiKey,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if api_key.is_authorized(&config) {
+        return Ok(next.run(request).await);
+    }
+
+    Err(match api_key.0 {
+        Some(_) => AppError::new(StatusCode::FORBIDDEN, "forbidden", "Invalid API key"),
+        None => AppError::new(StatusCode::UNAUTHORIZED, "unauthorized", "Missing API key"),
+    })
+}
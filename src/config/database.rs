@@ -1,13 +1,38 @@
+use mongodb::error::{Error, ErrorKind};
 use mongodb::{Client, Database};
-use std::env;
 
-pub async fn connect() -> Database {
-    let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
-    let db_name = env::var("MONGODB_DATABASE").unwrap_or_else(|_| "cleuly".to_string());
+use crate::config::Config;
 
-    let client = Client::with_uri_str(&uri)
+pub async fn connect(config: &Config) -> Database {
+    let client = Client::with_uri_str(&config.mongodb_uri)
         .await
         .expect("Failed to connect to MongoDB");
 
-    client.database(&db_name)
+    client.database(&config.mongodb_database)
+}
+
+/// Whether `watch` failed because the server isn't a replica set, the only
+/// deployment that supports change streams
+pub fn is_change_stream_unsupported(error: &Error) -> bool {
+    matches!(&*error.kind, ErrorKind::Command(e) if e.code == 40573)
+}
+
+/// Whether an error means Mongo couldn't be reached, as opposed to a
+/// rejected or failed operation
+pub fn is_unavailable(error: &Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::ServerSelection { .. } | ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. }
+    )
+}
+
+/// A count or sum from an aggregation. `$count` and `$sum` yield an int32
+/// or int64 depending on magnitude, and a double over floats.
+pub fn number(value: Option<&bson::Bson>) -> u64 {
+    match value {
+        Some(bson::Bson::Int32(n)) => *n as u64,
+        Some(bson::Bson::Int64(n)) => *n as u64,
+        Some(bson::Bson::Double(n)) => *n as u64,
+        _ => 0,
+    }
 }
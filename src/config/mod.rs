@@ -1,2 +1,5 @@
 pub mod database;
 pub mod redis;
+pub mod settings;
+
+pub use settings::{Config, Persistence, ProviderConfig};
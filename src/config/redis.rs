@@ -1,12 +1,22 @@
-use redis::aio::ConnectionManager;
-use std::env;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use std::time::Duration;
 
-pub async fn connect() -> ConnectionManager {
-    let uri = env::var("REDIS_URI").expect("REDIS_URI must be set");
+use crate::config::Config;
 
-    let client = redis::Client::open(uri).expect("Failed to create Redis client");
+pub async fn connect(config: &Config) -> ConnectionManager {
+    let client = redis::Client::open(config.redis_uri.as_str()).expect("Failed to create Redis client");
 
-    ConnectionManager::new(client)
+    // Reconnect with exponential backoff (100ms doubling up to 5s), and time
+    // out commands quickly so a Redis outage degrades to Mongo reads
+    // instead of hanging requests
+    let manager_config = ConnectionManagerConfig::new()
+        .set_exponent_base(2.0)
+        .set_min_delay(Duration::from_millis(100))
+        .set_max_delay(Duration::from_secs(5))
+        .set_connection_timeout(Some(Duration::from_secs(1)))
+        .set_response_timeout(Some(Duration::from_millis(500)));
+
+    ConnectionManager::new_with_config(client, manager_config)
         .await
         .expect("Failed to connect to Redis")
 }
@@ -0,0 +1,625 @@
+use axum::http::HeaderValue;
+use std::collections::BTreeMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::modules::session::model::MESSAGE_TOO_LONG_ACTIONS;
+use crate::services::backend::ClientBackend;
+use crate::services::llm::{DEFAULT_TITLE_PROMPT, DEFAULT_TYPE_TEMPERATURES};
+use crate::services::stt::{DEFAULT_AUDIO_FORMATS, LOW_CONFIDENCE_ACTIONS, STT_RESPONSE_FORMATS};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{0} must be set")]
+    Missing(&'static str),
+    #[error("{var} has an invalid value: {value}")]
+    Invalid { var: &'static str, value: String },
+}
+
+/// Connection settings for one LLM/STT provider
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub name: &'static str,
+    pub base_url: String,
+    pub api_key: String,
+    /// Fast model used when a request doesn't name one
+    pub default_model: String,
+}
+
+struct ProviderDefaults {
+    name: &'static str,
+    api_key_var: &'static str,
+    base_url_var: &'static str,
+    default_model_var: &'static str,
+    base_url: &'static str,
+    default_model: &'static str,
+}
+
+/// What gets written to Mongo. `None` is for deployments that mustn't keep
+/// prompts or transcriptions: requests are answered as usual, but
+/// completions, transcriptions and session messages are never stored, and
+/// new sessions keep only their title and metadata.
+///
+/// Under `None` these degrade:
+/// - session history is always empty, so chats get no earlier context and
+///   untitled sessions are never auto-titled
+/// - `GET` of a completion or transcription by its returned id is a 404,
+///   including background transcriptions polled by id
+/// - listings, facets, stats and similar-session search only cover what
+///   was stored before the switch
+/// - `/analyze` results aren't cached in Redis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Persistence {
+    #[default]
+    Full,
+    None,
+}
+
+impl Persistence {
+    pub fn stores(self) -> bool {
+        self == Persistence::Full
+    }
+}
+
+impl FromStr for Persistence {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Persistence::Full),
+            "none" => Ok(Persistence::None),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Supported providers. A provider is enabled when its API key is set.
+const PROVIDERS: &[ProviderDefaults] = &[
+    ProviderDefaults {
+        name: "openrouter",
+        api_key_var: "OPENROUTER_API_KEY",
+        base_url_var: "OPENROUTER_BASE_URL",
+        default_model_var: "OPENROUTER_MODEL",
+        base_url: "https://openrouter.ai/api/v1",
+        default_model: "nvidia/nemotron-3-nano-30b-a3b:free",
+    },
+    ProviderDefaults {
+        name: "groq",
+        api_key_var: "GROQ_API_KEY",
+        base_url_var: "GROQ_BASE_URL",
+        default_model_var: "GROQ_MODEL",
+        base_url: "https://api.groq.com/openai/v1",
+        default_model: "llama-3.1-8b-instant",
+    },
+];
+
+/// Application settings, read once from the environment at startup.
+///
+/// | Variable               | Default                          |
+/// |------------------------|----------------------------------|
+/// | `HOST`                 | `0.0.0.0`                        |
+/// | `PORT`                 | `8080`                           |
+/// | `MONGODB_URI`          | required                         |
+/// | `MONGODB_DATABASE`     | `cleuly`                         |
+/// | `REDIS_URI`            | required                         |
+/// | `DEFAULT_MODEL`        | `xiaomi/mimo-v2-flash:free`      |
+/// | `OPENROUTER_API_KEY`   | unset (OpenRouter disabled)      |
+/// | `OPENROUTER_BASE_URL`  | `https://openrouter.ai/api/v1`   |
+/// | `OPENROUTER_MODEL`     | `nvidia/nemotron-3-nano-30b-a3b:free` |
+/// | `GROQ_API_KEY`         | unset (Groq and STT disabled)    |
+/// | `GROQ_BASE_URL`        | `https://api.groq.com/openai/v1` |
+/// | `GROQ_MODEL`           | `llama-3.1-8b-instant`           |
+/// | `STT_MODEL`            | `whisper-large-v3-turbo`         |
+/// | `STT_RESPONSE_FORMAT`  | `verbose_json`                   |
+/// | `DAILY_TOKEN_QUOTA`    | unset (no daily limit)           |
+/// | `MONTHLY_TOKEN_QUOTA`  | unset (no monthly limit)         |
+/// | `ADMIN_API_KEY`        | unset (admin routes disabled)    |
+/// | `WARMUP`               | `false`                          |
+/// | `AUDIO_HINTS`          | `true`                           |
+/// | `MATCH_LANGUAGE`       | `true`                           |
+/// | `EMBEDDING_MODEL`      | `openai/text-embedding-3-small`  |
+/// | `AUTO_INDEX_SESSIONS`  | `false`                          |
+/// | `API_KEYS`             | unset (API-key auth disabled)    |
+/// | `CORS_ALLOWED_ORIGINS` | unset (any origin)               |
+/// | `DEGRADED_MODE`        | `false`                          |
+/// | `STT_MAX_CONCURRENT_PER_SESSION` | `2`                    |
+/// | `STT_QUEUE_WAIT_MS`    | unset (busy sessions get a 429)  |
+/// | `AI_MAX_CONCURRENT_PER_KEY` | `4`                         |
+/// | `AI_QUEUE_WAIT_MS`     | unset (busy keys get a 429)      |
+/// | `STT_LARGE_FILE_BYTES` | `5242880` (5MB)                  |
+/// | `STT_MAX_ATTEMPTS`     | `3`                              |
+/// | `STT_MAX_ATTEMPTS_LARGE` | `2`                            |
+/// | `STT_MIN_ANSWER_CONFIDENCE` | unset (always answered)     |
+/// | `STT_LOW_CONFIDENCE_ACTION` | `caveat`                    |
+/// | `AUDIO_FORMATS`        | `stt::DEFAULT_AUDIO_FORMATS`     |
+/// | `AUDIO_URL_ALLOW_PRIVATE` | `false`                       |
+/// | `DIARIZATION_URL`      | unset (diarize by pauses)        |
+/// | `DIARIZATION_PAUSE_SECS` | `1.5`                          |
+/// | `DIARIZATION_MAX_SPEAKERS` | `2`                          |
+/// | `SESSION_METADATA_SCHEMA` | unset (any metadata accepted) |
+/// | `SESSION_METADATA_MAX_BYTES` | `65536` (64KB, serialized) |
+/// | `SESSION_METADATA_MAX_DEPTH` | `16`                       |
+/// | `MAX_MESSAGE_CHARS`    | `100000`                         |
+/// | `MESSAGE_TOO_LONG_ACTION` | `reject`                      |
+/// | `MAX_AUDIO_DURATION_SECS` | unset (no duration limit)     |
+/// | `ANALYZE_CACHE_TTL_SECS` | unset (analyze results not cached) |
+/// | `ANALYZE_CACHE_MAX_CHARS` | `8000`                        |
+/// | `SESSION_STALE_TTL_SECS` | unset (no stale fallback)      |
+/// | `TRANSCRIPTION_DEDUPE_WINDOW_SECS` | `300`                |
+/// | `MAX_SESSIONS_PER_KEY` | unset (no limit)                 |
+/// | `AI_COMPLETION_RETENTION_DAYS` | unset (kept forever)     |
+/// | `BENCHMARK_MODEL_TIMEOUT_MS` | `30000`                    |
+/// | `BENCHMARK_DEADLINE_MS` | `60000`                         |
+/// | `AUTO_TITLE`           | `true`                           |
+/// | `AUTO_TITLE_MIN_WORDS` | `3`                              |
+/// | `AUTO_TITLE_PROMPT`    | `llm::DEFAULT_TITLE_PROMPT`      |
+/// | `PROMPT_TEMPLATES`     | unset (no templates)             |
+/// | `PROMPT_TEMPLATE_STRICT` | `false`                        |
+/// | `TYPE_TEMPERATURES`    | `llm::DEFAULT_TYPE_TEMPERATURES` |
+/// | `SPLIT_THINK_BLOCKS`   | `false`                          |
+/// | `SLOW_REQUEST_MS`      | unset (slow requests not logged) |
+/// | `PERSISTENCE`          | `full` (`none` stores nothing, see `Persistence`) |
+///
+/// List variables are comma-separated.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    pub mongodb_uri: String,
+    pub mongodb_database: String,
+    pub redis_uri: String,
+    pub default_model: String,
+    /// Configured providers by name (`openrouter`, `groq`)
+    pub providers: BTreeMap<&'static str, ProviderConfig>,
+    pub stt_model: String,
+    /// Whisper `response_format`, one of `stt::STT_RESPONSE_FORMATS`. Only
+    /// `verbose_json` reports duration, language and segments.
+    pub stt_response_format: String,
+    pub daily_token_quota: Option<u64>,
+    pub monthly_token_quota: Option<u64>,
+    pub admin_api_key: Option<String>,
+    pub warmup: bool,
+    /// Add `audio_hints` to transcriptions of oversized WAV uploads
+    pub audio_hints: bool,
+    /// Ask the model to answer in the transcribed/requested language
+    pub match_language: bool,
+    /// OpenRouter model used to embed session messages
+    pub embedding_model: String,
+    /// Embed new session messages in the background as they're added,
+    /// instead of only on `POST /api/session/{id}/index`
+    pub auto_index_sessions: bool,
+    /// Keys accepted on protected routes. Empty disables the check.
+    pub api_keys: Vec<String>,
+    /// Origins allowed by CORS. Empty allows any origin.
+    pub cors_allowed_origins: Vec<String>,
+    /// Answer session chats without history when Mongo is unreachable
+    pub degraded_mode: bool,
+    /// Transcriptions that may run at once for one session
+    pub stt_max_concurrent_per_session: usize,
+    /// How long a transcription waits for one of its session's slots before
+    /// giving up with a 503. `None` rejects it with a 429 right away.
+    pub stt_queue_wait_ms: Option<u64>,
+    /// LLM requests on `/api/ai/*` that may run at once for one API key
+    pub ai_max_concurrent_per_key: usize,
+    /// How long an LLM request waits for one of its key's slots before
+    /// giving up with a 503. `None` rejects it with a 429 right away.
+    pub ai_queue_wait_ms: Option<u64>,
+    /// Uploads at least this big use `stt_max_attempts_large`
+    pub stt_large_file_bytes: u64,
+    /// Upload attempts, including the first, for small files
+    pub stt_max_attempts: u32,
+    /// Upload attempts, including the first, for large files
+    pub stt_max_attempts_large: u32,
+    /// Transcriptions whose `SttResponse::average_confidence` is lower are
+    /// flagged `low_confidence` by `transcribe-ai`
+    pub stt_min_answer_confidence: Option<f32>,
+    /// One of `stt::LOW_CONFIDENCE_ACTIONS`
+    pub stt_low_confidence_action: String,
+    /// Accepted upload extensions, each with the MIME type sent to the STT
+    /// provider. The one list behind format checks and uploads.
+    pub audio_formats: Vec<(String, String)>,
+    /// Let `transcribe-url` download from loopback and private addresses,
+    /// e.g. a file server on the same host. Off, those are refused.
+    pub audio_url_allow_private: bool,
+    /// External speaker diarization service for meeting transcriptions
+    pub diarization_url: Option<String>,
+    /// Silence that starts a new speaker turn when diarizing by pauses
+    pub diarization_pause_secs: f32,
+    /// Speakers the pause heuristic rotates through
+    pub diarization_max_speakers: usize,
+    /// JSON Schema that session metadata must conform to
+    pub session_metadata_schema: Option<Arc<jsonschema::Validator>>,
+    /// Largest session metadata accepted, measured as serialized JSON
+    pub session_metadata_max_bytes: usize,
+    /// Deepest nesting of objects and arrays accepted in session metadata
+    pub session_metadata_max_depth: usize,
+    /// Longest session message content, in characters
+    pub max_message_chars: usize,
+    /// One of `session::model::MESSAGE_TOO_LONG_ACTIONS`
+    pub message_too_long_action: String,
+    /// Longest audio accepted for transcription
+    pub max_audio_duration_secs: Option<u32>,
+    /// How long identical analyze requests are served from Redis
+    pub analyze_cache_ttl_secs: Option<u64>,
+    /// Longer analyze texts are never cached
+    pub analyze_cache_max_chars: usize,
+    /// How long a last-known copy of a session is kept to serve while
+    /// Mongo is unreachable
+    pub session_stale_ttl_secs: Option<u64>,
+    /// How far back `POST /api/transcription?dedupe=true` looks for an
+    /// identical transcription
+    pub transcription_dedupe_window_secs: u64,
+    /// Sessions one API key may own. Callers without a key share a single
+    /// `anonymous` allowance.
+    pub max_sessions_per_key: Option<u64>,
+    /// Completions older than this are deleted by `services::retention`.
+    /// They silently drop out of `/api/ai/stats` usage and latency too.
+    pub ai_completion_retention_days: Option<u32>,
+    /// A benchmarked model slower than this is reported as a `timeout`
+    pub benchmark_model_timeout_ms: u64,
+    /// Models of a benchmark still running after this are abandoned and
+    /// reported as `pending`
+    pub benchmark_deadline_ms: u64,
+    /// Title untitled sessions from their first substantive chat message
+    pub auto_title: bool,
+    /// Shorter messages are too trivial to title a session from
+    pub auto_title_min_words: usize,
+    /// System prompt used to generate session titles
+    pub auto_title_prompt: String,
+    /// Named prompt templates, read from the JSON object (name to template)
+    /// in the `PROMPT_TEMPLATES` file
+    pub prompt_templates: BTreeMap<String, String>,
+    /// Reject template requests missing a variable instead of leaving it empty
+    pub prompt_template_strict: bool,
+    /// Default temperature by suggestion/analysis type
+    pub type_temperatures: BTreeMap<String, f32>,
+    /// Move `<think>` blocks out of completions into `reasoning`
+    pub split_think_blocks: bool,
+    /// Requests taking at least this long are logged at warn level
+    pub slow_request_ms: Option<u64>,
+    pub persistence: Persistence,
+    /// Replaces provider HTTP calls, e.g. with the `mock` feature's
+    /// `MockBackend`. Never read from the environment.
+    pub client_backend: Option<Arc<dyn ClientBackend>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8080,
+            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            mongodb_database: "cleuly".to_string(),
+            redis_uri: "redis://localhost:6379".to_string(),
+            default_model: "xiaomi/mimo-v2-flash:free".to_string(),
+            providers: BTreeMap::new(),
+            stt_model: "whisper-large-v3-turbo".to_string(),
+            stt_response_format: "verbose_json".to_string(),
+            daily_token_quota: None,
+            monthly_token_quota: None,
+            admin_api_key: None,
+            warmup: false,
+            audio_hints: true,
+            match_language: true,
+            embedding_model: "openai/text-embedding-3-small".to_string(),
+            auto_index_sessions: false,
+            api_keys: Vec::new(),
+            cors_allowed_origins: Vec::new(),
+            degraded_mode: false,
+            stt_max_concurrent_per_session: 2,
+            stt_queue_wait_ms: None,
+            ai_max_concurrent_per_key: 4,
+            ai_queue_wait_ms: None,
+            stt_large_file_bytes: 5 * 1024 * 1024,
+            stt_max_attempts: 3,
+            stt_max_attempts_large: 2,
+            stt_min_answer_confidence: None,
+            stt_low_confidence_action: "caveat".to_string(),
+            audio_formats: DEFAULT_AUDIO_FORMATS
+                .iter()
+                .map(|(ext, mime)| (ext.to_string(), mime.to_string()))
+                .collect(),
+            audio_url_allow_private: false,
+            diarization_url: None,
+            diarization_pause_secs: 1.5,
+            diarization_max_speakers: 2,
+            session_metadata_schema: None,
+            session_metadata_max_bytes: 64 * 1024,
+            session_metadata_max_depth: 16,
+            max_message_chars: 100_000,
+            message_too_long_action: "reject".to_string(),
+            max_audio_duration_secs: None,
+            analyze_cache_ttl_secs: None,
+            analyze_cache_max_chars: 8000,
+            session_stale_ttl_secs: None,
+            transcription_dedupe_window_secs: 300,
+            max_sessions_per_key: None,
+            ai_completion_retention_days: None,
+            benchmark_model_timeout_ms: 30_000,
+            benchmark_deadline_ms: 60_000,
+            auto_title: true,
+            auto_title_min_words: 3,
+            auto_title_prompt: DEFAULT_TITLE_PROMPT.to_string(),
+            prompt_templates: BTreeMap::new(),
+            prompt_template_strict: false,
+            type_temperatures: DEFAULT_TYPE_TEMPERATURES
+                .iter()
+                .map(|(kind, temperature)| (kind.to_string(), *temperature))
+                .collect(),
+            split_think_blocks: false,
+            slow_request_ms: None,
+            persistence: Persistence::Full,
+            client_backend: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+
+        Ok(Self {
+            host: string_or("HOST", defaults.host),
+            port: parse_or("PORT", defaults.port)?,
+            mongodb_uri: required("MONGODB_URI")?,
+            mongodb_database: string_or("MONGODB_DATABASE", defaults.mongodb_database),
+            redis_uri: required("REDIS_URI")?,
+            default_model: string_or("DEFAULT_MODEL", defaults.default_model),
+            providers: providers(),
+            stt_model: string_or("STT_MODEL", defaults.stt_model),
+            stt_response_format: one_of(
+                "STT_RESPONSE_FORMAT",
+                STT_RESPONSE_FORMATS,
+                defaults.stt_response_format,
+            )?,
+            daily_token_quota: parse_optional("DAILY_TOKEN_QUOTA")?,
+            monthly_token_quota: parse_optional("MONTHLY_TOKEN_QUOTA")?,
+            admin_api_key: optional("ADMIN_API_KEY"),
+            warmup: parse_or("WARMUP", defaults.warmup)?,
+            audio_hints: parse_or("AUDIO_HINTS", defaults.audio_hints)?,
+            match_language: parse_or("MATCH_LANGUAGE", defaults.match_language)?,
+            embedding_model: string_or("EMBEDDING_MODEL", defaults.embedding_model),
+            auto_index_sessions: parse_or("AUTO_INDEX_SESSIONS", defaults.auto_index_sessions)?,
+            api_keys: list("API_KEYS"),
+            cors_allowed_origins: cors_origins("CORS_ALLOWED_ORIGINS")?,
+            degraded_mode: parse_or("DEGRADED_MODE", defaults.degraded_mode)?,
+            stt_max_concurrent_per_session: parse_or(
+                "STT_MAX_CONCURRENT_PER_SESSION",
+                defaults.stt_max_concurrent_per_session,
+            )?,
+            stt_queue_wait_ms: parse_optional("STT_QUEUE_WAIT_MS")?,
+            ai_max_concurrent_per_key: parse_or(
+                "AI_MAX_CONCURRENT_PER_KEY",
+                defaults.ai_max_concurrent_per_key,
+            )?,
+            ai_queue_wait_ms: parse_optional("AI_QUEUE_WAIT_MS")?,
+            stt_large_file_bytes: parse_or("STT_LARGE_FILE_BYTES", defaults.stt_large_file_bytes)?,
+            stt_max_attempts: parse_or("STT_MAX_ATTEMPTS", defaults.stt_max_attempts)?,
+            stt_max_attempts_large: parse_or(
+                "STT_MAX_ATTEMPTS_LARGE",
+                defaults.stt_max_attempts_large,
+            )?,
+            stt_min_answer_confidence: parse_optional("STT_MIN_ANSWER_CONFIDENCE")?,
+            stt_low_confidence_action: one_of(
+                "STT_LOW_CONFIDENCE_ACTION",
+                LOW_CONFIDENCE_ACTIONS,
+                defaults.stt_low_confidence_action,
+            )?,
+            audio_formats: audio_formats("AUDIO_FORMATS")?.unwrap_or(defaults.audio_formats),
+            audio_url_allow_private: parse_or(
+                "AUDIO_URL_ALLOW_PRIVATE",
+                defaults.audio_url_allow_private,
+            )?,
+            diarization_url: optional("DIARIZATION_URL"),
+            diarization_pause_secs: parse_or(
+                "DIARIZATION_PAUSE_SECS",
+                defaults.diarization_pause_secs,
+            )?,
+            diarization_max_speakers: parse_or(
+                "DIARIZATION_MAX_SPEAKERS",
+                defaults.diarization_max_speakers,
+            )?,
+            session_metadata_schema: json_schema("SESSION_METADATA_SCHEMA")?,
+            session_metadata_max_bytes: parse_or(
+                "SESSION_METADATA_MAX_BYTES",
+                defaults.session_metadata_max_bytes,
+            )?,
+            session_metadata_max_depth: parse_or(
+                "SESSION_METADATA_MAX_DEPTH",
+                defaults.session_metadata_max_depth,
+            )?,
+            max_message_chars: parse_or("MAX_MESSAGE_CHARS", defaults.max_message_chars)?,
+            message_too_long_action: one_of(
+                "MESSAGE_TOO_LONG_ACTION",
+                MESSAGE_TOO_LONG_ACTIONS,
+                defaults.message_too_long_action,
+            )?,
+            max_audio_duration_secs: parse_optional("MAX_AUDIO_DURATION_SECS")?,
+            analyze_cache_ttl_secs: parse_optional("ANALYZE_CACHE_TTL_SECS")?,
+            analyze_cache_max_chars: parse_or(
+                "ANALYZE_CACHE_MAX_CHARS",
+                defaults.analyze_cache_max_chars,
+            )?,
+            session_stale_ttl_secs: parse_optional("SESSION_STALE_TTL_SECS")?,
+            transcription_dedupe_window_secs: parse_or(
+                "TRANSCRIPTION_DEDUPE_WINDOW_SECS",
+                defaults.transcription_dedupe_window_secs,
+            )?,
+            max_sessions_per_key: parse_optional("MAX_SESSIONS_PER_KEY")?,
+            ai_completion_retention_days: parse_optional("AI_COMPLETION_RETENTION_DAYS")?,
+            benchmark_model_timeout_ms: parse_or(
+                "BENCHMARK_MODEL_TIMEOUT_MS",
+                defaults.benchmark_model_timeout_ms,
+            )?,
+            benchmark_deadline_ms: parse_or("BENCHMARK_DEADLINE_MS", defaults.benchmark_deadline_ms)?,
+            auto_title: parse_or("AUTO_TITLE", defaults.auto_title)?,
+            auto_title_min_words: parse_or("AUTO_TITLE_MIN_WORDS", defaults.auto_title_min_words)?,
+            auto_title_prompt: string_or("AUTO_TITLE_PROMPT", defaults.auto_title_prompt),
+            prompt_templates: prompt_templates("PROMPT_TEMPLATES")?,
+            prompt_template_strict: parse_or("PROMPT_TEMPLATE_STRICT", defaults.prompt_template_strict)?,
+            type_temperatures: type_temperatures("TYPE_TEMPERATURES", defaults.type_temperatures)?,
+            split_think_blocks: parse_or("SPLIT_THINK_BLOCKS", defaults.split_think_blocks)?,
+            slow_request_ms: parse_optional("SLOW_REQUEST_MS")?,
+            persistence: parse_or("PERSISTENCE", defaults.persistence)?,
+            client_backend: None,
+        })
+    }
+
+    /// Settings for a provider, if its API key is configured
+    pub fn provider(&self, name: &str) -> Option<&ProviderConfig> {
+        self.providers.get(name)
+    }
+
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+fn providers() -> BTreeMap<&'static str, ProviderConfig> {
+    PROVIDERS
+        .iter()
+        .filter_map(|p| {
+            let api_key = optional(p.api_key_var)?;
+            Some((
+                p.name,
+                ProviderConfig {
+                    name: p.name,
+                    base_url: string_or(p.base_url_var, p.base_url.to_string()),
+                    api_key,
+                    default_model: string_or(p.default_model_var, p.default_model.to_string()),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Empty values are treated as unset
+fn optional(var: &'static str) -> Option<String> {
+    env::var(var).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn required(var: &'static str) -> Result<String, ConfigError> {
+    optional(var).ok_or(ConfigError::Missing(var))
+}
+
+fn string_or(var: &'static str, default: String) -> String {
+    optional(var).unwrap_or(default)
+}
+
+/// Comma-separated values, ignoring blanks
+fn list(var: &'static str) -> Vec<String> {
+    optional(var)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Like `list`, but each origin must be usable as a header value
+fn cors_origins(var: &'static str) -> Result<Vec<String>, ConfigError> {
+    let origins = list(var);
+    match origins.iter().find(|o| HeaderValue::from_str(o).is_err()) {
+        Some(value) => Err(ConfigError::Invalid { var, value: value.clone() }),
+        None => Ok(origins),
+    }
+}
+
+/// `var` if it's one of `allowed`, `default` if unset
+fn one_of(var: &'static str, allowed: &[&str], default: String) -> Result<String, ConfigError> {
+    match optional(var) {
+        Some(value) if allowed.contains(&value.as_str()) => Ok(value),
+        Some(value) => Err(ConfigError::Invalid { var, value }),
+        None => Ok(default),
+    }
+}
+
+/// `ext=mime/type` pairs, e.g. `mp3=audio/mpeg,aac=audio/aac`. Extensions
+/// are matched case-insensitively, without the dot.
+fn audio_formats(var: &'static str) -> Result<Option<Vec<(String, String)>>, ConfigError> {
+    let Some(value) = optional(var) else {
+        return Ok(None);
+    };
+
+    let formats = list(var)
+        .iter()
+        .map(|entry| {
+            let (ext, mime) = entry.split_once('=')?;
+            let ext = ext.trim().trim_start_matches('.').to_lowercase();
+            let mime = mime.trim();
+            (!ext.is_empty() && mime.contains('/')).then(|| (ext, mime.to_string()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|formats| !formats.is_empty())
+        .ok_or(ConfigError::Invalid { var, value })?;
+
+    Ok(Some(formats))
+}
+
+/// `type=temperature` pairs, e.g. `meeting=0.8,coding=0`, each replacing
+/// that type's default. Temperatures must be between 0 and 2.
+fn type_temperatures(
+    var: &'static str,
+    mut temperatures: BTreeMap<String, f32>,
+) -> Result<BTreeMap<String, f32>, ConfigError> {
+    for entry in list(var) {
+        let parsed = entry.split_once('=').and_then(|(kind, temperature)| {
+            let temperature: f32 = temperature.trim().parse().ok()?;
+            let kind = kind.trim();
+            (!kind.is_empty() && (0.0..=2.0).contains(&temperature)).then_some((kind, temperature))
+        });
+        let Some((kind, temperature)) = parsed else {
+            return Err(ConfigError::Invalid { var, value: entry });
+        };
+        temperatures.insert(kind.to_string(), temperature);
+    }
+
+    Ok(temperatures)
+}
+
+/// Compile the JSON Schema file whose path is in `var`
+fn json_schema(var: &'static str) -> Result<Option<Arc<jsonschema::Validator>>, ConfigError> {
+    let Some(path) = optional(var) else {
+        return Ok(None);
+    };
+
+    let schema = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .and_then(|schema| jsonschema::validator_for(&schema).ok())
+        .ok_or(ConfigError::Invalid { var, value: path })?;
+
+    Ok(Some(Arc::new(schema)))
+}
+
+/// Templates from a JSON file like `{"leetcode": "Solve this {language} problem: {prompt}"}`
+fn prompt_templates(var: &'static str) -> Result<BTreeMap<String, String>, ConfigError> {
+    let Some(path) = optional(var) else {
+        return Ok(BTreeMap::new());
+    };
+
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .ok_or(ConfigError::Invalid { var, value: path })
+}
+
+fn parse_optional<T: FromStr>(var: &'static str) -> Result<Option<T>, ConfigError> {
+    optional(var)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::Invalid { var, value })
+        })
+        .transpose()
+}
+
+fn parse_or<T: FromStr>(var: &'static str, default: T) -> Result<T, ConfigError> {
+    Ok(parse_optional(var)?.unwrap_or(default))
+}
@@ -0,0 +1,182 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::services::llm::LlmError;
+use crate::services::prompts::TemplateError;
+use crate::services::stt::SttError;
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct ErrorResponse {
+    pub message: String,
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// Error type shared by all handlers. Serializes as `{ message, code, fields? }`.
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub body: ErrorResponse,
+    /// Sent as `Retry-After` (seconds)
+    pub retry_after: Option<u64>,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorResponse {
+                message: message.into(),
+                code,
+                fields: None,
+            },
+            retry_after: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn invalid_id() -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_id", "Invalid ID format")
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+}
+
+/// Router fallback for a known path requested with an unsupported method.
+/// axum still adds the `Allow` header.
+pub async fn method_not_allowed() -> AppError {
+    AppError::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "method_not_allowed",
+        "Method not allowed for this route",
+    )
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(self.body)).into_response();
+        if let Some(seconds) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
+
+/// Flatten (possibly nested) validation errors into `path -> messages`,
+/// e.g. `messages[0].role`
+fn collect_field_errors(
+    errors: &ValidationErrors,
+    prefix: &str,
+    fields: &mut BTreeMap<String, Vec<String>>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errs) => {
+                let messages = errs.iter().map(|e| {
+                    e.message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| e.code.to_string())
+                });
+                fields.entry(path).or_default().extend(messages);
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &path, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}]", path, index), fields);
+                }
+            }
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = BTreeMap::new();
+        collect_field_errors(&errors, "", &mut fields);
+
+        let mut error = Self::new(StatusCode::BAD_REQUEST, "validation_failed", "Validation failed");
+        error.body.fields = Some(fields);
+        error
+    }
+}
+
+impl From<mongodb::error::Error> for AppError {
+    fn from(e: mongodb::error::Error) -> Self {
+        Self::internal(e.to_string())
+    }
+}
+
+impl From<LlmError> for AppError {
+    fn from(e: LlmError) -> Self {
+        match e {
+            LlmError::InvalidResponse(_) => {
+                Self::new(StatusCode::BAD_GATEWAY, "invalid_provider_response", e.to_string())
+            }
+            LlmError::ContextLengthExceeded { .. } => {
+                Self::new(StatusCode::PAYLOAD_TOO_LARGE, "context_length_exceeded", e.to_string())
+            }
+            _ => Self::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<TemplateError> for AppError {
+    fn from(e: TemplateError) -> Self {
+        let code = match e {
+            TemplateError::UnknownTemplate(_) => "unknown_template",
+            TemplateError::MissingVariable(_) => "missing_template_variable",
+        };
+        Self::new(StatusCode::BAD_REQUEST, code, e.to_string())
+    }
+}
+
+impl From<SttError> for AppError {
+    fn from(e: SttError) -> Self {
+        match e {
+            SttError::InvalidResponse(_) => {
+                Self::new(StatusCode::BAD_GATEWAY, "invalid_provider_response", e.to_string())
+            }
+            SttError::DownloadFailed(_) => {
+                Self::new(StatusCode::BAD_REQUEST, "download_failed", e.to_string())
+            }
+            SttError::AudioTooLong { .. } => {
+                Self::new(StatusCode::PAYLOAD_TOO_LARGE, "audio_too_long", e.to_string())
+            }
+            SttError::ModelUnavailable { .. } => {
+                Self::new(StatusCode::SERVICE_UNAVAILABLE, "stt_model_unavailable", e.to_string())
+            }
+            _ => Self::internal(e.to_string()),
+        }
+    }
+}
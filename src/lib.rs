@@ -1,12 +1,51 @@
+use axum::extract::FromRef;
 use mongodb::Database;
 use redis::aio::ConnectionManager;
+use std::sync::Arc;
 
+use crate::services::discovery::Discovery;
+use crate::services::limiter::ConcurrencyLimiter;
+
+pub mod auth;
 pub mod config;
+pub mod error;
 pub mod modules;
+pub mod pagination;
+pub mod request_log;
+pub mod router;
 pub mod services;
+pub mod sse;
+pub mod timing;
+pub mod util;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Database,
     pub redis: ConnectionManager,
+    pub config: Arc<config::Config>,
+    /// Concurrent transcriptions per session
+    pub stt_slots: Arc<ConcurrencyLimiter>,
+    /// Concurrent LLM requests per API key
+    pub ai_slots: Arc<ConcurrencyLimiter>,
+    /// Memoized model list and audio formats
+    pub discovery: Arc<Discovery>,
+}
+
+impl AppState {
+    pub fn new(db: Database, redis: ConnectionManager, config: config::Config) -> Self {
+        Self {
+            db,
+            redis,
+            stt_slots: Arc::new(ConcurrencyLimiter::new(config.stt_max_concurrent_per_session)),
+            ai_slots: Arc::new(ConcurrencyLimiter::new(config.ai_max_concurrent_per_key)),
+            discovery: Arc::new(Discovery::new()),
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<config::Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
 }
@@ -1,7 +1,5 @@
-use axum::Router;
-use cleuly::{config, modules, AppState};
+use cleuly::{config, modules, router, services, AppState};
 use std::env;
-use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -15,27 +13,35 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let db = config::database::connect().await;
-    let redis = config::redis::connect().await;
+    let config = config::Config::from_env().expect("Invalid configuration");
 
-    let state = AppState { db, redis };
+    let db = config::database::connect(&config).await;
+    let redis = config::redis::connect(&config).await;
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    if let Err(e) = modules::session::crud::SessionCrud::new(&db, redis.clone())
+        .ensure_indexes()
+        .await
+    {
+        tracing::warn!("Failed to create session indexes: {}", e);
+    }
 
-    let app = Router::new()
-        .merge(modules::transcription::routes::routes())
-        .merge(modules::ai::routes::routes())
-        .merge(modules::session::routes::routes())
-        .merge(modules::stt::routes::routes())
-        .layer(cors)
-        .with_state(state);
+    if let Err(e) = modules::session::crud::MessageEmbeddingCrud::new(&db)
+        .ensure_indexes()
+        .await
+    {
+        tracing::warn!("Failed to create message embedding indexes: {}", e);
+    }
 
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let addr = format!("{}:{}", host, port);
+    if let Some(days) = config.ai_completion_retention_days {
+        services::retention::spawn(db.clone(), days);
+    }
+
+    if config.warmup {
+        services::warmup::run(&config).await;
+    }
+
+    let addr = config.addr();
+    let app = router::build_router(AppState::new(db, redis, config.clone()), &config);
 
     tracing::info!("Server running on http://{}", addr);
 
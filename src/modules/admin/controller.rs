@@ -0,0 +1,180 @@
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAliveStream, Sse},
+    Json,
+};
+use bson::oid::ObjectId;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use std::convert::Infallible;
+use validator::Validate;
+
+use crate::auth::ApiKey;
+use crate::error::AppError;
+use crate::modules::admin::schema::{PurgeRequest, PurgeResponse, ReembedProgress, ReembedQuery};
+use crate::modules::ai::crud::AiCrud;
+use crate::modules::session::controller::index_embeddings;
+use crate::modules::session::crud::{MessageEmbeddingCrud, SessionCrud, SessionEmbeddingCrud};
+use crate::modules::stt::crud::SttCrud;
+use crate::modules::transcription::crud::TranscriptionCrud;
+use crate::services::llm::LlmClient;
+use crate::sse::sse_response;
+use crate::AppState;
+
+/// Collections that may be purged through the admin API
+const PURGEABLE_COLLECTIONS: &[&str] = &[
+    "sessions",
+    "ai_completions",
+    "stt_transcriptions",
+    "transcriptions",
+];
+
+/// Delete all documents in a collection. Uses `delete_many` rather than
+/// `drop` so indexes survive the purge.
+pub async fn purge(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Json(payload): Json<PurgeRequest>,
+) -> Result<Json<PurgeResponse>, AppError> {
+    api_key.require_admin(&state.config)?;
+    payload.validate()?;
+
+    if payload.confirm != "YES" {
+        return Err(AppError::bad_request("Purge must be confirmed with confirm: \"YES\""));
+    }
+
+    let deleted = match payload.collection.as_str() {
+        "sessions" => {
+            // Embeddings are meaningless without their sessions
+            MessageEmbeddingCrud::new(&state.db).delete_all().await?;
+            SessionEmbeddingCrud::new(&state.db).delete_all().await?;
+            SessionCrud::new(&state.db, state.redis.clone()).delete_all().await?
+        }
+        "ai_completions" => AiCrud::new(&state.db).delete_all().await?,
+        "stt_transcriptions" => SttCrud::new(&state.db).delete_all().await?,
+        "transcriptions" => TranscriptionCrud::new(&state.db).delete_all().await?,
+        _ => {
+            return Err(AppError::bad_request(format!(
+                "Unknown collection. Allowed: {:?}",
+                PURGEABLE_COLLECTIONS
+            )))
+        }
+    };
+
+    tracing::warn!("Purged {} documents from {}", deleted, payload.collection);
+
+    Ok(Json(PurgeResponse {
+        collection: payload.collection,
+        deleted,
+    }))
+}
+
+/// Collections whose vectors can be recomputed through the admin API
+const REEMBED_COLLECTIONS: &[&str] = &["sessions", "messages"];
+
+/// Sessions re-indexed at once; each already embeds its messages in batches
+const REEMBED_CONCURRENCY: usize = 4;
+
+type EventSender = UnboundedSender<Result<Event, Infallible>>;
+
+/// Recompute stored vectors with the current `EMBEDDING_MODEL` after it
+/// changes. Every session with a vector from another model in `collection`
+/// is re-indexed whole, since a session's vector is the mean of its message
+/// vectors. Sessions already on the current model are skipped, so the job
+/// can be re-run after an interruption. Streams a `progress` event per
+/// session, then `done` (or `error`).
+pub async fn reembed(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<ReembedQuery>,
+) -> Result<Sse<KeepAliveStream<UnboundedReceiver<Result<Event, Infallible>>>>, AppError> {
+    api_key.require_admin(&state.config)?;
+    LlmClient::new(&state.config)?;
+
+    let model = state.config.embedding_model.clone();
+    let ids: BoxStream<'static, Result<ObjectId, mongodb::error::Error>> =
+        match query.collection.as_str() {
+            "sessions" => SessionEmbeddingCrud::new(&state.db)
+                .stale_session_ids(&model)
+                .await?
+                .boxed(),
+            "messages" => MessageEmbeddingCrud::new(&state.db)
+                .stale_session_ids(&model)
+                .await?
+                .boxed(),
+            _ => {
+                return Err(AppError::bad_request(format!(
+                    "Unknown collection. Allowed: {:?}",
+                    REEMBED_COLLECTIONS
+                )))
+            }
+        };
+
+    let progress = ReembedProgress {
+        collection: query.collection,
+        model,
+        processed: 0,
+        failed: 0,
+    };
+    let (tx, rx) = mpsc::unbounded();
+
+    // Runs to the end even if the client disconnects
+    tokio::spawn(async move {
+        let event = match reembed_sessions(&state, ids, progress, &tx).await {
+            Ok(progress) => {
+                tracing::info!(
+                    "Re-embedded {} sessions from {} ({} failed)",
+                    progress.processed,
+                    progress.collection,
+                    progress.failed
+                );
+                Event::default().event("done").json_data(progress)
+            }
+            Err(e) => Event::default().event("error").json_data(e.body),
+        };
+        let _ = tx.unbounded_send(Ok(event.unwrap_or_default()));
+    });
+
+    Ok(sse_response(rx))
+}
+
+async fn reembed_sessions(
+    state: &AppState,
+    ids: BoxStream<'static, Result<ObjectId, mongodb::error::Error>>,
+    mut progress: ReembedProgress,
+    tx: &EventSender,
+) -> Result<ReembedProgress, AppError> {
+    let mut results = ids
+        .map_ok(|id| async move { Ok((id, reembed_session(state, id).await)) })
+        .try_buffer_unordered(REEMBED_CONCURRENCY);
+
+    while let Some((id, result)) = results.try_next().await? {
+        match result {
+            Ok(()) => progress.processed += 1,
+            Err(e) => {
+                tracing::warn!("Failed to re-embed session {}: {}", id, e.body.message);
+                progress.failed += 1;
+            }
+        }
+        let event = Event::default().event("progress").json_data(&progress);
+        let _ = tx.unbounded_send(Ok(event.unwrap_or_default()));
+    }
+
+    Ok(progress)
+}
+
+async fn reembed_session(state: &AppState, id: ObjectId) -> Result<(), AppError> {
+    let session = SessionCrud::new(&state.db, state.redis.clone()).find_by_id(&id).await?;
+    match session {
+        Some(session) => {
+            index_embeddings(state, id, &session).await?;
+        }
+        // Vectors left behind by a deleted session
+        None => {
+            MessageEmbeddingCrud::new(&state.db).delete_by_session(&id).await?;
+            SessionEmbeddingCrud::new(&state.db).delete_by_session(&id).await?;
+        }
+    }
+    Ok(())
+}
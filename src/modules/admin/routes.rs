@@ -0,0 +1,10 @@
+use axum::{routing::post, Router};
+
+use crate::modules::admin::controller;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/purge", post(controller::purge))
+        .route("/api/admin/reembed", post(controller::reembed))
+}
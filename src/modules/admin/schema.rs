@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PurgeRequest {
+    #[validate(length(min = 1, message = "Collection cannot be empty"))]
+    pub collection: String,
+    pub confirm: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct PurgeResponse {
+    pub collection: String,
+    pub deleted: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReembedQuery {
+    /// `sessions` or `messages`: whose stale vectors select the sessions to re-index
+    pub collection: String,
+}
+
+/// Payload of the `progress` and final `done` SSE events of a re-embed
+#[derive(Debug, Serialize, Clone)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct ReembedProgress {
+    pub collection: String,
+    /// The embedding model vectors are recomputed with
+    pub model: String,
+    /// Sessions re-indexed, or whose orphaned vectors were removed
+    pub processed: u64,
+    /// Sessions that could not be re-embedded; a re-run retries them
+    pub failed: u64,
+}
@@ -1,174 +1,742 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAliveStream, Sse},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bson::oid::ObjectId;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::{self, FuturesUnordered, Stream, StreamExt};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use validator::Validate;
 
+use crate::auth::{ApiKey, EndUser};
+use crate::config::database::{self, number};
+use crate::config::Config;
+use crate::error::AppError;
 use crate::modules::ai::{
-    crud::AiCrud,
-    model::AiCompletion,
+    crud::{AiCrud, BenchmarkCrud},
+    model::{AiCompletion, Benchmark, BenchmarkResult},
     schema::{
-        AiModel, AiResponse, AnalyzeRequest, CompleteRequest, MessageResponse,
-        ModelInfo, ModelsResponse, SuggestRequest,
+        AiModel, AiResponse, AnalyzeBatchItem, AnalyzeBatchRequest, AnalyzeBatchResponse,
+        AnalyzeRequest, BenchmarkHistoryPoint, BenchmarkHistoryQuery, BenchmarkHistoryResponse,
+        BenchmarkModelResult, BenchmarkRequest, BenchmarkResponse, ChatCompletionRequest,
+        ChatCompletionResponse, ChatTurn, CompleteBatchItem, CompleteBatchRequest,
+        CompleteBatchResponse, CompleteRequest, CompletionContentResponse,
+        DisplayQuery, ModelInfo, ModelsQuery, ModelsResponse, QuotaResponse, RankedCandidate,
+        ReplayQuery, ReplayResponse, RerankRequest, RerankResponse, StatsQuery, StatsResponse,
+        SuggestRequest, TokenEstimateRequest, TokenEstimateResponse, UsageInfo,
+        DEFAULT_BENCHMARK_PROMPT,
     },
 };
-use crate::services::llm::LlmClient;
+use crate::modules::health::{controller::DEEP_CACHE_KEY, schema::DeepHealthResponse};
+use crate::modules::session::{
+    controller::{session_crud, spawn_reindex},
+    crud::SessionCrud,
+    model::Message,
+};
+use crate::services::llm::{
+    normalize_type, supports_reasoning, ChatMessage, LlmClient, LlmError, LlmProvider,
+    StreamChunk,
+};
+use crate::services::cache;
+use crate::services::discovery::ModelHealth;
+use crate::services::prompts;
+use crate::services::limiter::SlotGuard;
+use crate::services::quota::QuotaTracker;
+use crate::services::tokens;
+use crate::timing::ServerTiming;
+use crate::sse::sse_response;
 use crate::AppState;
 
-fn create_llm_client() -> Result<LlmClient, crate::services::llm::LlmError> {
+/// Completions scoped to the caller's own, see `ApiKey::owner_scope`
+fn ai_crud(state: &AppState, api_key: &ApiKey) -> AiCrud {
+    AiCrud::new(&state.db)
+        .with_owner(api_key.owner_scope(&state.config))
+        .with_persistence(state.config.persistence)
+}
+
+fn create_llm_client(
+    config: &Config,
+    end_user: &EndUser,
+) -> Result<LlmClient, LlmError> {
     // Try Groq first (faster), fall back to OpenRouter
-    LlmClient::new_groq().or_else(|_| LlmClient::new())
+    let llm = LlmClient::new_groq(config).or_else(|_| LlmClient::new(config))?;
+    Ok(llm.with_user(end_user.0.clone()))
+}
+
+/// Shorten `content` to at most `max_chars`, cutting at the last line break
+/// (or failing that, the last whitespace) so code and words stay intact.
+/// Returns the display content and whether it was truncated.
+pub fn truncate_for_display(content: &str, max_chars: Option<usize>) -> (String, bool) {
+    let max_chars = match max_chars {
+        Some(max) if content.chars().count() > max => max,
+        _ => return (content.to_string(), false),
+    };
+
+    let byte_limit = content
+        .char_indices()
+        .nth(max_chars)
+        .map(|(i, _)| i)
+        .unwrap_or(content.len());
+    let head = &content[..byte_limit];
+
+    let cut = head
+        .rfind('\n')
+        .or_else(|| head.rfind(char::is_whitespace))
+        .filter(|&i| i > 0)
+        .unwrap_or(byte_limit);
+
+    (head[..cut].trim_end().to_string(), true)
+}
+
+/// `text` (the request's `field`) rendered through the named prompt
+/// template, or unchanged without one. The template sees `text` as
+/// `{field}` alongside the request's own variables.
+fn apply_template(
+    config: &Config,
+    template: Option<&str>,
+    variables: Option<&BTreeMap<String, String>>,
+    field: &str,
+    text: &str,
+) -> Result<String, AppError> {
+    let Some(name) = template else {
+        return Ok(text.to_string());
+    };
+
+    let mut variables = variables.cloned().unwrap_or_default();
+    variables.entry(field.to_string()).or_insert_with(|| text.to_string());
+    Ok(prompts::render_named(&config.prompt_templates, name, &variables, config.prompt_template_strict)?)
+}
+
+/// Seconds a client is told to wait when its key has no free AI slot
+const AI_RETRY_AFTER_SECS: u64 = 2;
+
+/// Reserve one of the API key's concurrent LLM request slots, freed when
+/// the guard drops.
+///
+/// With `AI_QUEUE_WAIT_MS` set, a busy key's request waits that long for a
+/// slot and then fails with a 503; otherwise it's rejected at once with a
+/// 429.
+async fn acquire_ai_slot(state: &AppState, key_id: &str) -> Result<SlotGuard, AppError> {
+    if let Some(wait_ms) = state.config.ai_queue_wait_ms {
+        return state
+            .ai_slots
+            .acquire(key_id, Duration::from_millis(wait_ms))
+            .await
+            .ok_or_else(|| {
+                AppError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "queue_timeout",
+                    format!("No AI request slot for this key freed up within {}ms", wait_ms),
+                )
+                .with_retry_after(AI_RETRY_AFTER_SECS)
+            });
+    }
+
+    state.ai_slots.try_acquire(key_id).ok_or_else(|| {
+        AppError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "too_many_ai_requests",
+            format!(
+                "At most {} AI requests may run at once for a key",
+                state.config.ai_max_concurrent_per_key
+            ),
+        )
+        .with_retry_after(AI_RETRY_AFTER_SECS)
+    })
+}
+
+async fn ensure_quota(quota: &QuotaTracker, key_id: &str) -> Result<(), AppError> {
+    let status = quota.status(key_id).await;
+    if status.exceeded() {
+        return Err(AppError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "quota_exceeded",
+            format!(
+                "Token quota exceeded. Resets at {}",
+                status.resets_at.to_rfc3339()
+            ),
+        ));
+    }
+    Ok(())
 }
 
 pub async fn complete(
     State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Query(display): Query<DisplayQuery>,
     Json(payload): Json<CompleteRequest>,
-) -> Result<Json<AiResponse>, (StatusCode, Json<MessageResponse>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: e.to_string() }),
-        ));
-    }
+) -> Result<(ServerTiming, Json<AiResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
 
-    let llm = create_llm_client().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let prompt = apply_template(
+        &state.config,
+        payload.template.as_deref(),
+        payload.variables.as_ref(),
+        "prompt",
+        &payload.prompt,
+    )?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
 
     let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
 
-    let result = llm
-        .complete(
-            &payload.prompt,
-            &model,
-            payload.system_prompt.as_deref(),
-            payload.max_tokens,
-            payload.temperature,
+    let result = timing
+        .measure(
+            "llm",
+            llm.complete_with_reasoning(
+                &prompt,
+                &model,
+                payload.system_prompt.as_deref(),
+                payload.max_tokens,
+                payload.temperature,
+                payload.reasoning_effort.as_deref(),
+            ),
         )
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+        .await?;
+
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
 
     // Store in database
-    let crud = AiCrud::new(&state.db);
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
     let completion = AiCompletion::new(
-        payload.prompt,
+        prompt,
         payload.system_prompt,
         model.clone(),
         result.content.clone(),
         result.usage.clone(),
         "complete".to_string(),
-    );
+    )
+    .with_owner(key_id.clone())
+    .with_finish_reason(result.finish_reason.clone());
 
-    let id = crud.create(completion.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let id = timing.measure("db", crud.create(completion.clone())).await?;
 
-    Ok(Json(AiResponse {
+    let (content, truncated) = truncate_for_display(&result.content, display.max_display_chars);
+
+    Ok((timing, Json(AiResponse {
         id: id.to_hex(),
         model,
-        content: result.content,
+        content,
+        truncated,
+        billed_tokens: result.usage.as_ref().map(|u| u.total_tokens),
         usage: result.usage,
         created_at: completion.created_at.to_rfc3339(),
-    }))
+        finish_reason: completion.finish_reason,
+        cached: false,
+        reasoning: result.reasoning,
+    })))
+}
+
+/// Complete several prompts sharing a system prompt and settings. Prompts
+/// fail individually; the batch only fails on validation, quota or a
+/// missing provider.
+pub async fn complete_batch(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<CompleteBatchRequest>,
+) -> Result<Json<CompleteBatchResponse>, AppError> {
+    payload.validate()?;
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+    let outcomes = llm
+        .complete_many(
+            &payload.prompts,
+            &model,
+            payload.system_prompt.as_deref(),
+            payload.max_tokens,
+            payload.temperature,
+        )
+        .await;
+
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
+    let mut results = Vec::with_capacity(outcomes.len());
+    for (prompt, outcome) in payload.prompts.into_iter().zip(outcomes) {
+        let result = match outcome {
+            Ok(result) => result,
+            Err(e) => {
+                results.push(CompleteBatchItem {
+                    prompt,
+                    id: None,
+                    content: None,
+                    usage: None,
+                    finish_reason: None,
+                    error: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+
+        if let Some(usage) = &result.usage {
+            quota.record(&key_id, usage.total_tokens).await;
+        }
+
+        let completion = AiCompletion::new(
+            prompt.clone(),
+            payload.system_prompt.clone(),
+            model.clone(),
+            result.content.clone(),
+            result.usage.clone(),
+            "complete".to_string(),
+        )
+        .with_owner(key_id.clone())
+        .with_finish_reason(result.finish_reason.clone());
+
+        // The completion succeeded, so a failed insert is logged rather than
+        // turning the item into an error
+        let id = match crud.create(completion).await {
+            Ok(id) => Some(id.to_hex()),
+            Err(e) => {
+                tracing::error!("Failed to store batch completion: {}", e);
+                None
+            }
+        };
+
+        results.push(CompleteBatchItem {
+            prompt,
+            id,
+            content: Some(result.content),
+            usage: result.usage,
+            finish_reason: result.finish_reason,
+            error: None,
+        });
+    }
+
+    Ok(Json(CompleteBatchResponse { model, results }))
+}
+
+type EventStream = UnboundedReceiver<Result<Event, Infallible>>;
+
+/// Streaming variant of `complete`. Emits `delta` events as text arrives,
+/// a `usage` event when the provider reports it, then `done` with the id of
+/// the stored completion (or `error`).
+pub async fn complete_stream(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<CompleteRequest>,
+) -> Result<Sse<KeepAliveStream<EventStream>>, AppError> {
+    payload.validate()?;
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+
+    let mut messages = Vec::new();
+    if let Some(system_prompt) = &payload.system_prompt {
+        messages.push(ChatMessage::new("system", system_prompt));
+    }
+    messages.push(ChatMessage::new("user", &payload.prompt));
+    let prompt_tokens = messages.iter().map(|m| tokens::count_tokens(&m.content, &model)).sum();
+
+    // Errors before the first byte are still plain HTTP errors
+    let chunks = Box::pin(
+        llm.complete_stream(
+            messages,
+            &model,
+            payload.max_tokens,
+            payload.temperature,
+            payload.reasoning_effort.as_deref(),
+        )
+        .await?,
+    );
+
+    let (tx, rx) = mpsc::unbounded();
+
+    tokio::spawn(async move {
+        // Held until the stream ends, not just until the response starts
+        let _slot = slot;
+        let forwarded = forward_chunks(chunks, &tx, &quota, &key_id, &model, prompt_tokens).await;
+        let Some((content, usage)) = forwarded else {
+            return;
+        };
+
+        let completion = AiCompletion::new(
+            payload.prompt,
+            payload.system_prompt,
+            model.clone(),
+            content,
+            usage,
+            "complete".to_string(),
+        )
+        .with_owner(key_id.clone());
+
+        let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
+        let event = match crud.create(completion).await {
+            Ok(id) => Event::default()
+                .event("done")
+                .json_data(serde_json::json!({ "id": id.to_hex(), "model": model }))
+                .unwrap_or_default(),
+            Err(e) => stream_error_event(AppError::from(e)),
+        };
+        let _ = tx.unbounded_send(Ok(event));
+    });
+
+    Ok(sse_response(rx))
+}
+
+/// Send `chunks` on as `delta` and `usage` events, charging the reported
+/// usage to `key_id`. Returns the full content and usage once the provider
+/// is done, or `None` when it failed (after an `error` event) or the client
+/// went away. Cut short before usage is reported, `prompt_tokens` plus the
+/// tokens of the content so far are charged instead.
+async fn forward_chunks(
+    mut chunks: impl Stream<Item = Result<StreamChunk, LlmError>> + Unpin,
+    tx: &UnboundedSender<Result<Event, Infallible>>,
+    quota: &QuotaTracker,
+    key_id: &str,
+    model: &str,
+    prompt_tokens: usize,
+) -> Option<(String, Option<UsageInfo>)> {
+    let mut content = String::new();
+    let mut usage = None;
+    let mut completed = true;
+
+    while let Some(chunk) = chunks.next().await {
+        let event = match chunk {
+            Ok(StreamChunk::Delta(delta)) => {
+                content.push_str(&delta);
+                Event::default()
+                    .event("delta")
+                    .json_data(serde_json::json!({ "content": delta }))
+            }
+            Ok(StreamChunk::Usage(u)) => {
+                let event = Event::default().event("usage").json_data(&u);
+                usage = Some(u);
+                event
+            }
+            Ok(StreamChunk::Done) => break,
+            Err(e) => {
+                let _ = tx.unbounded_send(Ok(stream_error_event(AppError::from(e))));
+                completed = false;
+                break;
+            }
+        };
+
+        // Client went away; dropping the stream cancels the provider request
+        if tx.unbounded_send(Ok(event.unwrap_or_default())).is_err() {
+            completed = false;
+            break;
+        }
+    }
+
+    let tokens = match &usage {
+        Some(u) => u.total_tokens,
+        None if !completed => (prompt_tokens + tokens::count_tokens(&content, model)) as u32,
+        None => 0,
+    };
+    quota.record(key_id, tokens).await;
+
+    completed.then_some((content, usage))
+}
+
+fn stream_error_event(error: AppError) -> Event {
+    Event::default()
+        .event("error")
+        .json_data(error.body)
+        .unwrap_or_default()
+}
+
+/// The context a suggestion is made from: the (templated) request context,
+/// after the session's recent messages if `session_id` is given. Also
+/// returns the session, for saving the suggestion to it.
+async fn suggest_context(
+    state: &AppState,
+    api_key: &ApiKey,
+    payload: &SuggestRequest,
+    timing: &mut ServerTiming,
+) -> Result<(String, Option<(ObjectId, SessionCrud)>), AppError> {
+    let request_context = apply_template(
+        &state.config,
+        payload.template.as_deref(),
+        payload.variables.as_ref(),
+        "context",
+        &payload.context,
+    )?;
+
+    // Prepend recent session messages so the suggestion sees the live transcript
+    let session = match &payload.session_id {
+        Some(session_id) => {
+            let oid = ObjectId::parse_str(session_id).map_err(|_| AppError::invalid_id())?;
+            let session_crud = session_crud(state, api_key);
+            let session = timing
+                .measure("db", session_crud.find_by_id(&oid))
+                .await?
+                .ok_or_else(|| AppError::not_found("Session not found"))?;
+            Some((oid, session_crud, session))
+        }
+        None => None,
+    };
+
+    let context = match &session {
+        Some((_, _, session)) => {
+            let history = session
+                .get_context_messages(payload.context_messages.unwrap_or(10))
+                .iter()
+                .map(|m| format!("{}: {}", m.role, m.content))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            if history.is_empty() {
+                request_context
+            } else {
+                format!("Recent conversation:\n{}\n\n{}", history, request_context)
+            }
+        }
+        None => request_context,
+    };
+
+    Ok((context, session.map(|(oid, session_crud, _)| (oid, session_crud))))
 }
 
 pub async fn suggest(
     State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Query(display): Query<DisplayQuery>,
     Json(payload): Json<SuggestRequest>,
-) -> Result<Json<AiResponse>, (StatusCode, Json<MessageResponse>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: e.to_string() }),
-        ));
-    }
+) -> Result<(ServerTiming, Json<AiResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let (context, session) = suggest_context(&state, &api_key, &payload, &mut timing).await?;
 
-    let llm = create_llm_client().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.clone().unwrap_or_else(|| llm.default_model().to_string());
+
+    let result = timing
+        .measure(
+            "llm",
+            llm.suggest(
+                &context,
+                &model,
+                payload.suggestion_type.as_deref(),
+                payload.language.as_deref().filter(|_| state.config.match_language),
+                payload.temperature,
+            ),
         )
-    })?;
+        .await?;
 
-    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+    if let Some((oid, session_crud)) = &session {
+        let message = Message::assistant(result.content.clone());
+        if timing.measure("db", session_crud.add_message(oid, message)).await? {
+            spawn_reindex(&state, *oid);
+        }
+    }
 
-    let result = llm
-        .suggest(&payload.context, &model, payload.suggestion_type.as_deref())
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
 
     // Store in database
-    let crud = AiCrud::new(&state.db);
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
     let completion = AiCompletion::new(
-        payload.context,
+        context,
         None,
         model.clone(),
         result.content.clone(),
         result.usage.clone(),
         "suggest".to_string(),
-    );
+    )
+    .with_owner(key_id.clone())
+    .with_finish_reason(result.finish_reason.clone());
 
-    let id = crud.create(completion.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let id = timing.measure("db", crud.create(completion.clone())).await?;
+
+    let (content, truncated) = truncate_for_display(&result.content, display.max_display_chars);
 
-    Ok(Json(AiResponse {
+    Ok((timing, Json(AiResponse {
         id: id.to_hex(),
         model,
-        content: result.content,
+        content,
+        truncated,
+        billed_tokens: result.usage.as_ref().map(|u| u.total_tokens),
         usage: result.usage,
         created_at: completion.created_at.to_rfc3339(),
-    }))
+        finish_reason: completion.finish_reason,
+        cached: false,
+        reasoning: result.reasoning,
+    })))
+}
+
+/// Streaming variant of `suggest` for live coaching: `delta` events as the
+/// suggestion is generated, `usage` when reported, then `done` with the id
+/// of the stored completion (or `error`). The suggestion is added to the
+/// session and stored only once it's complete.
+pub async fn suggest_stream(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<SuggestRequest>,
+) -> Result<Sse<KeepAliveStream<EventStream>>, AppError> {
+    payload.validate()?;
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let (context, session) =
+        suggest_context(&state, &api_key, &payload, &mut ServerTiming::new()).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.clone().unwrap_or_else(|| llm.default_model().to_string());
+    let prompt_tokens = tokens::count_tokens(&context, &model);
+
+    // Errors before the first byte are still plain HTTP errors
+    let chunks = Box::pin(
+        llm.suggest_stream(
+            &context,
+            &model,
+            payload.suggestion_type.as_deref(),
+            payload.language.as_deref().filter(|_| state.config.match_language),
+            payload.temperature,
+        )
+        .await?,
+    );
+
+    let (tx, rx) = mpsc::unbounded();
+
+    tokio::spawn(async move {
+        // Held until the stream ends, not just until the response starts
+        let _slot = slot;
+        let forwarded = forward_chunks(chunks, &tx, &quota, &key_id, &model, prompt_tokens).await;
+        let Some((content, usage)) = forwarded else {
+            return;
+        };
+
+        if let Some((oid, session_crud)) = &session {
+            let message = Message::assistant(content.clone());
+            match session_crud.add_message(oid, message).await {
+                Ok(true) => spawn_reindex(&state, *oid),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to save streamed suggestion to session {}: {}", oid, e)
+                }
+            }
+        }
+
+        let completion =
+            AiCompletion::new(context, None, model.clone(), content, usage, "suggest".to_string())
+                .with_owner(key_id.clone());
+
+        let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
+        let event = match crud.create(completion).await {
+            Ok(id) => Event::default()
+                .event("done")
+                .json_data(serde_json::json!({ "id": id.to_hex(), "model": model }))
+                .unwrap_or_default(),
+            Err(e) => stream_error_event(AppError::from(e)),
+        };
+        let _ = tx.unbounded_send(Ok(event));
+    });
+
+    Ok(sse_response(rx))
 }
 
 pub async fn analyze(
     State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Query(display): Query<DisplayQuery>,
     Json(payload): Json<AnalyzeRequest>,
-) -> Result<Json<AiResponse>, (StatusCode, Json<MessageResponse>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: e.to_string() }),
-        ));
-    }
+) -> Result<(ServerTiming, Json<AiResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
 
-    let llm = create_llm_client().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
 
     let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
 
-    let result = llm
-        .analyze(&payload.text, &model, payload.analysis_type.as_deref())
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+    // Analysis is low-temperature and short, so identical texts are worth
+    // caching. An explicit temperature asks for something else; skip the cache.
+    // Cached results hold the analysed text, so `PERSISTENCE=none` skips it too.
+    let cache_key = state
+        .config
+        .analyze_cache_ttl_secs
+        .filter(|_| state.config.persistence.stores())
+        .filter(|_| payload.temperature.is_none())
+        .filter(|_| payload.text.chars().count() <= state.config.analyze_cache_max_chars)
+        .map(|ttl| {
+            let analysis_type = payload.analysis_type.as_deref();
+            let key = analyze_cache_key(&key_id, analysis_type, &model, &payload.text);
+            (key, ttl)
+        });
+
+    if let Some((key, _)) = &cache_key {
+        let cached = timing.measure("cache", cache::get(&state.redis, key)).await;
+        let cached = cached.and_then(|c| serde_json::from_str::<AiResponse>(&c).ok());
+        if let Some(mut response) = cached {
+            let (content, truncated) =
+                truncate_for_display(&response.content, display.max_display_chars);
+            response.content = content;
+            response.truncated = truncated;
+            // Nothing was spent, so nothing is recorded against the quota
+            response.cached = true;
+            response.billed_tokens = Some(0);
+            return Ok((timing, Json(response)));
+        }
+    }
+
+    let result = timing
+        .measure("llm", llm.analyze(
+            &payload.text,
+            &model,
+            payload.analysis_type.as_deref(),
+            payload.temperature,
+        ))
+        .await?;
+
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
 
     // Store in database
-    let crud = AiCrud::new(&state.db);
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
     let completion = AiCompletion::new(
         payload.text,
         None,
@@ -176,38 +744,773 @@ pub async fn analyze(
         result.content.clone(),
         result.usage.clone(),
         "analyze".to_string(),
-    );
+    )
+    .with_owner(key_id.clone())
+    .with_finish_reason(result.finish_reason.clone());
 
-    let id = crud.create(completion.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let id = timing.measure("db", crud.create(completion.clone())).await?;
 
-    Ok(Json(AiResponse {
+    let mut response = AiResponse {
         id: id.to_hex(),
         model,
         content: result.content,
+        truncated: false,
+        billed_tokens: result.usage.as_ref().map(|u| u.total_tokens),
         usage: result.usage,
         created_at: completion.created_at.to_rfc3339(),
+        finish_reason: completion.finish_reason,
+        cached: false,
+        reasoning: result.reasoning,
+    };
+
+    if let Some((key, ttl)) = cache_key {
+        if let Ok(json) = serde_json::to_string(&response) {
+            timing.measure("cache", cache::set_ex(&state.redis, &key, json, ttl)).await;
+        }
+    }
+
+    let (content, truncated) = truncate_for_display(&response.content, display.max_display_chars);
+    response.content = content;
+    response.truncated = truncated;
+
+    Ok((timing, Json(response)))
+}
+
+/// Redis key for a cached analysis. The text is hashed so keys stay short.
+/// Keyed by `owner` (`ApiKey::id`) too, as the cached response names the
+/// owner's stored completion.
+pub fn analyze_cache_key(
+    owner: &str,
+    analysis_type: Option<&str>,
+    model: &str,
+    text: &str,
+) -> String {
+    format!(
+        "analyze:{}:{}:{}:{:x}",
+        owner,
+        normalize_type(analysis_type).unwrap_or("general"),
+        model,
+        Sha256::digest(text.as_bytes())
+    )
+}
+
+/// Analyses run concurrently per batch request
+const BATCH_CONCURRENCY: usize = 4;
+
+/// Score candidate answers against a query and return them best first
+pub async fn rerank(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<RerankRequest>,
+) -> Result<(ServerTiming, Json<RerankResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+
+    let (scores, result) = timing
+        .measure("llm", llm.rerank(&payload.query, &payload.candidates, &model))
+        .await?;
+
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
+
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
+    let completion = AiCompletion::new(
+        payload.query,
+        None,
+        model.clone(),
+        result.content,
+        result.usage.clone(),
+        "rerank".to_string(),
+    )
+    .with_owner(key_id.clone());
+
+    let id = timing.measure("db", crud.create(completion)).await?;
+
+    let mut results = payload
+        .candidates
+        .into_iter()
+        .zip(scores)
+        .enumerate()
+        .map(|(index, (text, score))| RankedCandidate { index, text, score })
+        .collect::<Vec<_>>();
+    // Stable, so equal scores keep the request order
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok((timing, Json(RerankResponse {
+        id: id.to_hex(),
+        model,
+        results,
+        usage: result.usage,
+    })))
+}
+
+/// Analyze many short texts. Items fail individually; the batch only fails
+/// on validation, quota or a missing provider.
+pub async fn analyze_batch(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<AnalyzeBatchRequest>,
+) -> Result<Json<AnalyzeBatchResponse>, AppError> {
+    payload.validate()?;
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+    let analysis_type = payload.analysis_type.as_deref();
+    let crud = AiCrud::new(&state.db).with_persistence(state.config.persistence);
+
+    let results = stream::iter(payload.texts)
+        .map(|text| {
+            let (llm, model, crud, quota, key_id) = (&llm, &model, &crud, &quota, &key_id);
+            async move {
+                if text.trim().is_empty() {
+                    return AnalyzeBatchItem {
+                        input: text,
+                        id: None,
+                        content: None,
+                        error: Some("Text cannot be empty".to_string()),
+                    };
+                }
+
+                let result = match llm.analyze(&text, model, analysis_type, None).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        return AnalyzeBatchItem {
+                            input: text,
+                            id: None,
+                            content: None,
+                            error: Some(e.to_string()),
+                        }
+                    }
+                };
+
+                if let Some(usage) = &result.usage {
+                    quota.record(key_id, usage.total_tokens).await;
+                }
+
+                let completion = AiCompletion::new(
+                    text.clone(),
+                    None,
+                    model.clone(),
+                    result.content.clone(),
+                    result.usage,
+                    "analyze".to_string(),
+                )
+                .with_owner(key_id.clone());
+
+                // The analysis succeeded, so a failed insert is logged rather than
+                // turning the item into an error
+                let id = match crud.create(completion).await {
+                    Ok(id) => Some(id.to_hex()),
+                    Err(e) => {
+                        tracing::error!("Failed to store batch analysis: {}", e);
+                        None
+                    }
+                };
+
+                AnalyzeBatchItem {
+                    input: text,
+                    id,
+                    content: Some(result.content),
+                    error: None,
+                }
+            }
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    Ok(Json(AnalyzeBatchResponse { model, results }))
+}
+
+/// Stateless multi-turn chat. The caller sends the whole conversation and
+/// nothing is persisted.
+pub async fn chat(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<ChatCompletionRequest>,
+) -> Result<(ServerTiming, Json<ChatCompletionResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+
+    let model = payload.model.unwrap_or_else(|| llm.default_model().to_string());
+
+    let messages = payload
+        .messages
+        .iter()
+        .map(|m| ChatMessage::new(&m.role, &m.content))
+        .collect();
+
+    let result = timing
+        .measure(
+            "llm",
+            llm.complete_messages(messages, &model, payload.max_tokens, payload.temperature, None),
+        )
+        .await?;
+
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
+
+    Ok((timing, Json(ChatCompletionResponse {
+        model,
+        message: ChatTurn {
+            role: "assistant".to_string(),
+            content: result.content,
+        },
+        usage: result.usage,
+        finish_reason: result.finish_reason,
+        reasoning: result.reasoning,
+    })))
+}
+
+pub async fn get_completion(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<AiResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let completion = ai_crud(&state, &api_key)
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Completion not found"))?;
+
+    Ok(Json(stored_response(id, completion)))
+}
+
+/// New completions as NDJSON, one `AiResponse` per line, until the client
+/// disconnects. Needs change streams, so Mongo must run as a replica set.
+/// Admin only, as the feed carries every caller's completions.
+pub async fn tail_completions(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+) -> Result<Response, AppError> {
+    api_key.require_admin(&state.config)?;
+
+    let completions = AiCrud::new(&state.db).watch_created().await.map_err(|e| {
+        if database::is_change_stream_unsupported(&e) {
+            AppError::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "change_streams_unsupported",
+                "Tailing completions needs MongoDB to run as a replica set",
+            )
+        } else {
+            AppError::from(e)
+        }
+    })?;
+
+    // The body owns the change stream, so a disconnect drops its cursor
+    let lines = completions.map(|completion| {
+        completion.map(|completion| {
+            let id = completion.id.map(|id| id.to_hex()).unwrap_or_default();
+            let mut line = serde_json::to_vec(&stored_response(id, completion)).unwrap_or_default();
+            line.push(b'\n');
+            line
+        })
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Full content of a stored completion, for clients that showed a
+/// `max_display_chars` version. Plain text when the client accepts
+/// `text/plain`, JSON otherwise.
+pub async fn get_completion_content(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let completion = ai_crud(&state, &api_key)
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Completion not found"))?;
+
+    let wants_text = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/plain"));
+
+    Ok(if wants_text {
+        completion.response.into_response()
+    } else {
+        Json(CompletionContentResponse {
+            id,
+            content: completion.response,
+        })
+        .into_response()
+    })
+}
+
+fn stored_response(id: String, completion: AiCompletion) -> AiResponse {
+    AiResponse {
+        id,
+        model: completion.model,
+        content: completion.response,
+        truncated: false,
+        billed_tokens: completion.usage.as_ref().map(|u| u.total_tokens),
+        usage: completion.usage,
+        created_at: completion.created_at.to_rfc3339(),
+        finish_reason: completion.finish_reason,
+        cached: false,
+        reasoning: None,
+    }
+}
+
+/// Re-run a stored completion's prompt on another model and return both.
+/// Suggestions and analyses are re-run with the default type, since the
+/// type a request asked for isn't stored.
+pub async fn replay_completion(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Path(id): Path<String>,
+    Query(query): Query<ReplayQuery>,
+) -> Result<(ServerTiming, Json<ReplayResponse>), AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+    let mut timing = ServerTiming::new();
+
+    let crud = ai_crud(&state, &api_key);
+    let original = timing
+        .measure("db", crud.find_by_id(&oid))
+        .await?
+        .ok_or_else(|| AppError::not_found("Completion not found"))?;
+
+    if !matches!(original.request_type.as_str(), "complete" | "suggest" | "analyze") {
+        return Err(AppError::bad_request(format!(
+            "{} completions can't be replayed",
+            original.request_type
+        )));
+    }
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+    let model = query.model;
+    let prompt = original.prompt.as_str();
+
+    let result = match original.request_type.as_str() {
+        "suggest" => timing.measure("llm", llm.suggest(prompt, &model, None, None, None)).await?,
+        "analyze" => timing.measure("llm", llm.analyze(prompt, &model, None, None)).await?,
+        _ => {
+            let system_prompt = original.system_prompt.as_deref();
+            timing
+                .measure("llm", llm.complete(prompt, &model, system_prompt, None, None))
+                .await?
+        }
+    };
+
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    if let Some(usage) = &result.usage {
+        quota.record(&key_id, usage.total_tokens).await;
+    }
+
+    let replay = AiCompletion::new(
+        original.prompt.clone(),
+        original.system_prompt.clone(),
+        model,
+        result.content,
+        result.usage,
+        original.request_type.clone(),
+    )
+    .with_owner(key_id.clone())
+    .with_replay_of(oid)
+    .with_finish_reason(result.finish_reason);
+
+    let replay_id = timing.measure("db", crud.create(replay.clone())).await?;
+
+    Ok((timing, Json(ReplayResponse {
+        original: stored_response(id, original),
+        replay: stored_response(replay_id.to_hex(), replay),
+    })))
+}
+
+/// Usage patterns: how many of the caller's completions of each type and
+/// model
+pub async fn stats(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<StatsResponse>, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(AppError::bad_request("from must be before to"));
+        }
+    }
+
+    let result = ai_crud(&state, &api_key).stats(query.from, query.to).await?;
+
+    let counts = |facet: &str| {
+        result
+            .get_array(facet)
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(|g| g.as_document())
+                    .map(|g| {
+                        let key = g.get_str("_id").unwrap_or("unknown").to_string();
+                        (key, number(g.get("count")))
+                    })
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let totals = result
+        .get_array("totals")
+        .ok()
+        .and_then(|t| t.first())
+        .and_then(|t| t.as_document());
+
+    Ok(Json(StatsResponse {
+        total_completions: totals.map(|t| number(t.get("completions"))).unwrap_or(0),
+        total_tokens: totals.map(|t| number(t.get("tokens"))).unwrap_or(0),
+        by_request_type: counts("by_request_type"),
+        by_model: counts("by_model"),
+    }))
+}
+
+/// Count the tokens a text takes up for a model, without calling it
+pub async fn estimate_tokens(
+    State(state): State<AppState>,
+    Json(payload): Json<TokenEstimateRequest>,
+) -> Result<Json<TokenEstimateResponse>, AppError> {
+    payload.validate()?;
+
+    let model = payload
+        .model
+        .unwrap_or_else(|| state.config.default_model.clone());
+
+    Ok(Json(TokenEstimateResponse {
+        tokens: tokens::count_tokens(&payload.text, &model),
+        exact: tokens::has_tokenizer(&model),
+        model,
     }))
 }
 
-pub async fn list_models() -> Json<ModelsResponse> {
-    let models = vec![
+/// Send one prompt to every model at once and store the latencies. Each
+/// model gets `BENCHMARK_MODEL_TIMEOUT_MS`, and one that fails or times out
+/// is recorded rather than failing the run. The response is sent once
+/// `BENCHMARK_DEADLINE_MS` passes even if models are still running; those
+/// are listed as `pending` and left out of the results.
+pub async fn benchmark(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Json(payload): Json<BenchmarkRequest>,
+) -> Result<Json<BenchmarkResponse>, AppError> {
+    payload.validate()?;
+
+    let quota = QuotaTracker::new(state.redis.clone(), &state.config);
+    let key_id = api_key.id();
+    ensure_quota(&quota, &key_id).await?;
+    let _slot = acquire_ai_slot(&state, &key_id).await?;
+
+    let llm = create_llm_client(&state.config, &end_user)?;
+    let prompt = payload
+        .prompt
+        .unwrap_or_else(|| DEFAULT_BENCHMARK_PROMPT.to_string());
+    let max_tokens = payload.max_tokens.unwrap_or(200);
+
+    // Models run concurrently so one that hangs holds up neither the others
+    // nor, past the deadline, the response
+    let model_timeout = Duration::from_millis(state.config.benchmark_model_timeout_ms);
+    let deadline =
+        tokio::time::Instant::now() + Duration::from_millis(state.config.benchmark_deadline_ms);
+    let mut runs = payload
+        .models
+        .iter()
+        .enumerate()
+        .map(|(index, model)| {
+            let messages = vec![ChatMessage::new("user", &prompt)];
+            let completion = llm.complete_messages(messages, model, Some(max_tokens), None, None);
+            async move {
+                let start = Instant::now();
+                let outcome = tokio::time::timeout(model_timeout, completion).await;
+                (index, outcome, start.elapsed().as_millis() as u64)
+            }
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    let mut finished: Vec<Option<BenchmarkResult>> = payload.models.iter().map(|_| None).collect();
+    while let Ok(Some((index, outcome, latency_ms))) =
+        tokio::time::timeout_at(deadline, runs.next()).await
+    {
+        let model = payload.models[index].clone();
+        finished[index] = Some(match outcome {
+            Ok(Ok(response)) => {
+                if let Some(usage) = &response.usage {
+                    quota.record(&key_id, usage.total_tokens).await;
+                }
+                BenchmarkResult {
+                    model,
+                    latency_ms,
+                    usage: response.usage,
+                    error: None,
+                }
+            }
+            Ok(Err(e)) => BenchmarkResult {
+                model,
+                latency_ms,
+                usage: None,
+                error: Some(e.to_string()),
+            },
+            Err(_) => BenchmarkResult {
+                model,
+                latency_ms,
+                usage: None,
+                error: Some("timeout".to_string()),
+            },
+        });
+    }
+    drop(runs);
+
+    let pending = payload
+        .models
+        .iter()
+        .zip(&finished)
+        .filter(|(_, result)| result.is_none())
+        .map(|(model, _)| model.clone())
+        .collect::<Vec<_>>();
+    if !pending.is_empty() {
+        tracing::warn!("Benchmark deadline passed with {:?} still running", pending);
+    }
+
+    let benchmark = Benchmark::new(prompt, finished.into_iter().flatten().collect());
+    let id = BenchmarkCrud::new(&state.db)
+        .with_persistence(state.config.persistence)
+        .create(benchmark.clone())
+        .await?;
+
+    Ok(Json(BenchmarkResponse {
+        id: id.to_hex(),
+        prompt: benchmark.prompt,
+        results: benchmark
+            .results
+            .into_iter()
+            .map(|r| BenchmarkModelResult {
+                model: r.model,
+                latency_ms: r.latency_ms,
+                usage: r.usage,
+                error: r.error,
+            })
+            .collect(),
+        partial: !pending.is_empty(),
+        pending,
+        created_at: benchmark.created_at.to_rfc3339(),
+    }))
+}
+
+/// Daily benchmark latency per model, for charting trends
+pub async fn benchmark_history(
+    State(state): State<AppState>,
+    Query(query): Query<BenchmarkHistoryQuery>,
+) -> Result<Json<BenchmarkHistoryResponse>, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(AppError::bad_request("from must be before to"));
+        }
+    }
+
+    let groups = BenchmarkCrud::new(&state.db)
+        .history(query.model.as_deref(), query.from, query.to)
+        .await?;
+
+    let points = groups
+        .iter()
+        .map(|g| {
+            let key = g.get_document("_id").ok();
+            let field = |name: &str| {
+                key.and_then(|k| k.get_str(name).ok())
+                    .unwrap_or("unknown")
+                    .to_string()
+            };
+
+            BenchmarkHistoryPoint {
+                date: field("day"),
+                model: field("model"),
+                runs: number(g.get("runs")),
+                errors: number(g.get("errors")),
+                avg_latency_ms: g.get_f64("avg_latency_ms").ok(),
+                max_latency_ms: g
+                    .get("max_latency_ms")
+                    .filter(|v| !matches!(v, bson::Bson::Null))
+                    .map(|v| number(Some(v))),
+            }
+        })
+        .collect();
+
+    Ok(Json(BenchmarkHistoryResponse { points }))
+}
+
+pub async fn quota(State(state): State<AppState>, api_key: ApiKey) -> Json<QuotaResponse> {
+    let status = QuotaTracker::new(state.redis.clone(), &state.config)
+        .status(&api_key.id())
+        .await;
+
+    Json(QuotaResponse {
+        used: status.used,
+        limit: status.limit,
+        resets_at: status.resets_at.to_rfc3339(),
+    })
+}
+
+/// Models the configured providers can serve. With `?health=true` each one
+/// is annotated from the cached deep health check and recent benchmarks.
+pub async fn list_models(
+    State(state): State<AppState>,
+    Query(query): Query<ModelsQuery>,
+) -> Json<ModelsResponse> {
+    let mut models = state
+        .discovery
+        .models
+        .get_or_init(|| configured_models(&state.config))
+        .clone();
+
+    if query.health {
+        let refresh_state = state.clone();
+        let health = state
+            .discovery
+            .model_health
+            .get_or_refresh(|| async move { model_health(&refresh_state).await })
+            .await;
+
+        for model in &mut models {
+            model.available = health
+                .providers
+                .iter()
+                .find(|p| p.provider == model.provider)
+                .map(|p| p.authenticated);
+            model.avg_latency_ms = health.latencies.get(&model.id).copied();
+        }
+    }
+
+    Json(ModelsResponse { models })
+}
+
+/// Provider status from the cached deep health check, and benchmark
+/// latencies, for annotating the model list
+async fn model_health(state: &AppState) -> ModelHealth {
+    let providers = cache::get(&state.redis, DEEP_CACHE_KEY)
+        .await
+        .and_then(|c| serde_json::from_str::<DeepHealthResponse>(&c).ok())
+        .map(|health| health.providers)
+        .unwrap_or_default();
+
+    ModelHealth {
+        providers,
+        latencies: benchmark_latencies(state).await,
+    }
+}
+
+/// Mean latency per model over the past week's successful benchmark runs
+async fn benchmark_latencies(state: &AppState) -> BTreeMap<String, f64> {
+    let since = chrono::Utc::now() - chrono::Duration::days(7);
+    let groups = match BenchmarkCrud::new(&state.db).history(None, Some(since), None).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            tracing::warn!("Benchmark latencies unavailable: {}", e);
+            return BTreeMap::new();
+        }
+    };
+
+    // Days are grouped separately; weight each by its successful runs
+    let mut totals: BTreeMap<String, (f64, u64)> = BTreeMap::new();
+    for g in &groups {
+        let (Some(model), Ok(avg)) = (
+            g.get_document("_id").ok().and_then(|k| k.get_str("model").ok()),
+            g.get_f64("avg_latency_ms"),
+        ) else {
+            continue;
+        };
+        let successes = number(g.get("runs")).saturating_sub(number(g.get("errors")));
+        let total = totals.entry(model.to_string()).or_default();
+        total.0 += avg * successes as f64;
+        total.1 += successes;
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, (_, runs))| *runs > 0)
+        .map(|(model, (sum, runs))| (model, sum / runs as f64))
+        .collect()
+}
+
+/// `available_models` limited to providers with a configured key. A test
+/// backend stands in for every provider, so nothing is filtered then.
+pub fn configured_models(config: &Config) -> Vec<ModelInfo> {
+    available_models()
+        .iter()
+        .filter(|m| config.client_backend.is_some() || config.provider(&m.provider).is_some())
+        .cloned()
+        .collect()
+}
+
+/// Models offered to clients, with reasoning support filled in. Built once.
+pub fn available_models() -> &'static [ModelInfo] {
+    static MODELS: OnceLock<Vec<ModelInfo>> = OnceLock::new();
+    MODELS.get_or_init(build_models)
+}
+
+fn build_models() -> Vec<ModelInfo> {
+    let mut models = vec![
         // Groq models (fastest - ~500ms)
         ModelInfo {
             id: "llama-3.1-8b-instant".to_string(),
             name: "Llama 3.1 8B Instant (Groq)".to_string(),
             description: "Fastest model (~500ms). Great for quick coding help.".to_string(),
             context_length: 131072,
+            supports_reasoning: false,
+            provider: LlmProvider::Groq.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
         ModelInfo {
             id: "llama-3.3-70b-versatile".to_string(),
             name: "Llama 3.3 70B (Groq)".to_string(),
             description: "Larger Groq model for complex tasks. Slower but smarter.".to_string(),
             context_length: 131072,
+            supports_reasoning: false,
+            provider: LlmProvider::Groq.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
         // OpenRouter models (free tier)
         ModelInfo {
@@ -215,26 +1518,46 @@ pub async fn list_models() -> Json<ModelsResponse> {
             name: "Nemotron 3 Nano 30B".to_string(),
             description: "Fast free model (~700ms). NVIDIA's efficient MoE.".to_string(),
             context_length: 256000,
+            supports_reasoning: false,
+            provider: LlmProvider::OpenRouter.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
         ModelInfo {
             id: "google/gemma-3-27b-it:free".to_string(),
             name: "Gemma 3 27B".to_string(),
             description: "Google's fast model (~900ms). Good quality.".to_string(),
             context_length: 131072,
+            supports_reasoning: false,
+            provider: LlmProvider::OpenRouter.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
         ModelInfo {
             id: AiModel::KatCoderPro.as_str().to_string(),
             name: "KAT-Coder-Pro V1".to_string(),
             description: "Coding specialist (~1200ms). 73.4% on SWE-Bench.".to_string(),
             context_length: 256000,
+            supports_reasoning: false,
+            provider: LlmProvider::OpenRouter.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
         ModelInfo {
             id: AiModel::Devstral.as_str().to_string(),
             name: "Devstral 2".to_string(),
             description: "Mistral coding model (~2300ms). 256K context.".to_string(),
             context_length: 262144,
+            supports_reasoning: false,
+            provider: LlmProvider::OpenRouter.as_str().to_string(),
+            available: None,
+            avg_latency_ms: None,
         },
     ];
 
-    Json(ModelsResponse { models })
+    for model in &mut models {
+        model.supports_reasoning = supports_reasoning(&model.id);
+    }
+
+    models
 }
@@ -1,27 +1,61 @@
-use crate::modules::ai::model::AiCompletion;
-use bson::{doc, oid::ObjectId};
+use crate::config::Persistence;
+use crate::modules::ai::model::{AiCompletion, Benchmark};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::Stream;
 use mongodb::{Collection, Database};
 
 const COLLECTION_NAME: &str = "ai_completions";
+const BENCHMARKS_COLLECTION: &str = "benchmarks";
 
 pub struct AiCrud {
     collection: Collection<AiCompletion>,
+    /// Restricts by-id reads to this owner's completions
+    owner: Option<String>,
+    store: bool,
 }
 
 impl AiCrud {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection(COLLECTION_NAME),
+            owner: None,
+            store: true,
         }
     }
 
+    /// Only see completions requested by this owner (see
+    /// `ApiKey::owner_scope`). `None` sees all.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// `filter` narrowed to the owner's completions, if scoped
+    fn scoped(&self, mut filter: Document) -> Document {
+        if let Some(owner) = &self.owner {
+            filter.insert("owner", owner);
+        }
+        filter
+    }
+
+    /// Under `Persistence::None`, `create` skips the insert and returns a
+    /// fresh id that was never stored
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.store = persistence.stores();
+        self
+    }
+
     pub async fn create(&self, completion: AiCompletion) -> Result<ObjectId, mongodb::error::Error> {
+        if !self.store {
+            return Ok(ObjectId::new());
+        }
         let result = self.collection.insert_one(completion).await?;
         Ok(result.inserted_id.as_object_id().unwrap())
     }
 
     pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<AiCompletion>, mongodb::error::Error> {
-        self.collection.find_one(doc! { "_id": id }).await
+        self.collection.find_one(self.scoped(doc! { "_id": id })).await
     }
 
     pub async fn find_recent(&self, limit: i64) -> Result<Vec<AiCompletion>, mongodb::error::Error> {
@@ -30,14 +64,165 @@ impl AiCrud {
         let cursor = self
             .collection
             .find(doc! {})
-            .sort(doc! { "created_at": -1 })
+            .sort(doc! { "created_at": -1, "_id": -1 })
             .limit(limit)
             .await?;
 
         cursor.try_collect().await
     }
 
+    /// Completions inserted from now on, through a change stream. Dropping
+    /// the stream closes its server-side cursor.
+    pub async fn watch_created(
+        &self,
+    ) -> Result<impl Stream<Item = Result<AiCompletion, mongodb::error::Error>>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let changes = self
+            .collection
+            .watch()
+            .pipeline([doc! { "$match": { "operationType": "insert" } }])
+            .await?;
+
+        Ok(changes.try_filter_map(|change| async move { Ok(change.full_document) }))
+    }
+
     pub async fn count(&self) -> Result<u64, mongodb::error::Error> {
         self.collection.count_documents(doc! {}).await
     }
+
+    /// Delete completions created before `cutoff`
+    pub async fn delete_older_than(&self, cutoff: DateTime<Utc>) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(created_at_range(None, Some(cutoff))).await?;
+        Ok(result.deleted_count)
+    }
+
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+        Ok(result.deleted_count)
+    }
+
+    /// Counts by `request_type` and `model` plus totals, in one `$facet`
+    /// aggregation
+    pub async fn stats(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Document, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let filter = self.scoped(created_at_range(from, to));
+
+        let group_count = |field: &str| {
+            vec![
+                doc! { "$group": { "_id": format!("${}", field), "count": { "$sum": 1 } } },
+                doc! { "$sort": { "count": -1, "_id": 1 } },
+            ]
+        };
+
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$facet": {
+                "by_request_type": group_count("request_type"),
+                "by_model": group_count("model"),
+                "totals": [
+                    { "$group": {
+                        "_id": null,
+                        "completions": { "$sum": 1 },
+                        "tokens": { "$sum": { "$ifNull": ["$usage.total_tokens", 0] } }
+                    } }
+                ]
+            } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        Ok(cursor.try_next().await?.unwrap_or_default())
+    }
+}
+
+/// `created_at` filter for an optional range. Timestamps are stored as
+/// RFC 3339 strings, so the bounds are compared lexicographically.
+fn created_at_range(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Document {
+    let mut range = Document::new();
+    if let Some(from) = from {
+        range.insert("$gte", from.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    }
+    if let Some(to) = to {
+        range.insert("$lt", to.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    }
+
+    if range.is_empty() {
+        doc! {}
+    } else {
+        doc! { "created_at": range }
+    }
+}
+
+pub struct BenchmarkCrud {
+    collection: Collection<Benchmark>,
+    store: bool,
+}
+
+impl BenchmarkCrud {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection(BENCHMARKS_COLLECTION),
+            store: true,
+        }
+    }
+
+    /// Under `Persistence::None`, `create` skips the insert and returns a
+    /// fresh id that was never stored
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.store = persistence.stores();
+        self
+    }
+
+    pub async fn create(&self, benchmark: Benchmark) -> Result<ObjectId, mongodb::error::Error> {
+        if !self.store {
+            return Ok(ObjectId::new());
+        }
+        let result = self.collection.insert_one(benchmark).await?;
+        Ok(result.inserted_id.as_object_id().unwrap())
+    }
+
+    /// Daily latency per model: runs, failures, and mean/max latency of
+    /// the successful runs, oldest day first
+    pub async fn history(
+        &self,
+        model: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let result_filter = match model {
+            Some(model) => doc! { "results.model": model },
+            None => doc! {},
+        };
+
+        // `null` for failed runs, which `$avg` and `$max` skip
+        let latency = doc! {
+            "$cond": [{ "$ifNull": ["$results.error", false] }, null, "$results.latency_ms"]
+        };
+
+        let pipeline = vec![
+            doc! { "$match": created_at_range(from, to) },
+            doc! { "$unwind": "$results" },
+            doc! { "$match": result_filter },
+            doc! { "$group": {
+                "_id": {
+                    "model": "$results.model",
+                    "day": { "$substrCP": ["$created_at", 0, 10] }
+                },
+                "runs": { "$sum": 1 },
+                "errors": { "$sum": { "$cond": [{ "$ifNull": ["$results.error", false] }, 1, 0] } },
+                "avg_latency_ms": { "$avg": latency.clone() },
+                "max_latency_ms": { "$max": latency }
+            } },
+            doc! { "$sort": { "_id.day": 1, "_id.model": 1 } },
+        ];
+
+        self.collection.aggregate(pipeline).await?.try_collect().await
+    }
 }
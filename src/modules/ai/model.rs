@@ -14,6 +14,16 @@ pub struct AiCompletion {
     pub response: String,
     pub usage: Option<UsageInfo>,
     pub request_type: String,
+    /// The completion this one re-ran on another model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<ObjectId>,
+    /// Why the model stopped, see `LlmResponse::finish_reason`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Id of the API key that requested it (`ApiKey::id`). Missing on
+    /// completions stored before completions had owners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -34,6 +44,57 @@ impl AiCompletion {
             response,
             usage,
             request_type,
+            replay_of: None,
+            finish_reason: None,
+            owner: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn with_replay_of(mut self, original: ObjectId) -> Self {
+        self.replay_of = Some(original);
+        self
+    }
+
+    pub fn with_finish_reason(mut self, finish_reason: Option<String>) -> Self {
+        self.finish_reason = finish_reason;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+}
+
+/// One model's outcome within a benchmark run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchmarkResult {
+    pub model: String,
+    /// Time to the full response, or to the error
+    pub latency_ms: u64,
+    pub usage: Option<UsageInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A benchmark run: the same prompt sent to every model concurrently. Models
+/// still running at the deadline have no result.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Benchmark {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub prompt: String,
+    pub results: Vec<BenchmarkResult>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Benchmark {
+    pub fn new(prompt: String, results: Vec<BenchmarkResult>) -> Self {
+        Self {
+            id: None,
+            prompt,
+            results,
             created_at: Utc::now(),
         }
     }
@@ -9,7 +9,26 @@ use crate::AppState;
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/api/ai/complete", post(controller::complete))
+        .route("/api/ai/complete/stream", post(controller::complete_stream))
+        .route("/api/ai/complete/batch", post(controller::complete_batch))
         .route("/api/ai/suggest", post(controller::suggest))
+        .route("/api/ai/suggest/stream", post(controller::suggest_stream))
         .route("/api/ai/analyze", post(controller::analyze))
-        .route("/api/ai/models", get(controller::list_models))
+        .route("/api/ai/analyze/batch", post(controller::analyze_batch))
+        .route("/api/ai/rerank", post(controller::rerank))
+        .route("/api/ai/estimate", post(controller::estimate_tokens))
+        .route("/api/ai/chat", post(controller::chat))
+        .route("/api/ai/completions/tail", get(controller::tail_completions))
+        .route("/api/ai/completions/{id}", get(controller::get_completion))
+        .route("/api/ai/completions/{id}/content", get(controller::get_completion_content))
+        .route("/api/ai/completions/{id}/replay", post(controller::replay_completion))
+        .route("/api/ai/quota", get(controller::quota))
+        .route("/api/ai/stats", get(controller::stats))
+        .route("/api/ai/benchmark", post(controller::benchmark))
+        .route("/api/ai/benchmark/history", get(controller::benchmark_history))
+}
+
+/// Served without an API key, outside `auth::require_api_key`
+pub fn public_routes() -> Router<AppState> {
+    Router::new().route("/api/ai/models", get(controller::list_models))
 }
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use std::collections::BTreeMap;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum AiModel {
+    #[default]
     #[serde(rename = "xiaomi/mimo-v2-flash:free")]
     MimoV2Flash,
     #[serde(rename = "nvidia/nemotron-3-nano-30b-a3b:free")]
@@ -37,12 +39,6 @@ impl AiModel {
     }
 }
 
-impl Default for AiModel {
-    fn default() -> Self {
-        AiModel::MimoV2Flash
-    }
-}
-
 #[derive(Debug, Deserialize, Validate)]
 pub struct CompleteRequest {
     #[validate(length(min = 1, message = "Prompt cannot be empty"))]
@@ -51,6 +47,65 @@ pub struct CompleteRequest {
     pub system_prompt: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    #[validate(custom(function = "validate_reasoning_effort"))]
+    pub reasoning_effort: Option<String>,
+    /// Name of a `PROMPT_TEMPLATES` entry to send instead of `prompt`,
+    /// which the template can include as `{prompt}`
+    pub template: Option<String>,
+    pub variables: Option<BTreeMap<String, String>>,
+}
+
+/// Prompts completed together with one system prompt and settings
+#[derive(Debug, Deserialize, Validate)]
+pub struct CompleteBatchRequest {
+    #[validate(
+        length(min = 1, max = 50, message = "Between 1 and 50 prompts per batch"),
+        custom(function = "validate_prompts")
+    )]
+    pub prompts: Vec<String>,
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+fn validate_prompts(prompts: &[String]) -> Result<(), ValidationError> {
+    if prompts.iter().any(|p| p.trim().is_empty()) {
+        return Err(ValidationError::new("prompts").with_message("Prompts cannot be empty".into()));
+    }
+    Ok(())
+}
+
+/// One batch completion, in the same position as its prompt. Exactly one of
+/// `content` and `error` is set.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct CompleteBatchItem {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct CompleteBatchResponse {
+    pub model: String,
+    pub results: Vec<CompleteBatchItem>,
+}
+
+fn validate_reasoning_effort(effort: &str) -> Result<(), ValidationError> {
+    match effort {
+        "low" | "medium" | "high" => Ok(()),
+        _ => Err(ValidationError::new("reasoning_effort")
+            .with_message("Reasoning effort must be low, medium or high".into())),
+    }
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -59,6 +114,21 @@ pub struct SuggestRequest {
     pub context: String,
     pub model: Option<String>,
     pub suggestion_type: Option<String>,
+    /// Session whose recent messages are prepended to `context`.
+    /// The suggestion is appended back to it as an assistant message.
+    pub session_id: Option<String>,
+    /// Number of recent session messages to include (default 10)
+    #[validate(range(min = 1, max = 50, message = "context_messages must be between 1 and 50"))]
+    pub context_messages: Option<usize>,
+    /// Language to answer in (Whisper code or name, e.g. `es` or `spanish`)
+    pub language: Option<String>,
+    /// Overrides the suggestion type's default (`TYPE_TEMPERATURES`)
+    #[validate(range(min = 0.0, max = 2.0, message = "temperature must be between 0 and 2"))]
+    pub temperature: Option<f32>,
+    /// Name of a `PROMPT_TEMPLATES` entry to use in place of `context`,
+    /// which the template can include as `{context}`
+    pub template: Option<String>,
+    pub variables: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -67,17 +137,149 @@ pub struct AnalyzeRequest {
     pub text: String,
     pub model: Option<String>,
     pub analysis_type: Option<String>,
+    /// Overrides the analysis type's default (`TYPE_TEMPERATURES`)
+    #[validate(range(min = 0.0, max = 2.0, message = "temperature must be between 0 and 2"))]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RerankRequest {
+    #[validate(length(min = 1, message = "Query cannot be empty"))]
+    pub query: String,
+    #[validate(length(min = 1, max = 20, message = "Between 1 and 20 candidates"))]
+    pub candidates: Vec<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct RankedCandidate {
+    /// Position in the request's `candidates`
+    pub index: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct RerankResponse {
+    pub id: String,
+    pub model: String,
+    /// Best candidate first
+    pub results: Vec<RankedCandidate>,
+    pub usage: Option<UsageInfo>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AnalyzeBatchRequest {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 texts per batch"))]
+    pub texts: Vec<String>,
+    pub model: Option<String>,
+    pub analysis_type: Option<String>,
+}
+
+/// One batch result, in the same position as its input. Exactly one of
+/// `content` and `error` is set.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct AnalyzeBatchItem {
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct AnalyzeBatchResponse {
+    pub model: String,
+    pub results: Vec<AnalyzeBatchItem>,
+}
+
+/// Query options shared by the completion endpoints
+#[derive(Debug, Deserialize)]
+pub struct DisplayQuery {
+    /// Truncate `content` in the response to this many characters.
+    /// The full content is still stored with the completion.
+    pub max_display_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ChatTurn {
+    #[validate(custom(function = "validate_role"))]
+    pub role: String,
+    #[validate(length(min = 1, message = "Content cannot be empty"))]
+    pub content: String,
+}
+
+fn validate_role(role: &str) -> Result<(), ValidationError> {
+    match role {
+        "user" | "assistant" | "system" => Ok(()),
+        _ => Err(ValidationError::new("role")
+            .with_message("Role must be user, assistant or system".into())),
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_ends_with_user"))]
+pub struct ChatCompletionRequest {
+    #[validate(length(min = 1, message = "Messages cannot be empty"), nested)]
+    pub messages: Vec<ChatTurn>,
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+fn validate_ends_with_user(request: &ChatCompletionRequest) -> Result<(), ValidationError> {
+    match request.messages.last() {
+        Some(last) if last.role != "user" => Err(ValidationError::new("messages")
+            .with_message("The last message must be from the user".into())),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct ChatCompletionResponse {
+    pub model: String,
+    pub message: ChatTurn,
+    pub usage: Option<UsageInfo>,
+    pub finish_reason: Option<String>,
+    /// The model's reasoning, kept out of `message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct AiResponse {
     pub id: String,
     pub model: String,
     pub content: String,
+    pub truncated: bool,
     pub usage: Option<UsageInfo>,
     pub created_at: String,
+    /// `length` means the output was cut off by `max_tokens`
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    /// Served from the analyze cache rather than the model
+    #[serde(default)]
+    pub cached: bool,
+    /// Tokens counted against the key's quota for this response. Matches
+    /// `usage` for fresh results but is 0 for cached ones, whose `usage` is
+    /// what the original call cost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billed_tokens: Option<u32>,
+    /// The model's reasoning, kept out of `content`, when it reports one.
+    /// Not stored, so never set on completions fetched by id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
 }
 
+/// Also stored with completions and messages, so it keeps snake_case field
+/// names even with the `camelCase` feature
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageInfo {
     pub prompt_tokens: u32,
@@ -86,19 +288,170 @@ pub struct UsageInfo {
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct ModelsResponse {
     pub models: Vec<ModelInfo>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct ModelInfo {
     pub id: String,
     pub name: String,
     pub description: String,
     pub context_length: u32,
+    pub supports_reasoning: bool,
+    /// `groq` or `openrouter`
+    pub provider: String,
+    /// From the last deep health check of the provider; omitted when
+    /// health wasn't requested or no recent check is cached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+    /// Mean latency of successful benchmark runs over the past week
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelsQuery {
+    /// Annotate each model with `available` and `avg_latency_ms`
+    #[serde(default)]
+    pub health: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    /// Inclusive lower bound (RFC 3339)
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exclusive upper bound (RFC 3339)
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct StatsResponse {
+    pub total_completions: u64,
+    pub total_tokens: u64,
+    pub by_request_type: BTreeMap<String, u64>,
+    pub by_model: BTreeMap<String, u64>,
+}
+
+/// Untruncated content of a stored completion
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct CompletionContentResponse {
+    pub id: String,
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    pub model: String,
+}
+
+/// A stored completion next to its re-run
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct ReplayResponse {
+    pub original: AiResponse,
+    pub replay: AiResponse,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TokenEstimateRequest {
+    #[validate(length(min = 1, message = "Text cannot be empty"))]
+    pub text: String,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct TokenEstimateResponse {
+    pub model: String,
+    pub tokens: usize,
+    /// Counted with the model's tokenizer rather than estimated
+    pub exact: bool,
+}
+
+/// Prompt used when a benchmark request doesn't give one
+pub const DEFAULT_BENCHMARK_PROMPT: &str =
+    "Write a function in Python to check if a number is prime. Keep it short.";
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BenchmarkRequest {
+    #[validate(length(min = 1, max = 10, message = "Between 1 and 10 models per benchmark"))]
+    pub models: Vec<String>,
+    #[validate(length(min = 1, max = 10000))]
+    pub prompt: Option<String>,
+    #[validate(range(min = 1, max = 4096))]
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct BenchmarkModelResult {
+    pub model: String,
+    pub latency_ms: u64,
+    pub usage: Option<UsageInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results in request order. A model slower than `BENCHMARK_MODEL_TIMEOUT_MS`
+/// has `error: "timeout"`.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct BenchmarkResponse {
+    pub id: String,
+    pub prompt: String,
+    pub results: Vec<BenchmarkModelResult>,
+    /// Set when `BENCHMARK_DEADLINE_MS` passed before every model finished
+    pub partial: bool,
+    /// Models abandoned at the deadline; they have no entry in `results`
+    pub pending: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkHistoryQuery {
+    pub model: Option<String>,
+    /// Inclusive lower bound (RFC 3339)
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exclusive upper bound (RFC 3339)
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One model's benchmark runs on one day (UTC)
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct BenchmarkHistoryPoint {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub model: String,
+    pub runs: u64,
+    pub errors: u64,
+    /// Over successful runs; `None` when every run failed
+    pub avg_latency_ms: Option<f64>,
+    pub max_latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct BenchmarkHistoryResponse {
+    /// Oldest day first
+    pub points: Vec<BenchmarkHistoryPoint>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct QuotaResponse {
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub resets_at: String,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct MessageResponse {
     pub message: String,
 }
@@ -0,0 +1,162 @@
+use axum::{extract::State, http::StatusCode, Json};
+use bson::doc;
+use chrono::Utc;
+use redis::AsyncCommands;
+
+use crate::auth::ApiKey;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::modules::ai::controller::configured_models;
+use crate::modules::health::schema::{
+    CapabilitiesResponse, DeepHealthResponse, HealthResponse, LanguageInfo, ProviderHealth,
+    StreamingCapabilities, VersionResponse,
+};
+use crate::services::cache;
+use crate::services::llm::{supported_languages, LlmClient, LlmProvider};
+use crate::services::stt::MAX_AUDIO_BYTES;
+use crate::AppState;
+
+/// How long a deep check result is reused before providers are hit again
+const DEEP_CACHE_TTL_SECS: u64 = 30;
+pub const DEEP_CACHE_KEY: &str = "health:deep";
+
+/// Deep checks allowed per key per minute
+const DEEP_RATE_LIMIT: u64 = 6;
+
+pub async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let mongodb = state.db.run_command(doc! { "ping": 1 }).await.is_ok();
+
+    let mut redis = state.redis.clone();
+    let redis_ok = redis::cmd("PING")
+        .query_async::<String>(&mut redis)
+        .await
+        .is_ok();
+
+    let (status, label) = if mongodb && redis_ok {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+
+    (
+        status,
+        Json(HealthResponse {
+            status: label.to_string(),
+            mongodb,
+            redis: redis_ok,
+            redis_errors_total: cache::redis_errors_total(),
+        }),
+    )
+}
+
+pub async fn version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
+
+pub async fn capabilities(State(state): State<AppState>) -> Json<CapabilitiesResponse> {
+    let config = &state.config;
+
+    Json(CapabilitiesResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        providers: config.providers.keys().map(|p| p.to_string()).collect(),
+        stt: config.provider("groq").is_some(),
+        streaming: StreamingCapabilities {
+            completions: true,
+            transcribe_url: true,
+        },
+        max_upload_bytes: MAX_AUDIO_BYTES,
+        audio_formats: state.discovery.formats(config).to_vec(),
+        languages: supported_languages()
+            .iter()
+            .map(|(code, name)| LanguageInfo {
+                code: code.to_string(),
+                name: name.to_string(),
+            })
+            .collect(),
+        models: state
+            .discovery
+            .models
+            .get_or_init(|| configured_models(config))
+            .clone(),
+        auth_required: !config.api_keys.is_empty(),
+        quota_enforced: config.daily_token_quota.is_some() || config.monthly_token_quota.is_some(),
+    })
+}
+
+/// Verify that every configured provider accepts its API key. Catches a
+/// rotated or expired key before the first user request does.
+pub async fn deep_health(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+) -> Result<Json<DeepHealthResponse>, AppError> {
+    api_key.require_admin(&state.config)?;
+
+    let mut redis = state.redis.clone();
+
+    let rate_key = format!("health:deep:rate:{}", api_key.id());
+    let calls: u64 = redis.incr(&rate_key, 1).await.unwrap_or(0);
+    if calls == 1 {
+        let _: Result<bool, _> = redis.expire(&rate_key, 60).await;
+    }
+    if calls > DEEP_RATE_LIMIT {
+        return Err(AppError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            "Too many deep health checks, try again in a minute",
+        ));
+    }
+
+    let cached = cache::get(&state.redis, DEEP_CACHE_KEY).await;
+    if let Some(mut response) = cached.and_then(|c| serde_json::from_str::<DeepHealthResponse>(&c).ok()) {
+        response.cached = true;
+        return Ok(Json(response));
+    }
+
+    let providers = check_providers(&state.config).await;
+    let status = if providers.iter().all(|p| p.authenticated) {
+        "ok"
+    } else {
+        "degraded"
+    };
+
+    let response = DeepHealthResponse {
+        status: status.to_string(),
+        providers,
+        checked_at: Utc::now().to_rfc3339(),
+        cached: false,
+    };
+
+    if let Ok(json) = serde_json::to_string(&response) {
+        cache::set_ex(&state.redis, DEEP_CACHE_KEY, json, DEEP_CACHE_TTL_SECS).await;
+    }
+
+    Ok(Json(response))
+}
+
+async fn check_providers(config: &Config) -> Vec<ProviderHealth> {
+    let mut providers = Vec::new();
+    // Only configured providers are checked
+    for &provider in config.providers.keys() {
+        let Some(client) = LlmProvider::from_name(provider)
+            .and_then(|p| LlmClient::for_provider(config, p).ok())
+        else {
+            continue;
+        };
+
+        let error = client.check_connectivity().await.err();
+        if let Some(e) = &error {
+            tracing::warn!("Deep health: {} failed: {}", provider, e);
+        }
+
+        providers.push(ProviderHealth {
+            provider: provider.to_string(),
+            authenticated: error.is_none(),
+            error: error.map(|e| e.to_string()),
+        });
+    }
+
+    providers
+}
@@ -0,0 +1,17 @@
+use axum::{routing::get, Router};
+
+use crate::modules::health::controller;
+use crate::AppState;
+
+/// `/health/deep` checks the admin key itself
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/health/deep", get(controller::deep_health))
+}
+
+/// Served without an API key, outside `auth::require_api_key`
+pub fn public_routes() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(controller::health))
+        .route("/api/version", get(controller::version))
+        .route("/api/capabilities", get(controller::capabilities))
+}
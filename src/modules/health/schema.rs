@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::modules::ai::schema::ModelInfo;
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct HealthResponse {
+    pub status: String,
+    pub mongodb: bool,
+    pub redis: bool,
+    /// Failed Redis commands since startup (cache reads fall back to Mongo)
+    pub redis_errors_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub authenticated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct DeepHealthResponse {
+    pub status: String,
+    pub providers: Vec<ProviderHealth>,
+    pub checked_at: String,
+    /// Whether this result was served from the short-lived cache
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct VersionResponse {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct LanguageInfo {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct StreamingCapabilities {
+    pub completions: bool,
+    pub transcribe_url: bool,
+}
+
+/// Everything a client needs to adapt to this deployment
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct CapabilitiesResponse {
+    pub version: String,
+    /// Configured LLM providers
+    pub providers: Vec<String>,
+    /// Whether speech-to-text is configured
+    pub stt: bool,
+    pub streaming: StreamingCapabilities,
+    pub max_upload_bytes: u64,
+    pub audio_formats: Vec<String>,
+    pub languages: Vec<LanguageInfo>,
+    pub models: Vec<ModelInfo>,
+    pub auth_required: bool,
+    pub quota_enforced: bool,
+}
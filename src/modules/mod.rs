@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod ai;
+pub mod health;
 pub mod session;
 pub mod stt;
 pub mod transcription;
@@ -1,22 +1,43 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAliveStream, Sse},
+    response::{IntoResponse, Response},
     Json,
 };
 use bson::oid::ObjectId;
-use std::env;
+use chrono::DateTime;
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use std::time::Instant;
+use tokio_util::io::ReaderStream;
 use validator::Validate;
 
+use crate::auth::{ApiKey, EndUser};
+use crate::config::database;
+use crate::error::AppError;
 use crate::modules::session::{
-    crud::SessionCrud,
-    model::{Message, Session},
+    crud::{MessageEmbeddingCrud, MessageSearch, SessionCrud, SessionEmbeddingCrud},
+    model::{Message, MessageEmbedding, MessageMeta, Session, SessionEmbedding},
     schema::{
-        AddMessageRequest, ChatRequest, ChatResponse, CreateSessionRequest,
-        MessageResponse, MessageResponse2, SessionListResponse, SessionResponse,
-        SessionSummary,
+        AddMessageRequest, BulkAddMessagesRequest, BulkAddMessagesResponse, ChatQuery,
+        ChatRequest, ChatResponse, CreateSessionRequest, DeleteSessionQuery,
+        DeleteSessionResponse, IndexSessionResponse,
+        LatestMessageQuery, MessageResponse, MetadataQuery, RetitleQuery, RetitleResponse,
+        SearchMessageResult,
+        SearchMessagesQuery, SessionListResponse, SessionMetadataResponse, SessionResponse,
+        SessionSummary, SimilarSession, SimilarSessionsQuery, SimilarSessionsResponse,
     },
 };
-use crate::services::llm::LlmClient;
+use crate::modules::stt::controller::{stt_crud, to_response as stt_response};
+use crate::pagination::{Page, Paginated};
+use crate::services::{bundle, embeddings};
+use crate::services::llm::{clean_title, is_substantive, ChatMessage, LlmClient, StreamChunk};
+use crate::sse::sse_response;
+use crate::timing::ServerTiming;
 use crate::AppState;
 
 fn to_message_response(m: &Message) -> MessageResponse {
@@ -24,6 +45,10 @@ fn to_message_response(m: &Message) -> MessageResponse {
         role: m.role.clone(),
         content: m.content.clone(),
         timestamp: m.timestamp_rfc3339(),
+        meta: m.meta.clone(),
+        incomplete: m.incomplete,
+        provider_response_id: m.provider_response_id.clone(),
+        truncated: m.truncated,
     }
 }
 
@@ -34,86 +59,179 @@ fn to_session_response(s: &Session) -> SessionResponse {
         session_type: s.session_type.clone(),
         messages: s.messages.iter().map(to_message_response).collect(),
         message_count: s.messages.len(),
+        pinned: s.pinned,
         created_at: s.created_at_rfc3339(),
         updated_at: s.updated_at_rfc3339(),
+        stale: false,
     }
 }
 
-fn to_session_summary(s: &Session) -> SessionSummary {
+pub(crate) fn to_session_summary(s: &Session) -> SessionSummary {
     SessionSummary {
         id: s.id.map(|id| id.to_hex()).unwrap_or_default(),
         title: s.title.clone(),
         session_type: s.session_type.clone(),
         message_count: s.messages.len(),
+        pinned: s.pinned,
         created_at: s.created_at_rfc3339(),
         updated_at: s.updated_at_rfc3339(),
     }
 }
 
+/// Session access for the caller, limited to their own sessions when API
+/// keys are configured
+pub(crate) fn session_crud(state: &AppState, api_key: &ApiKey) -> SessionCrud {
+    SessionCrud::new(&state.db, state.redis.clone())
+        .with_owner(api_key.owner_scope(&state.config))
+        .with_persistence(state.config.persistence)
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(id).map_err(|_| AppError::invalid_id())
+}
+
+/// Check metadata against the configured schema. Violations are reported per
+/// JSON pointer under `fields`, e.g. `metadata/customer/id`.
+pub fn validate_metadata(
+    schema: &jsonschema::Validator,
+    metadata: &serde_json::Value,
+) -> Result<(), AppError> {
+    let mut fields = BTreeMap::<String, Vec<String>>::new();
+    for error in schema.iter_errors(metadata) {
+        fields
+            .entry(format!("metadata{}", error.instance_path))
+            .or_default()
+            .push(error.to_string());
+    }
+
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    let mut error = AppError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_metadata",
+        "Metadata does not match the session metadata schema",
+    );
+    error.body.fields = Some(fields);
+    Err(error)
+}
+
+/// Nesting depth of a JSON value; scalars are 0, `{}` and `[]` are 1
+fn json_depth(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Object(map) => 1 + map.values().map(json_depth).max().unwrap_or(0),
+        serde_json::Value::Array(items) => 1 + items.iter().map(json_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Reject metadata too large or too deeply nested to store, before any
+/// schema validation walks it
+pub fn check_metadata_limits(
+    metadata: &serde_json::Value,
+    max_bytes: usize,
+    max_depth: usize,
+) -> Result<(), AppError> {
+    let too_large = |message: String| {
+        AppError::new(StatusCode::BAD_REQUEST, "metadata_too_large", message)
+    };
+
+    let size = serde_json::to_vec(metadata).map(|bytes| bytes.len()).unwrap_or(usize::MAX);
+    if size > max_bytes {
+        return Err(too_large(format!(
+            "Metadata is {} bytes serialized; the limit is {}",
+            size, max_bytes
+        )));
+    }
+    if json_depth(metadata) > max_depth {
+        return Err(too_large(format!("Metadata is nested more than {} levels deep", max_depth)));
+    }
+    Ok(())
+}
+
 pub async fn create_session(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Json(payload): Json<CreateSessionRequest>,
-) -> Result<(StatusCode, Json<SessionResponse>), (StatusCode, Json<MessageResponse2>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: e.to_string() }),
-        ));
+) -> Result<(StatusCode, Json<SessionResponse>), AppError> {
+    payload.validate()?;
+
+    if let Some(metadata) = &payload.metadata {
+        check_metadata_limits(
+            metadata,
+            state.config.session_metadata_max_bytes,
+            state.config.session_metadata_max_depth,
+        )?;
+    }
+    if let (Some(schema), Some(metadata)) = (&state.config.session_metadata_schema, &payload.metadata) {
+        validate_metadata(schema, metadata)?;
     }
 
     let crud = SessionCrud::new(&state.db, state.redis.clone());
-    let session = Session::new(payload.title, payload.session_type, payload.metadata);
+    let owner = api_key.id();
 
-    match crud.create(session.clone()).await {
-        Ok(id) => {
-            let mut response = to_session_response(&session);
-            response.id = id.to_hex();
-            Ok((StatusCode::CREATED, Json(response)))
+    // Concurrent creates can each pass the check, so the limit may be
+    // overshot by a few; it guards against runaway clients, not exact counts
+    if let Some(limit) = state.config.max_sessions_per_key {
+        if crud.count_owned(&owner).await? >= limit {
+            return Err(AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "session_limit_reached",
+                format!("This API key already has the maximum of {} sessions", limit),
+            ));
         }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )),
     }
+
+    let session = Session::new(payload.title, payload.session_type, payload.metadata).with_owner(owner);
+
+    let id = crud.create(session.clone()).await?;
+    let mut response = to_session_response(&session);
+    response.id = id.to_hex();
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 pub async fn get_session(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-) -> Result<Json<SessionResponse>, (StatusCode, Json<MessageResponse2>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let oid = parse_id(&id)?;
 
-    let crud = SessionCrud::new(&state.db, state.redis.clone());
+    let crud = session_crud(&state, &api_key)
+        .with_stale_ttl(state.config.session_stale_ttl_secs);
+
+    // Reads through the Redis cache, so a warm poll never touches Mongo
+    let (session, stale) = crud
+        .find_by_id_or_stale(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
 
-    match crud.find_by_id(&oid).await {
-        Ok(Some(s)) => Ok(Json(to_session_response(&s))),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse2 { message: "Session not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )),
+    let etag = session.etag();
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
     }
+
+    let response = SessionResponse {
+        stale,
+        ..to_session_response(&session)
+    };
+    Ok(([(header::ETAG, etag)], Json(response)).into_response())
 }
 
 pub async fn list_sessions(
     State(state): State<AppState>,
-) -> Result<Json<SessionListResponse>, (StatusCode, Json<MessageResponse2>)> {
-    let crud = SessionCrud::new(&state.db, state.redis.clone());
+    api_key: ApiKey,
+) -> Result<Json<SessionListResponse>, AppError> {
+    let crud = session_crud(&state, &api_key);
 
-    let sessions = crud.find_all(50).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )
-    })?;
+    let sessions = crud.find_all(50).await?;
 
     let total = crud.count().await.unwrap_or(0);
 
@@ -123,165 +241,1095 @@ pub async fn list_sessions(
     }))
 }
 
+/// Longest snippet returned by message search
+const SNIPPET_CHARS: usize = 240;
+
+/// Words of a `$text` query worth highlighting: quotes are dropped and
+/// negated (`-word`) terms skipped
+pub fn search_terms(q: &str) -> Vec<String> {
+    q.split_whitespace()
+        .filter(|t| !t.starts_with('-'))
+        .map(|t| t.trim_matches('"').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max_chars).collect::<String>())
+    }
+}
+
+/// Sentences of `content`, split after `.`, `!`, `?` or a line break
+fn sentences(content: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let ends = matches!(c, '.' | '!' | '?' | '\n')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if ends {
+            let end = i + c.len_utf8();
+            sentences.push(content[start..end].trim());
+            start = end;
+        }
+    }
+    sentences.push(content[start..].trim());
+
+    sentences.into_iter().filter(|s| !s.is_empty()).collect()
+}
+
+/// The first sentence mentioning one of `terms`, with the sentences around it
+/// when they fit in `SNIPPET_CHARS`. `…` marks omitted text. Falls back to
+/// the start of the content when nothing matches.
+pub fn snippet(content: &str, terms: &[String]) -> String {
+    let sentences = sentences(content);
+    let terms = terms.iter().map(|t| t.to_lowercase()).collect::<Vec<_>>();
+
+    let hit = sentences.iter().position(|s| {
+        let lower = s.to_lowercase();
+        terms.iter().any(|t| lower.contains(t.as_str()))
+    });
+    let Some(hit) = hit else {
+        return truncate_chars(content.trim(), SNIPPET_CHARS);
+    };
+
+    let window = |first: usize, last: usize| {
+        let mut text = sentences[first..=last].join(" ");
+        if first > 0 {
+            text = format!("…{}", text);
+        }
+        if last + 1 < sentences.len() {
+            text.push('…');
+        }
+        text
+    };
+
+    let with_context = window(hit.saturating_sub(1), (hit + 1).min(sentences.len() - 1));
+    if with_context.chars().count() <= SNIPPET_CHARS {
+        return with_context;
+    }
+
+    let alone = window(hit, hit);
+    if alone.chars().count() <= SNIPPET_CHARS {
+        return alone;
+    }
+
+    // A single long sentence: cut around the first matching term
+    let sentence = sentences[hit];
+    let lower = sentence.to_lowercase();
+    let at = terms
+        .iter()
+        .filter_map(|t| lower.find(t.as_str()))
+        .min()
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0);
+    let start = at.saturating_sub(SNIPPET_CHARS / 3);
+    let cut = sentence.chars().skip(start).collect::<String>();
+    let text = truncate_chars(&cut, SNIPPET_CHARS);
+
+    if start > 0 || hit > 0 {
+        format!("…{}", text)
+    } else {
+        text
+    }
+}
+
+/// Messages across all sessions, by time window and/or full-text query
+pub async fn search_messages(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<SearchMessagesQuery>,
+) -> Result<Json<Paginated<SearchMessageResult>>, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(AppError::bad_request("from must be before to"));
+        }
+    }
+    if let Some(role) = query.role.as_deref() {
+        if !matches!(role, "user" | "assistant" | "system") {
+            return Err(AppError::bad_request("role must be user, assistant or system"));
+        }
+    }
+
+    let terms = query.q.as_deref().map(search_terms).unwrap_or_default();
+    let page = Page::new(query.page, query.per_page);
+    let crud = session_crud(&state, &api_key);
+
+    let search = MessageSearch {
+        text: query.q.as_deref().filter(|_| !terms.is_empty()),
+        terms: &terms,
+        from: query.from.map(bson::DateTime::from_chrono),
+        to: query.to.map(bson::DateTime::from_chrono),
+        role: query.role.as_deref(),
+    };
+    let (hits, total) = crud
+        .search_messages(&search, page.skip(), page.per_page as i64)
+        .await?;
+
+    let results = hits
+        .into_iter()
+        .map(|hit| SearchMessageResult {
+            session_id: hit.session_id.to_hex(),
+            snippet: snippet(&hit.message.content, &terms),
+            timestamp: hit.message.timestamp_rfc3339(),
+            role: hit.message.role,
+            score: hit.score,
+        })
+        .collect();
+
+    Ok(Json(Paginated::new(results, page, total)))
+}
+
 pub async fn delete_session(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-) -> Result<Json<MessageResponse2>, (StatusCode, Json<MessageResponse2>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+    Query(query): Query<DeleteSessionQuery>,
+) -> Result<Json<DeleteSessionResponse>, AppError> {
+    let oid = parse_id(&id)?;
 
-    let crud = SessionCrud::new(&state.db, state.redis.clone());
+    let crud = session_crud(&state, &api_key);
+
+    if !crud.delete(&oid).await? {
+        return Err(AppError::not_found("Session not found"));
+    }
+
+    MessageEmbeddingCrud::new(&state.db).delete_by_session(&oid).await?;
+    SessionEmbeddingCrud::new(&state.db).delete_by_session(&oid).await?;
+
+    let deleted_transcriptions = if query.cascade {
+        Some(stt_crud(&state, &api_key).delete_by_session(&id).await?)
+    } else {
+        None
+    };
+
+    Ok(Json(DeleteSessionResponse {
+        message: "Deleted successfully".to_string(),
+        deleted_transcriptions,
+    }))
+}
+
+/// Hold message content to `max_chars`: under `reject` longer content is a
+/// 400, under `truncate` it's cut down. Returns the content and whether it
+/// was truncated.
+pub fn limit_message_content(
+    content: String,
+    max_chars: usize,
+    action: &str,
+) -> Result<(String, bool), AppError> {
+    let chars = content.chars().count();
+    if chars <= max_chars {
+        return Ok((content, false));
+    }
 
-    match crud.delete(&oid).await {
-        Ok(true) => Ok(Json(MessageResponse2 { message: "Deleted successfully".to_string() })),
-        Ok(false) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse2 { message: "Session not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )),
+    if action == "truncate" {
+        return Ok((content.chars().take(max_chars).collect(), true));
     }
+    Err(AppError::new(
+        StatusCode::BAD_REQUEST,
+        "message_too_long",
+        format!("Message is {} characters; the limit is {}", chars, max_chars),
+    ))
+}
+
+fn limit_message(state: &AppState, content: String) -> Result<(String, bool), AppError> {
+    limit_message_content(
+        content,
+        state.config.max_message_chars,
+        &state.config.message_too_long_action,
+    )
 }
 
 pub async fn add_message(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
     Json(payload): Json<AddMessageRequest>,
-) -> Result<Json<MessageResponse>, (StatusCode, Json<MessageResponse2>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: e.to_string() }),
-        ));
-    }
-
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+) -> Result<Json<MessageResponse>, AppError> {
+    payload.validate()?;
 
-    let crud = SessionCrud::new(&state.db, state.redis.clone());
-    let message = Message::new(payload.role, payload.content);
+    let oid = parse_id(&id)?;
+    let (content, truncated) = limit_message(&state, payload.content)?;
 
-    match crud.add_message(&oid, message.clone()).await {
-        Ok(true) => Ok(Json(to_message_response(&message))),
-        Ok(false) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse2 { message: "Session not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )),
+    let crud = session_crud(&state, &api_key);
+    let message = Message::new(payload.role, content).with_truncated(truncated);
+
+    if crud.add_message(&oid, message.clone()).await? {
+        spawn_reindex(&state, oid);
+        Ok(Json(to_message_response(&message)))
+    } else {
+        Err(AppError::not_found("Session not found"))
     }
 }
 
-pub async fn chat(
+/// Import pre-transcribed history. Supplied timestamps are preserved.
+pub async fn add_messages_bulk(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-    Json(payload): Json<ChatRequest>,
-) -> Result<Json<ChatResponse>, (StatusCode, Json<MessageResponse2>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: e.to_string() }),
-        ));
-    }
-
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse2 { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+    Json(payload): Json<BulkAddMessagesRequest>,
+) -> Result<Json<BulkAddMessagesResponse>, AppError> {
+    payload.validate()?;
 
-    let crud = SessionCrud::new(&state.db, state.redis.clone());
+    let oid = parse_id(&id)?;
 
-    // Get session
-    let session = crud.find_by_id(&oid).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )
-    })?;
+    let messages = payload
+        .messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, m)| {
+            let (content, truncated) = limit_message(&state, m.content).map_err(|mut e| {
+                e.body.message = format!("messages[{}]: {}", index, e.body.message);
+                e
+            })?;
+            let mut message = Message::new(m.role, content).with_truncated(truncated);
+            if let Some(ts) = m.timestamp.and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok()) {
+                message.timestamp = bson::DateTime::from_millis(ts.timestamp_millis());
+            }
+            Ok(message)
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let crud = session_crud(&state, &api_key);
+    if !crud.add_messages(&oid, &messages).await? {
+        return Err(AppError::not_found("Session not found"));
+    }
+    spawn_reindex(&state, oid);
+
+    let message_count = crud.message_count(&oid).await?.unwrap_or(messages.len());
+
+    Ok(Json(BulkAddMessagesResponse {
+        added: messages.len(),
+        message_count,
+    }))
+}
+
+/// Messages embedded per provider request
+const EMBED_BATCH_SIZE: usize = 64;
+
+/// Embed every message of a session so `chat?retrieval=true` can recall
+/// relevant history and `/similar` can compare it with other sessions.
+/// Re-indexing replaces the previous vectors.
+pub async fn index_session(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<IndexSessionResponse>, AppError> {
+    let oid = parse_id(&id)?;
+
+    let session = session_crud(&state, &api_key)
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let indexed = index_embeddings(&state, oid, &session).await?;
+
+    Ok(Json(IndexSessionResponse {
+        session_id: id,
+        indexed,
+        model: state.config.embedding_model.clone(),
+    }))
+}
+
+/// Store a vector per message of `session`, and their mean as the session's
+/// own vector. Returns how many messages were embedded.
+pub(crate) async fn index_embeddings(
+    state: &AppState,
+    oid: ObjectId,
+    session: &Session,
+) -> Result<usize, AppError> {
+    index_messages(state, oid, session, false).await
+}
+
+/// Like `index_embeddings`, but only embeds the messages without a vector
+/// from the current model, so indexing after each message stays cheap
+async fn index_new_messages(state: &AppState, oid: ObjectId, session: &Session) -> Result<usize, AppError> {
+    index_messages(state, oid, session, true).await
+}
+
+async fn index_messages(
+    state: &AppState,
+    oid: ObjectId,
+    session: &Session,
+    only_new: bool,
+) -> Result<usize, AppError> {
+    let llm = LlmClient::new(&state.config)?;
+    let model = state.config.embedding_model.clone();
+    let crud = MessageEmbeddingCrud::new(&state.db);
+
+    let stored = if only_new {
+        crud.find_by_session(&oid)
+            .await?
+            .into_iter()
+            .filter(|e| e.model == model)
+            .map(|e| e.message_index)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let missing = session
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !stored.contains(&(*index as u32)))
+        .collect::<Vec<_>>();
+
+    let mut embeddings = Vec::with_capacity(missing.len());
+    for batch in missing.chunks(EMBED_BATCH_SIZE) {
+        let inputs = batch.iter().map(|(_, m)| m.content.clone()).collect::<Vec<_>>();
+        let vectors = llm.embed(&inputs, &model).await?;
+
+        for ((index, _), vector) in batch.iter().zip(vectors) {
+            embeddings.push(MessageEmbedding::new(oid, *index as u32, model.clone(), vector));
+        }
+    }
+
+    crud.upsert_many(&embeddings).await?;
+    crud.truncate(&oid, session.messages.len()).await?;
+
+    // Read back rather than reuse `embeddings`, so the mean also covers
+    // vectors stored by a concurrent re-index
+    let vectors = crud
+        .find_by_session(&oid)
+        .await?
+        .into_iter()
+        .filter(|e| e.model == model)
+        .map(|e| e.vector)
+        .collect::<Vec<_>>();
+
+    let session_embedding = SessionEmbeddingCrud::new(&state.db);
+    if vectors.is_empty() {
+        session_embedding.delete_by_session(&oid).await?;
+    } else {
+        let vector = embeddings::mean(&vectors);
+        let embedding = SessionEmbedding::new(oid, session.owner.clone(), model, vector);
+        session_embedding.upsert(&embedding).await?;
+    }
+
+    Ok(embeddings.len())
+}
+
+/// Embed a session's new messages in the background after they're added,
+/// so `/similar` and `chat?retrieval=true` see them without indexing the
+/// session by hand. Only with `AUTO_INDEX_SESSIONS`, and skipped when no
+/// provider can embed; failures are only logged.
+pub(crate) fn spawn_reindex(state: &AppState, oid: ObjectId) {
+    if !state.config.auto_index_sessions || LlmClient::new(&state.config).is_err() {
+        return;
+    }
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        let crud = SessionCrud::new(&state.db, state.redis.clone());
+        let session = match crud.find_by_id(&oid).await {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Failed to load session {} for re-indexing: {}", oid, e);
+                return;
+            }
+        };
+        if let Err(e) = index_new_messages(&state, oid, &session).await {
+            tracing::warn!("Failed to re-index session {}: {}", oid, e.body.message);
+        }
+    });
+}
+
+/// Default and largest `limit` for `similar_sessions`
+const SIMILAR_SESSIONS_DEFAULT: usize = 5;
+const SIMILAR_SESSIONS_MAX: usize = 50;
+
+/// The indexed sessions closest to this one by cosine similarity of their
+/// mean message embeddings, best first. Indexes the session first if it
+/// hasn't been; other sessions take part once indexed, by hand or in the
+/// background with `AUTO_INDEX_SESSIONS`.
+pub async fn similar_sessions(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+    Query(query): Query<SimilarSessionsQuery>,
+) -> Result<Json<SimilarSessionsResponse>, AppError> {
+    let oid = parse_id(&id)?;
+    let limit = query
+        .limit
+        .unwrap_or(SIMILAR_SESSIONS_DEFAULT)
+        .clamp(1, SIMILAR_SESSIONS_MAX);
+
+    let crud = session_crud(&state, &api_key);
+    let session = crud
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let embedding_crud = SessionEmbeddingCrud::new(&state.db);
+    let model = &state.config.embedding_model;
+    let target = match embedding_crud.find_by_session(&oid).await? {
+        Some(embedding) if &embedding.model == model => Some(embedding),
+        _ => {
+            index_embeddings(&state, oid, &session).await?;
+            embedding_crud.find_by_session(&oid).await?
+        }
+    };
+    let Some(target) = target else {
+        // Nothing to compare a session without messages by
+        return Ok(Json(SimilarSessionsResponse { data: Vec::new() }));
+    };
+
+    let owner = api_key.owner_scope(&state.config);
+    let candidates = embedding_crud
+        .find_candidates(&oid, model, owner.as_deref())
+        .await?
+        .into_iter()
+        .map(|e| (e.session_id, e.vector))
+        .collect::<Vec<_>>();
+    let ranked = embeddings::top_k_scored(&target.vector, &candidates, limit);
 
-    let session = session.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse2 { message: "Session not found".to_string() }),
+    let ids = ranked.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let sessions = crud.find_by_ids(&ids).await?;
+    let data = ranked
+        .into_iter()
+        .filter_map(|(id, similarity)| {
+            let session = sessions.iter().find(|s| s.id == Some(id))?;
+            Some(SimilarSession {
+                session: to_session_summary(session),
+                similarity,
+            })
+        })
+        .collect();
+
+    Ok(Json(SimilarSessionsResponse { data }))
+}
+
+/// Indices of the `k` indexed messages most similar to `text`, in
+/// chronological order. `None` if the session hasn't been indexed.
+async fn retrieve_relevant(
+    state: &AppState,
+    oid: &ObjectId,
+    text: &str,
+    k: usize,
+) -> Result<Option<Vec<usize>>, AppError> {
+    let stored = MessageEmbeddingCrud::new(&state.db).find_by_session(oid).await?;
+    if stored.is_empty() {
+        return Ok(None);
+    }
+
+    let llm = LlmClient::new(&state.config)?;
+    let query = llm
+        .embed(&[text.to_string()], &state.config.embedding_model)
+        .await?
+        .pop()
+        .unwrap_or_default();
+
+    let candidates = stored
+        .into_iter()
+        .map(|e| (e.message_index as usize, e.vector))
+        .collect::<Vec<_>>();
+
+    let mut indices = embeddings::top_k(&query, &candidates, k);
+    indices.sort_unstable();
+    Ok(Some(indices))
+}
+
+/// Pin a session so `/api/sessions` lists it first
+pub async fn pin_session(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<SessionSummary>, AppError> {
+    set_pinned(&state, &api_key, &id, true).await
+}
+
+pub async fn unpin_session(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<SessionSummary>, AppError> {
+    set_pinned(&state, &api_key, &id, false).await
+}
+
+async fn set_pinned(
+    state: &AppState,
+    api_key: &ApiKey,
+    id: &str,
+    pinned: bool,
+) -> Result<Json<SessionSummary>, AppError> {
+    let oid = parse_id(id)?;
+    let crud = session_crud(state, api_key);
+
+    if !crud.set_pinned(&oid, pinned).await? {
+        return Err(AppError::not_found("Session not found"));
+    }
+
+    let session = crud
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    Ok(Json(to_session_summary(&session)))
+}
+
+/// Last message of the session (of `role`, if given), or 204 if there is none
+pub async fn latest_message(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+    Query(query): Query<LatestMessageQuery>,
+) -> Result<Response, AppError> {
+    let oid = parse_id(&id)?;
+
+    let message = session_crud(&state, &api_key)
+        .latest_message(&oid, query.role.as_deref())
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    Ok(match message {
+        Some(message) => Json(to_message_response(&message)).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+/// Object field names only: no empty segments and nothing Mongo would read
+/// as an operator or expression
+pub fn validate_metadata_key(key: &str) -> Result<(), AppError> {
+    let valid = key
+        .split('.')
+        .all(|segment| !segment.is_empty() && !segment.starts_with('$'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::bad_request(
+            "key must be a dot-separated path of field names, e.g. config.theme",
+        ))
+    }
+}
+
+/// Most linked transcriptions included in a session bundle
+const BUNDLE_MAX_TRANSCRIPTIONS: i64 = 500;
+
+/// Download the session and its linked transcriptions as a ZIP, see
+/// `services::bundle`. The archive is written into a small in-memory pipe
+/// and streamed out as it's produced.
+pub async fn session_bundle(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let oid = parse_id(&id)?;
+
+    let session = session_crud(&state, &api_key)
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let transcriptions = stt_crud(&state, &api_key)
+        .find_by_session(&id, BUNDLE_MAX_TRANSCRIPTIONS)
+        .await?
+        .iter()
+        .map(stt_response)
+        .collect::<Vec<_>>();
+
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Err(e) = bundle::write_bundle(writer, &session, &transcriptions).await {
+            tracing::warn!("Failed to write bundle for session {}: {}", oid, e);
+        }
+    });
+
+    let disposition = format!("attachment; filename=\"session-{}.zip\"", id);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+/// Read session metadata without loading its messages. `?key=` narrows it
+/// to one nested value.
+pub async fn get_metadata(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+    Query(query): Query<MetadataQuery>,
+) -> Result<Json<SessionMetadataResponse>, AppError> {
+    let oid = parse_id(&id)?;
+    if let Some(key) = &query.key {
+        validate_metadata_key(key)?;
+    }
+
+    let value = session_crud(&state, &api_key)
+        .metadata(&oid, query.key.as_deref())
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let value = match (value, &query.key) {
+        (Some(value), _) => value.into_relaxed_extjson(),
+        (None, Some(_)) => return Err(AppError::not_found("Metadata key not found")),
+        (None, None) => serde_json::Value::Null,
+    };
+
+    Ok(Json(SessionMetadataResponse {
+        key: query.key,
+        value,
+    }))
+}
+
+/// Title an untitled session from a chat message, in the background so the
+/// reply isn't held up. Trivial messages are skipped by the caller, so the
+/// title waits for the first substantive one.
+fn spawn_auto_title(state: &AppState, llm: LlmClient, oid: ObjectId, message: String) {
+    let state = state.clone();
+
+    tokio::spawn(async move {
+        let model = llm.default_model().to_string();
+        let title = match llm.title(&message, &model, &state.config.auto_title_prompt).await {
+            Ok(response) => clean_title(&response.content),
+            Err(e) => {
+                tracing::warn!("Failed to generate title for session {}: {}", oid, e);
+                return;
+            }
+        };
+
+        if let Some(title) = title {
+            let crud = SessionCrud::new(&state.db, state.redis.clone());
+            if let Err(e) = crud.set_title_if_missing(&oid, title).await {
+                tracing::warn!("Failed to store title for session {}: {}", oid, e);
+            }
+        }
+    });
+}
+
+const RETITLE_DEFAULT_WORDS: u32 = 6;
+const RETITLE_MAX_WORDS: u32 = 20;
+
+/// Title the session from its whole conversation so far, replacing any
+/// title it had. Long conversations are condensed first so they fit.
+pub async fn retitle_session(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Path(id): Path<String>,
+    Query(query): Query<RetitleQuery>,
+) -> Result<Json<RetitleResponse>, AppError> {
+    let oid = parse_id(&id)?;
+    let max_words = query.max_words.unwrap_or(RETITLE_DEFAULT_WORDS);
+    if !(1..=RETITLE_MAX_WORDS).contains(&max_words) {
+        return Err(AppError::bad_request(format!(
+            "max_words must be between 1 and {}",
+            RETITLE_MAX_WORDS
+        )));
+    }
+
+    let crud = session_crud(&state, &api_key);
+    let session = crud
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+    if session.messages.is_empty() {
+        return Err(AppError::bad_request("Session has no messages to title"));
+    }
+
+    let lines = session
+        .messages
+        .iter()
+        .map(|m| format!("{}: {}", m.role, m.content))
+        .collect::<Vec<_>>();
+
+    let llm = LlmClient::new(&state.config)?.with_user(end_user.0);
+    let model = llm.default_model().to_string();
+    let conversation = llm.condense(&lines, &model).await?;
+
+    let prompt = format!(
+        "Write a short title (at most {} words) for this conversation. Reply with the title only.",
+        max_words
+    );
+    let response = llm.title(&conversation, &model, &prompt).await?;
+    let title = clean_title(&response.content).ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_GATEWAY,
+            "invalid_provider_response",
+            "The model returned an empty title",
         )
     })?;
 
-    // Build context from previous messages
-    let context_messages = session.get_context_messages(10);
+    if !crud.update_title(&oid, title.clone()).await? {
+        return Err(AppError::not_found("Session not found"));
+    }
+
+    Ok(Json(RetitleResponse { title }))
+}
+
+const DEFAULT_SYSTEM_PROMPT: &str =
+    "You are Cleuly, a helpful AI assistant. Provide concise, helpful responses.";
+
+/// Sent after the partial reply when finishing an incomplete message
+const CONTINUE_PROMPT: &str =
+    "Your last reply was cut off. Continue it exactly where it stopped, without repeating anything.";
+
+/// The new message, preceded by the most relevant (if indexed and requested)
+/// or most recent messages of the session
+async fn chat_prompt(
+    state: &AppState,
+    session: Option<&Session>,
+    oid: &ObjectId,
+    message: &str,
+    query: &ChatQuery,
+) -> Result<String, AppError> {
+    let relevant = match session {
+        Some(_) if query.retrieval.unwrap_or(false) => {
+            retrieve_relevant(state, oid, message, query.top_k.unwrap_or(5)).await?
+        }
+        _ => None,
+    };
+
+    let context_messages = match (session, relevant) {
+        (Some(session), Some(indices)) => indices
+            .into_iter()
+            .filter_map(|i| session.messages.get(i))
+            .collect(),
+        (Some(session), None) => session.get_context_messages(10),
+        (None, _) => Vec::new(),
+    };
     let context = context_messages
         .iter()
         .map(|m| format!("{}: {}", m.role, m.content))
         .collect::<Vec<_>>()
         .join("\n");
 
-    let prompt = if context.is_empty() {
-        payload.message.clone()
+    Ok(if context.is_empty() {
+        message.to_string()
     } else {
-        format!("Previous conversation:\n{}\n\nUser: {}", context, payload.message)
+        format!("Previous conversation:\n{}\n\nUser: {}", context, message)
+    })
+}
+
+pub async fn chat(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Path(id): Path<String>,
+    Query(query): Query<ChatQuery>,
+    Json(mut payload): Json<ChatRequest>,
+) -> Result<(ServerTiming, Json<ChatResponse>), AppError> {
+    payload.validate()?;
+    let mut timing = ServerTiming::new();
+
+    let oid = parse_id(&id)?;
+    let (message, truncated) = limit_message(&state, payload.message)?;
+    payload.message = message;
+
+    let crud = session_crud(&state, &api_key);
+
+    // Get session. In degraded mode an unreachable database means answering
+    // without history rather than failing the whole turn.
+    let session = match timing.measure("db", crud.find_by_id(&oid)).await {
+        Ok(Some(session)) => Some(session),
+        Ok(None) => return Err(AppError::not_found("Session not found")),
+        Err(e) if state.config.degraded_mode && database::is_unavailable(&e) => {
+            tracing::warn!("Database unavailable, answering chat without history: {}", e);
+            None
+        }
+        Err(e) => return Err(e.into()),
     };
 
+    let prompt = chat_prompt(&state, session.as_ref(), &oid, &payload.message, &query).await?;
+
     // Get AI response
-    let llm = LlmClient::new().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )
-    })?;
+    let llm = LlmClient::new(&state.config)?.with_user(end_user.0);
 
-    let model = payload.model.unwrap_or_else(|| {
-        env::var("DEFAULT_MODEL").unwrap_or_else(|_| "xiaomi/mimo-v2-flash:free".to_string())
-    });
+    let model = payload
+        .model
+        .unwrap_or_else(|| state.config.default_model.clone());
 
-    let system_prompt = payload.system_prompt.as_deref().unwrap_or(
-        "You are Cleuly, a helpful AI assistant. Provide concise, helpful responses."
-    );
+    let system_prompt = payload.system_prompt.as_deref().unwrap_or(DEFAULT_SYSTEM_PROMPT);
 
+    let max_tokens = payload.max_tokens.unwrap_or(1000);
+    let temperature = payload.temperature.unwrap_or(0.7);
+
+    let start = Instant::now();
     let result = llm
-        .complete(&prompt, &model, Some(system_prompt), Some(1000), Some(0.7))
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse2 { message: e.to_string() }),
-            )
-        })?;
+        .complete(&prompt, &model, Some(system_prompt), Some(max_tokens), Some(temperature))
+        .await?;
+    let latency = start.elapsed();
+    timing.record("llm", latency);
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+    let latency_ms = latency.as_millis() as u64;
 
     // Save user message and AI response
-    let user_message = Message::user(payload.message);
-    let assistant_message = Message::assistant(result.content.clone());
+    let user_message = Message::user(payload.message).with_truncated(truncated);
+    let assistant_message = Message::assistant(result.content.clone())
+        .with_meta(MessageMeta {
+            model: model.clone(),
+            temperature: Some(temperature),
+            max_tokens: Some(max_tokens),
+            system_prompt: Some(system_prompt.to_string()),
+            usage: result.usage,
+            latency_ms,
+        })
+        .with_provider_response_id(result.id.clone());
 
-    crud.add_message(&oid, user_message.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )
-    })?;
+    let persisted = match &session {
+        Some(_) if query.persist.unwrap_or(true) && state.config.persistence.stores() => {
+            let messages = [user_message.clone(), assistant_message.clone()];
+            let saved = timing.measure("db", crud.add_messages(&oid, &messages)).await;
+            match saved {
+                Ok(_) => {
+                    spawn_reindex(&state, oid);
+                    true
+                }
+                Err(e) if state.config.degraded_mode && database::is_unavailable(&e) => {
+                    tracing::warn!("Database unavailable, chat turn not saved: {}", e);
+                    false
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        _ => false,
+    };
 
-    crud.add_message(&oid, assistant_message.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse2 { message: e.to_string() }),
-        )
-    })?;
+    let untitled = session.as_ref().is_some_and(|s| s.title.is_none());
+    if persisted
+        && untitled
+        && state.config.auto_title
+        && is_substantive(&user_message.content, state.config.auto_title_min_words)
+    {
+        spawn_auto_title(&state, llm, oid, user_message.content.clone());
+    }
 
-    Ok(Json(ChatResponse {
+    Ok((timing, Json(ChatResponse {
         session_id: id,
         message: to_message_response(&user_message),
         response: to_message_response(&assistant_message),
         model,
-    }))
+        finish_reason: result.finish_reason,
+        persisted,
+        reasoning: result.reasoning,
+    })))
+}
+
+fn stream_error_event(error: AppError) -> Event {
+    Event::default()
+        .event("error")
+        .json_data(error.body)
+        .unwrap_or_default()
+}
+
+/// Streaming variant of `chat`: `delta` events as text arrives, then `done`
+/// with the saved reply (or `error`). If the client disconnects or the
+/// provider fails mid-stream, the text generated so far is saved as an
+/// `incomplete` message for `POST /continue` to finish.
+pub async fn chat_stream(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Path(id): Path<String>,
+    Query(query): Query<ChatQuery>,
+    Json(mut payload): Json<ChatRequest>,
+) -> Result<Sse<KeepAliveStream<UnboundedReceiver<Result<Event, Infallible>>>>, AppError> {
+    payload.validate()?;
+
+    let oid = parse_id(&id)?;
+    let (message, truncated) = limit_message(&state, payload.message)?;
+    payload.message = message;
+
+    let crud = session_crud(&state, &api_key);
+    let session = crud
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let prompt = chat_prompt(&state, Some(&session), &oid, &payload.message, &query).await?;
+
+    let llm = LlmClient::new(&state.config)?.with_user(end_user.0);
+
+    let model = payload
+        .model
+        .unwrap_or_else(|| state.config.default_model.clone());
+    let system_prompt = payload
+        .system_prompt
+        .unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string());
+    let max_tokens = payload.max_tokens.unwrap_or(1000);
+    let temperature = payload.temperature.unwrap_or(0.7);
+
+    let messages = vec![
+        ChatMessage::new("system", &system_prompt),
+        ChatMessage::new("user", &prompt),
+    ];
+
+    // Errors before the first byte are still plain HTTP errors
+    let start = Instant::now();
+    let mut chunks = Box::pin(
+        llm.complete_stream(messages, &model, Some(max_tokens), Some(temperature), None)
+            .await?,
+    );
+
+    let (tx, rx) = mpsc::unbounded();
+
+    tokio::spawn(async move {
+        let mut content = String::new();
+        let mut usage = None;
+        let mut error = None;
+
+        let finished = loop {
+            match chunks.next().await {
+                Some(Ok(StreamChunk::Delta(delta))) => {
+                    content.push_str(&delta);
+                    let event = Event::default()
+                        .event("delta")
+                        .json_data(serde_json::json!({ "content": delta }))
+                        .unwrap_or_default();
+
+                    // Client went away; dropping the stream cancels the provider request
+                    if tx.unbounded_send(Ok(event)).is_err() {
+                        break false;
+                    }
+                }
+                Some(Ok(StreamChunk::Usage(u))) => usage = Some(u),
+                Some(Ok(StreamChunk::Done)) | None => break true,
+                Some(Err(e)) => {
+                    error = Some(AppError::from(e));
+                    break false;
+                }
+            }
+        };
+
+        // Cut off before any text arrived; there's nothing to resume
+        if !finished && content.is_empty() {
+            if let Some(e) = error {
+                let _ = tx.unbounded_send(Ok(stream_error_event(e)));
+            }
+            return;
+        }
+
+        let assistant_message = Message::assistant(content)
+            .with_meta(MessageMeta {
+                model: model.clone(),
+                temperature: Some(temperature),
+                max_tokens: Some(max_tokens),
+                system_prompt: Some(system_prompt),
+                usage,
+                latency_ms: start.elapsed().as_millis() as u64,
+            })
+            .with_incomplete(!finished);
+
+        let user_message = Message::user(payload.message).with_truncated(truncated);
+        let messages = [user_message, assistant_message.clone()];
+        let crud = session_crud(&state, &api_key);
+        let saved = crud.add_messages(&oid, &messages).await;
+        match &saved {
+            Ok(_) => spawn_reindex(&state, oid),
+            Err(e) => tracing::warn!("Failed to save streamed chat turn for session {}: {}", oid, e),
+        }
+
+        let event = match (error, saved) {
+            (Some(e), _) => stream_error_event(e),
+            (None, Err(e)) => stream_error_event(AppError::from(e)),
+            (None, Ok(_)) => Event::default()
+                .event("done")
+                .json_data(serde_json::json!({
+                    "message": to_message_response(&assistant_message),
+                    "model": model,
+                }))
+                .unwrap_or_default(),
+        };
+        let _ = tx.unbounded_send(Ok(event));
+    });
+
+    Ok(sse_response(rx))
+}
+
+/// Finish an assistant reply left `incomplete` by an interrupted stream,
+/// using the model and settings it was started with
+pub async fn continue_message(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
+    Path(id): Path<String>,
+) -> Result<(ServerTiming, Json<MessageResponse>), AppError> {
+    let mut timing = ServerTiming::new();
+
+    let oid = parse_id(&id)?;
+
+    let crud = session_crud(&state, &api_key);
+    let session = timing
+        .measure("db", crud.find_by_id(&oid))
+        .await?
+        .ok_or_else(|| AppError::not_found("Session not found"))?;
+
+    let nothing_to_continue = || {
+        AppError::new(
+            StatusCode::CONFLICT,
+            "nothing_to_continue",
+            "The last message is not an incomplete reply",
+        )
+    };
+
+    let index = session
+        .messages
+        .len()
+        .checked_sub(1)
+        .filter(|&i| session.messages[i].incomplete)
+        .ok_or_else(nothing_to_continue)?;
+    let partial = &session.messages[index];
+    let meta = partial.meta.as_ref();
+
+    let model = meta
+        .map(|m| m.model.clone())
+        .unwrap_or_else(|| state.config.default_model.clone());
+    let system_prompt = meta
+        .and_then(|m| m.system_prompt.clone())
+        .unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string());
+    let max_tokens = meta.and_then(|m| m.max_tokens).unwrap_or(1000);
+    let temperature = meta.and_then(|m| m.temperature).unwrap_or(0.7);
+
+    let mut messages = vec![ChatMessage::new("system", &system_prompt)];
+    messages.extend(
+        session
+            .get_context_messages(10)
+            .into_iter()
+            .map(|m| ChatMessage::new(&m.role, &m.content)),
+    );
+    messages.push(ChatMessage::new("user", CONTINUE_PROMPT));
+
+    let llm = LlmClient::new(&state.config)?.with_user(end_user.0);
+    let start = Instant::now();
+    let result = llm
+        .complete_messages(messages, &model, Some(max_tokens), Some(temperature), None)
+        .await?;
+    let latency = start.elapsed();
+    timing.record("llm", latency);
+    timing.llm_call(&model, result.usage.as_ref().map(|u| u.total_tokens));
+
+    // Usage covers the continuation only; the stream was cut before reporting it
+    let meta = MessageMeta {
+        model,
+        temperature: Some(temperature),
+        max_tokens: Some(max_tokens),
+        system_prompt: Some(system_prompt),
+        usage: result.usage,
+        latency_ms: meta.map(|m| m.latency_ms).unwrap_or(0) + latency.as_millis() as u64,
+    };
+    let content = format!("{}{}", partial.content, result.content);
+
+    let completed = timing
+        .measure("db", crud.complete_message(&oid, index, &content, &meta))
+        .await?;
+    if !completed {
+        return Err(nothing_to_continue());
+    }
+
+    let message = Message {
+        content,
+        meta: Some(meta),
+        incomplete: false,
+        ..partial.clone()
+    };
+    Ok((timing, Json(to_message_response(&message))))
 }
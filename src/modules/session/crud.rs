@@ -1,15 +1,75 @@
-use crate::modules::session::model::{Message, Session};
+use crate::modules::session::model::{
+    Message, MessageEmbedding, MessageMeta, Session, SessionEmbedding,
+};
 use bson::{doc, oid::ObjectId};
-use mongodb::{Collection, Database};
+use futures::Stream;
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, Database, IndexModel};
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 
+use crate::config::{database, Persistence};
+use crate::services::cache;
+
 const COLLECTION_NAME: &str = "sessions";
+const EMBEDDINGS_COLLECTION_NAME: &str = "message_embeddings";
+const SESSION_EMBEDDINGS_COLLECTION_NAME: &str = "session_embeddings";
 const CACHE_TTL: u64 = 3600; // 1 hour
 
+/// Filters for `SessionCrud::search_messages`
+#[derive(Debug, Default)]
+pub struct MessageSearch<'a> {
+    /// `$text` query; requires the text index from `ensure_indexes`
+    pub text: Option<&'a str>,
+    /// Words of `text` a returned message must contain (case-insensitive)
+    pub terms: &'a [String],
+    pub from: Option<bson::DateTime>,
+    pub to: Option<bson::DateTime>,
+    pub role: Option<&'a str>,
+}
+
+pub struct MessageHit {
+    pub session_id: ObjectId,
+    pub message: Message,
+    /// Relevance of the session, only for text searches
+    pub score: Option<f64>,
+}
+
+/// `text` as a `$text` phrase query, which only matches content containing
+/// it verbatim (ignoring case). Quotes and backslashes inside would end the
+/// phrase early, so they're dropped. `None` when nothing searchable is left.
+pub fn text_phrase(text: &str) -> Option<String> {
+    let phrase = text
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!phrase.is_empty()).then(|| format!("\"{}\"", phrase))
+}
+
+fn escape_regex(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 pub struct SessionCrud {
     collection: Collection<Session>,
     redis: ConnectionManager,
+    /// Lifetime of the last-known copies `find_by_id_or_stale` falls back to
+    stale_ttl: Option<u64>,
+    /// Restricts reads, updates and deletes to this owner's sessions
+    owner: Option<String>,
+    /// Whether messages are written; sessions themselves always are
+    store_messages: bool,
 }
 
 impl SessionCrud {
@@ -17,13 +77,83 @@ impl SessionCrud {
         Self {
             collection: db.collection(COLLECTION_NAME),
             redis,
+            stale_ttl: None,
+            owner: None,
+            store_messages: true,
         }
     }
 
+    /// Keep a long-lived copy of each session read from Mongo, to serve
+    /// while Mongo is unreachable. `None` keeps no copies.
+    pub fn with_stale_ttl(mut self, ttl_secs: Option<u64>) -> Self {
+        self.stale_ttl = ttl_secs;
+        self
+    }
+
+    /// Only see sessions created by this owner (see `ApiKey::owner_scope`).
+    /// Anyone else's session behaves as if it didn't exist. `None` sees all.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Under `Persistence::None`, `add_message(s)` store nothing and only
+    /// report whether the session exists
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.store_messages = persistence.stores();
+        self
+    }
+
+    /// `filter` narrowed to the owner's sessions, if scoped
+    fn scoped(&self, mut filter: bson::Document) -> bson::Document {
+        if let Some(owner) = &self.owner {
+            filter.insert("owner", owner);
+        }
+        filter
+    }
+
+    fn id_filter(&self, id: &ObjectId) -> bson::Document {
+        self.scoped(doc! { "_id": id })
+    }
+
+    /// Whether a session read from the cache is visible to this owner
+    fn owns(&self, session: &Session) -> bool {
+        self.owner.is_none() || session.owner == self.owner
+    }
+
+    /// Multikey index on message timestamps and a text index on message
+    /// content, both used by `search_messages`, and an index on owners for
+    /// `count_owned`
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let indexes = [
+            IndexModel::builder().keys(doc! { "messages.timestamp": 1 }).build(),
+            IndexModel::builder().keys(doc! { "messages.content": "text" }).build(),
+            IndexModel::builder().keys(doc! { "owner": 1 }).build(),
+        ];
+        self.collection.create_indexes(indexes).await?;
+        Ok(())
+    }
+
     fn cache_key(id: &ObjectId) -> String {
         format!("session:{}", id.to_hex())
     }
 
+    fn stale_key(id: &ObjectId) -> String {
+        format!("session:stale:{}", id.to_hex())
+    }
+
+    fn generation_key(id: &ObjectId) -> String {
+        format!("session:generation:{}", id.to_hex())
+    }
+
+    /// Drop the cached copy after a write. Bumping the generation first
+    /// stops a read that began before the write from caching its older
+    /// copy afterwards (see `find_by_id`).
+    async fn invalidate(&self, id: &ObjectId) {
+        cache::bump_generation(&self.redis, &Self::generation_key(id), CACHE_TTL).await;
+        cache::del(&self.redis, &Self::cache_key(id)).await;
+    }
+
     pub async fn create(&self, session: Session) -> Result<ObjectId, mongodb::error::Error> {
         let result = self.collection.insert_one(session).await?;
         Ok(result.inserted_id.as_object_id().unwrap())
@@ -32,49 +162,184 @@ impl SessionCrud {
     pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<Session>, mongodb::error::Error> {
         // Try cache first
         let cache_key = Self::cache_key(id);
-        let mut redis = self.redis.clone();
 
-        if let Ok(cached) = redis.get::<_, String>(&cache_key).await {
+        if let Some(cached) = cache::get(&self.redis, &cache_key).await {
             if let Ok(session) = serde_json::from_str::<Session>(&cached) {
-                return Ok(Some(session));
+                return Ok(Some(session).filter(|s| self.owns(s)));
             }
         }
 
-        // Fallback to database
-        let session = self.collection.find_one(doc! { "_id": id }).await?;
+        // Fallback to database. The generation is read first: if a write
+        // bumps it meanwhile, this copy may predate the write and isn't cached.
+        let generation_key = Self::generation_key(id);
+        let generation = cache::generation(&self.redis, &generation_key).await;
+        let session = self.collection.find_one(self.id_filter(id)).await?;
 
         // Cache the result
-        if let Some(ref s) = session {
+        if let (Some(s), Some(generation)) = (&session, generation) {
             if let Ok(json) = serde_json::to_string(s) {
-                let _: Result<(), _> = redis.set_ex(&cache_key, json, CACHE_TTL).await;
+                let cached = cache::set_ex_if_generation(
+                    &self.redis,
+                    &cache_key,
+                    json.clone(),
+                    CACHE_TTL,
+                    &generation_key,
+                    generation,
+                )
+                .await;
+                if let (true, Some(ttl)) = (cached, self.stale_ttl) {
+                    cache::set_ex(&self.redis, &Self::stale_key(id), json, ttl).await;
+                }
             }
         }
 
         Ok(session)
     }
 
+    /// Like `find_by_id`, but while Mongo is unreachable serve the last copy
+    /// kept by `with_stale_ttl`, even if the regular cache entry expired.
+    /// The flag is true for such a stale copy.
+    pub async fn find_by_id_or_stale(
+        &self,
+        id: &ObjectId,
+    ) -> Result<Option<(Session, bool)>, mongodb::error::Error> {
+        match self.find_by_id(id).await {
+            Ok(session) => Ok(session.map(|s| (s, false))),
+            Err(e) if self.stale_ttl.is_some() && database::is_unavailable(&e) => {
+                let stale = cache::get(&self.redis, &Self::stale_key(id))
+                    .await
+                    .and_then(|cached| serde_json::from_str::<Session>(&cached).ok())
+                    .filter(|s| self.owns(s));
+
+                match stale {
+                    Some(session) => {
+                        tracing::warn!("Database unavailable, serving stale session {}: {}", id, e);
+                        Ok(Some((session, true)))
+                    }
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn find_all(&self, limit: i64) -> Result<Vec<Session>, mongodb::error::Error> {
         use futures::TryStreamExt;
 
         let cursor = self
             .collection
-            .find(doc! {})
-            .sort(doc! { "updated_at": -1 })
+            .find(self.scoped(doc! {}))
+            .sort(doc! { "pinned": -1, "updated_at": -1, "_id": -1 })
             .limit(limit)
             .await?;
 
         cursor.try_collect().await
     }
 
+    /// The sessions among `ids`, in no particular order
+    pub async fn find_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<Session>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let cursor = self
+            .collection
+            .find(self.scoped(doc! { "_id": { "$in": ids } }))
+            .await?;
+
+        cursor.try_collect().await
+    }
+
+    /// Sessions with a message containing `text` (see `text_phrase`), most
+    /// relevant first, and how many there are in total. Uses the text index
+    /// from `ensure_indexes`.
+    pub async fn find_containing(
+        &self,
+        text: &str,
+        limit: i64,
+    ) -> Result<(Vec<Session>, u64), mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let Some(phrase) = text_phrase(text) else {
+            return Ok((Vec::new(), 0));
+        };
+        let filter = self.scoped(doc! { "$text": { "$search": phrase } });
+
+        let sessions = self
+            .collection
+            .find(filter.clone())
+            .sort(doc! { "score": { "$meta": "textScore" }, "updated_at": -1, "_id": -1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+        let total = self.collection.count_documents(filter).await?;
+
+        Ok((sessions, total))
+    }
+
     pub async fn count(&self) -> Result<u64, mongodb::error::Error> {
-        self.collection.count_documents(doc! {}).await
+        self.collection.count_documents(self.scoped(doc! {})).await
+    }
+
+    /// Sessions created by the API key with id `owner`
+    pub async fn count_owned(&self, owner: &str) -> Result<u64, mongodb::error::Error> {
+        self.collection.count_documents(doc! { "owner": owner }).await
+    }
+
+    pub async fn message_count(&self, id: &ObjectId) -> Result<Option<usize>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let pipeline = vec![
+            doc! { "$match": self.id_filter(id) },
+            doc! { "$project": { "count": { "$size": "$messages" } } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let count = cursor
+            .try_next()
+            .await?
+            .and_then(|d| d.get_i32("count").ok())
+            .map(|c| c as usize);
+
+        Ok(count)
+    }
+
+    /// The session's metadata, or the value at a dot-separated `key` inside
+    /// it, projected server-side so messages aren't loaded. `None` if the
+    /// session doesn't exist, `Some(None)` if nothing is stored there.
+    pub async fn metadata(
+        &self,
+        id: &ObjectId,
+        key: Option<&str>,
+    ) -> Result<Option<Option<bson::Bson>>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let path = match key {
+            Some(key) => format!("$metadata.{}", key),
+            None => "$metadata".to_string(),
+        };
+
+        let pipeline = vec![
+            doc! { "$match": self.id_filter(id) },
+            doc! { "$project": { "value": path } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let Some(mut result) = cursor.try_next().await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(result.remove("value")))
     }
 
     pub async fn add_message(&self, id: &ObjectId, message: Message) -> Result<bool, mongodb::error::Error> {
+        if !self.store_messages {
+            return self.exists(id).await;
+        }
+
         let result = self
             .collection
             .update_one(
-                doc! { "_id": id },
+                self.id_filter(id),
                 doc! {
                     "$push": { "messages": bson::to_bson(&message).unwrap() },
                     "$set": { "updated_at": bson::DateTime::now() }
@@ -82,30 +347,254 @@ impl SessionCrud {
             )
             .await?;
 
-        // Invalidate cache
-        let cache_key = Self::cache_key(id);
-        let mut redis = self.redis.clone();
-        let _: Result<(), _> = redis.del(&cache_key).await;
+        self.invalidate(id).await;
+
+        Ok(result.modified_count > 0)
+    }
+
+    async fn exists(&self, id: &ObjectId) -> Result<bool, mongodb::error::Error> {
+        Ok(self.collection.count_documents(self.id_filter(id)).await? > 0)
+    }
+
+    /// Last message of a session, optionally only among messages with `role`,
+    /// without loading the rest of the array. `None` if the session is missing.
+    pub async fn latest_message(
+        &self,
+        id: &ObjectId,
+        role: Option<&str>,
+    ) -> Result<Option<Option<Message>>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let messages = match role {
+            Some(role) => bson::Bson::Document(doc! {
+                "$filter": {
+                    "input": "$messages",
+                    "as": "m",
+                    "cond": { "$eq": ["$$m.role", role] }
+                }
+            }),
+            None => bson::Bson::String("$messages".to_string()),
+        };
+
+        let pipeline = vec![
+            doc! { "$match": self.id_filter(id) },
+            doc! { "$project": { "last": { "$arrayElemAt": [messages, -1] } } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let Some(result) = cursor.try_next().await? else {
+            return Ok(None);
+        };
+
+        let message = result
+            .get_document("last")
+            .ok()
+            .and_then(|d| bson::from_document(d.clone()).ok());
+
+        Ok(Some(message))
+    }
+
+    /// One page of messages from any session with `from <= timestamp < to`,
+    /// plus the total number of matches. With `text`, only messages mentioning
+    /// one of `terms` are returned, most relevant session (`textScore`) first;
+    /// otherwise oldest first.
+    ///
+    /// The leading `$match` uses the `messages.timestamp` (or text) index to
+    /// pick sessions, but every message of those sessions is then unwound and
+    /// filtered again in memory, and the total needs a full pass. Long-lived
+    /// sessions spanning the window make this expensive, so keep windows
+    /// narrow on large datasets.
+    pub async fn search_messages(
+        &self,
+        search: &MessageSearch<'_>,
+        skip: u64,
+        limit: i64,
+    ) -> Result<(Vec<MessageHit>, u64), mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let mut range = bson::Document::new();
+        if let Some(from) = search.from {
+            range.insert("$gte", from);
+        }
+        if let Some(to) = search.to {
+            range.insert("$lt", to);
+        }
+
+        let mut session_match = self.scoped(bson::Document::new());
+        let mut message_match = bson::Document::new();
+        if !range.is_empty() {
+            session_match.insert("messages.timestamp", range.clone());
+            message_match.insert("messages.timestamp", range);
+        }
+        if let Some(role) = search.role {
+            message_match.insert("messages.role", role);
+        }
+
+        let mut pipeline = Vec::new();
+        let sort = match search.text {
+            Some(text) => {
+                session_match.insert("$text", doc! { "$search": text });
+                let pattern = search
+                    .terms
+                    .iter()
+                    .map(|t| escape_regex(t))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                message_match.insert("messages.content", doc! { "$regex": pattern, "$options": "i" });
+
+                pipeline.push(doc! { "$match": session_match });
+                pipeline.push(doc! { "$addFields": { "score": { "$meta": "textScore" } } });
+                doc! { "score": -1, "messages.timestamp": 1, "_id": 1, "message_index": 1 }
+            }
+            None => {
+                pipeline.push(doc! { "$match": session_match });
+                doc! { "messages.timestamp": 1, "_id": 1, "message_index": 1 }
+            }
+        };
+
+        pipeline.extend([
+            doc! { "$unwind": { "path": "$messages", "includeArrayIndex": "message_index" } },
+            doc! { "$match": message_match },
+            doc! { "$sort": sort },
+            doc! { "$facet": {
+                "total": [{ "$count": "count" }],
+                "hits": [
+                    // A page far past the end must not wrap to a negative skip
+                    { "$skip": i64::try_from(skip).unwrap_or(i64::MAX) },
+                    { "$limit": limit },
+                    { "$project": { "_id": 1, "message": "$messages", "score": 1 } },
+                ],
+            } },
+        ]);
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let result = cursor.try_next().await?.unwrap_or_default();
+
+        let total = database::number(
+            result
+                .get_array("total")
+                .ok()
+                .and_then(|t| t.first())
+                .and_then(|t| t.as_document())
+                .and_then(|t| t.get("count")),
+        );
+
+        let hits = result
+            .get_array("hits")
+            .map(|hits| {
+                hits.iter()
+                    .filter_map(|h| h.as_document())
+                    .filter_map(|h| {
+                        let message = h.get_document("message").ok()?.clone();
+                        Some(MessageHit {
+                            session_id: h.get_object_id("_id").ok()?,
+                            message: bson::from_document(message).ok()?,
+                            score: h.get_f64("score").ok(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok((hits, total))
+    }
+
+    /// Append several messages in order with a single `$push`/`$each`
+    pub async fn add_messages(&self, id: &ObjectId, messages: &[Message]) -> Result<bool, mongodb::error::Error> {
+        if !self.store_messages {
+            return self.exists(id).await;
+        }
+
+        let messages = messages
+            .iter()
+            .map(|m| bson::to_bson(m).unwrap())
+            .collect::<Vec<_>>();
+
+        let result = self
+            .collection
+            .update_one(
+                self.id_filter(id),
+                doc! {
+                    "$push": { "messages": { "$each": messages } },
+                    "$set": { "updated_at": bson::DateTime::now() }
+                },
+            )
+            .await?;
+
+        self.invalidate(id).await;
+
+        Ok(result.modified_count > 0)
+    }
+
+    /// Replace an incomplete message at `index` with its finished version.
+    /// `false` if it's gone or was already completed.
+    pub async fn complete_message(
+        &self,
+        id: &ObjectId,
+        index: usize,
+        content: &str,
+        meta: &MessageMeta,
+    ) -> Result<bool, mongodb::error::Error> {
+        let field = |name: &str| format!("messages.{}.{}", index, name);
+
+        let result = self
+            .collection
+            .update_one(
+                self.scoped(doc! { "_id": id, field("incomplete"): true }),
+                doc! {
+                    "$set": {
+                        field("content"): content,
+                        field("meta"): bson::to_bson(meta).unwrap(),
+                        "updated_at": bson::DateTime::now()
+                    },
+                    "$unset": { field("incomplete"): "" }
+                },
+            )
+            .await?;
+
+        self.invalidate(id).await;
 
         Ok(result.modified_count > 0)
     }
 
     pub async fn delete(&self, id: &ObjectId) -> Result<bool, mongodb::error::Error> {
-        let result = self.collection.delete_one(doc! { "_id": id }).await?;
+        let result = self.collection.delete_one(self.id_filter(id)).await?;
 
-        // Invalidate cache
-        let cache_key = Self::cache_key(id);
-        let mut redis = self.redis.clone();
-        let _: Result<(), _> = redis.del(&cache_key).await;
+        // Invalidate cache, including the stale copy
+        self.invalidate(id).await;
+        cache::del(&self.redis, &Self::stale_key(id)).await;
 
         Ok(result.deleted_count > 0)
     }
 
+    /// Delete every session and clear all cached copies
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+
+        let mut redis = self.redis.clone();
+        let mut keys: Vec<String> = Vec::new();
+        match redis.scan_match::<_, String>("session:*").await {
+            Ok(mut iter) => {
+                while let Some(Ok(key)) = iter.next_item().await {
+                    keys.push(key);
+                }
+            }
+            Err(e) => cache::record_redis_error("SCAN", &e),
+        }
+        if !keys.is_empty() {
+            if let Err(e) = redis.del::<_, ()>(keys).await {
+                cache::record_redis_error("DEL", &e);
+            }
+        }
+
+        Ok(result.deleted_count)
+    }
+
     pub async fn update_title(&self, id: &ObjectId, title: String) -> Result<bool, mongodb::error::Error> {
         let result = self
             .collection
             .update_one(
-                doc! { "_id": id },
+                self.id_filter(id),
                 doc! {
                     "$set": {
                         "title": title,
@@ -115,11 +604,215 @@ impl SessionCrud {
             )
             .await?;
 
-        // Invalidate cache
-        let cache_key = Self::cache_key(id);
-        let mut redis = self.redis.clone();
-        let _: Result<(), _> = redis.del(&cache_key).await;
+        self.invalidate(id).await;
 
         Ok(result.modified_count > 0)
     }
+
+    /// Pin or unpin a session. Leaves `updated_at` alone so unpinning puts
+    /// the session back where it was. False if the session doesn't exist.
+    pub async fn set_pinned(&self, id: &ObjectId, pinned: bool) -> Result<bool, mongodb::error::Error> {
+        let result = self
+            .collection
+            .update_one(self.id_filter(id), doc! { "$set": { "pinned": pinned } })
+            .await?;
+
+        self.invalidate(id).await;
+
+        Ok(result.matched_count > 0)
+    }
+
+    /// Set the title unless the session already has one, so an automatic
+    /// title never overwrites one the user chose in the meantime
+    pub async fn set_title_if_missing(&self, id: &ObjectId, title: String) -> Result<bool, mongodb::error::Error> {
+        let result = self
+            .collection
+            .update_one(
+                self.scoped(doc! { "_id": id, "title": null }),
+                doc! {
+                    "$set": {
+                        "title": title,
+                        "updated_at": bson::DateTime::now()
+                    }
+                },
+            )
+            .await?;
+
+        if result.modified_count > 0 {
+            self.invalidate(id).await;
+        }
+
+        Ok(result.modified_count > 0)
+    }
+}
+
+pub struct MessageEmbeddingCrud {
+    collection: Collection<MessageEmbedding>,
+}
+
+impl MessageEmbeddingCrud {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection(EMBEDDINGS_COLLECTION_NAME),
+        }
+    }
+
+    /// One vector per message: `upsert_many` relies on it so concurrent
+    /// re-indexes of a session overwrite each other instead of duplicating
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "session_id": 1, "message_index": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.collection.create_index(index).await?;
+        Ok(())
+    }
+
+    /// Store `embeddings`, replacing any vector already stored for the same
+    /// message
+    pub async fn upsert_many(&self, embeddings: &[MessageEmbedding]) -> Result<(), mongodb::error::Error> {
+        for embedding in embeddings {
+            self.collection
+                .replace_one(
+                    doc! {
+                        "session_id": embedding.session_id,
+                        "message_index": embedding.message_index,
+                    },
+                    embedding,
+                )
+                .upsert(true)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the vectors of messages at `len` and after, left over from a
+    /// longer version of the session
+    pub async fn truncate(&self, session_id: &ObjectId, len: usize) -> Result<u64, mongodb::error::Error> {
+        let result = self
+            .collection
+            .delete_many(doc! { "session_id": session_id, "message_index": { "$gte": len as i64 } })
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    pub async fn find_by_session(&self, session_id: &ObjectId) -> Result<Vec<MessageEmbedding>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let cursor = self
+            .collection
+            .find(doc! { "session_id": session_id })
+            .sort(doc! { "message_index": 1 })
+            .await?;
+
+        cursor.try_collect().await
+    }
+
+    pub async fn delete_by_session(&self, session_id: &ObjectId) -> Result<u64, mongodb::error::Error> {
+        let result = self
+            .collection
+            .delete_many(doc! { "session_id": session_id })
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    /// Sessions with any message vector not made by `model`. Re-indexing a
+    /// session drops it from the results, so an interrupted re-embed
+    /// resumes where it stopped.
+    pub async fn stale_session_ids(
+        &self,
+        model: &str,
+    ) -> Result<impl Stream<Item = Result<ObjectId, mongodb::error::Error>>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let pipeline = vec![
+            doc! { "$match": { "model": { "$ne": model } } },
+            doc! { "$group": { "_id": "$session_id" } },
+        ];
+        let groups = self.collection.aggregate(pipeline).await?;
+
+        Ok(groups.try_filter_map(|group| async move { Ok(group.get_object_id("_id").ok()) }))
+    }
+
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+        Ok(result.deleted_count)
+    }
+}
+
+pub struct SessionEmbeddingCrud {
+    collection: Collection<SessionEmbedding>,
+}
+
+impl SessionEmbeddingCrud {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection(SESSION_EMBEDDINGS_COLLECTION_NAME),
+        }
+    }
+
+    /// Store `embedding`, replacing the session's previous one
+    pub async fn upsert(&self, embedding: &SessionEmbedding) -> Result<(), mongodb::error::Error> {
+        self.collection
+            .replace_one(doc! { "_id": embedding.session_id }, embedding)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_by_session(
+        &self,
+        session_id: &ObjectId,
+    ) -> Result<Option<SessionEmbedding>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "_id": session_id }).await
+    }
+
+    /// Embeddings made with `model` for every session but `exclude`, only
+    /// `owner`'s when given
+    pub async fn find_candidates(
+        &self,
+        exclude: &ObjectId,
+        model: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<SessionEmbedding>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let mut filter = doc! { "_id": { "$ne": exclude }, "model": model };
+        if let Some(owner) = owner {
+            filter.insert("owner", owner);
+        }
+
+        let cursor = self.collection.find(filter).await?;
+        cursor.try_collect().await
+    }
+
+    pub async fn delete_by_session(&self, session_id: &ObjectId) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_one(doc! { "_id": session_id }).await?;
+        Ok(result.deleted_count)
+    }
+
+    /// Sessions whose vector was not made by `model`, see
+    /// `MessageEmbeddingCrud::stale_session_ids`
+    pub async fn stale_session_ids(
+        &self,
+        model: &str,
+    ) -> Result<impl Stream<Item = Result<ObjectId, mongodb::error::Error>>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let cursor = self
+            .collection
+            .clone_with_type::<bson::Document>()
+            .find(doc! { "model": { "$ne": model } })
+            .projection(doc! { "_id": 1 })
+            .await?;
+
+        Ok(cursor.try_filter_map(|embedding| async move {
+            Ok(embedding.get_object_id("_id").ok())
+        }))
+    }
+
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+        Ok(result.deleted_count)
+    }
 }
@@ -1,11 +1,40 @@
 use bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
+use crate::modules::ai::schema::UsageInfo;
+
+/// What happens to message content over `MAX_MESSAGE_CHARS`
+pub const MESSAGE_TOO_LONG_ACTIONS: &[&str] = &["reject", "truncate"];
+
+/// Parameters that produced an assistant message, so a turn can be reproduced
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageMeta {
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub system_prompt: Option<String>,
+    pub usage: Option<UsageInfo>,
+    pub latency_ms: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
     pub role: String,
     pub content: String,
     pub timestamp: bson::DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MessageMeta>,
+    /// A streamed reply cut short by a disconnect or provider error, kept so
+    /// `POST /api/session/{id}/continue` can finish it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub incomplete: bool,
+    /// The provider's completion id, for matching a reply against the
+    /// provider's own logs and usage dashboard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_response_id: Option<String>,
+    /// Content was cut to `MAX_MESSAGE_CHARS`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
 }
 
 impl Message {
@@ -14,9 +43,34 @@ impl Message {
             role,
             content,
             timestamp: bson::DateTime::now(),
+            meta: None,
+            incomplete: false,
+            provider_response_id: None,
+            truncated: false,
         }
     }
 
+    pub fn with_meta(mut self, meta: MessageMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    pub fn with_incomplete(mut self, incomplete: bool) -> Self {
+        self.incomplete = incomplete;
+        self
+    }
+
+    pub fn with_truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
+
+    /// Providers that omit the id report it as an empty string; that is not stored
+    pub fn with_provider_response_id(mut self, id: String) -> Self {
+        self.provider_response_id = Some(id).filter(|id| !id.is_empty());
+        self
+    }
+
     pub fn user(content: String) -> Self {
         Self::new("user".to_string(), content)
     }
@@ -42,6 +96,13 @@ pub struct Session {
     pub session_type: String,
     pub messages: Vec<Message>,
     pub metadata: Option<serde_json::Value>,
+    /// Pinned sessions are listed first
+    #[serde(default)]
+    pub pinned: bool,
+    /// Id of the API key that created the session (`ApiKey::id`). Missing on
+    /// sessions created before sessions had owners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
     pub created_at: bson::DateTime,
     pub updated_at: bson::DateTime,
 }
@@ -55,11 +116,18 @@ impl Session {
             session_type: session_type.unwrap_or_else(|| "general".to_string()),
             messages: Vec::new(),
             metadata,
+            pinned: false,
+            owner: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    pub fn with_owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
         self.updated_at = bson::DateTime::now();
@@ -78,7 +146,72 @@ impl Session {
         self.created_at.try_to_rfc3339_string().unwrap_or_default()
     }
 
+    /// Weak ETag derived from the last update time, message count and pin.
+    /// Pinning doesn't touch `updated_at`, so it's part of the tag itself.
+    pub fn etag(&self) -> String {
+        let pinned = if self.pinned { "-pinned" } else { "" };
+        format!(
+            "W/\"{}-{}{}\"",
+            self.updated_at.timestamp_millis(),
+            self.messages.len(),
+            pinned
+        )
+    }
+
     pub fn updated_at_rfc3339(&self) -> String {
         self.updated_at.try_to_rfc3339_string().unwrap_or_default()
     }
 }
+
+/// Vector for one session message, stored in `message_embeddings`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageEmbedding {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub session_id: ObjectId,
+    /// Position of the message in `Session::messages`
+    pub message_index: u32,
+    pub model: String,
+    pub vector: Vec<f32>,
+    pub created_at: bson::DateTime,
+}
+
+impl MessageEmbedding {
+    pub fn new(session_id: ObjectId, message_index: u32, model: String, vector: Vec<f32>) -> Self {
+        Self {
+            id: None,
+            session_id,
+            message_index,
+            model,
+            vector,
+            created_at: bson::DateTime::now(),
+        }
+    }
+}
+
+/// One vector for a whole session, the mean of its message embeddings,
+/// stored in `session_embeddings` for finding similar sessions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionEmbedding {
+    /// The session's id; there is one embedding per session
+    #[serde(rename = "_id")]
+    pub session_id: ObjectId,
+    /// Copied from the session so lookups can be scoped without a join
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub model: String,
+    pub vector: Vec<f32>,
+    pub updated_at: bson::DateTime,
+}
+
+impl SessionEmbedding {
+    pub fn new(session_id: ObjectId, owner: Option<String>, model: String, vector: Vec<f32>) -> Self {
+        Self {
+            session_id,
+            owner,
+            model,
+            vector,
+            updated_at: bson::DateTime::now(),
+        }
+    }
+}
@@ -12,6 +12,18 @@ pub fn routes() -> Router<AppState> {
         .route("/api/session/{id}", get(controller::get_session))
         .route("/api/session/{id}", delete(controller::delete_session))
         .route("/api/session/{id}/message", post(controller::add_message))
+        .route("/api/session/{id}/messages/bulk", post(controller::add_messages_bulk))
+        .route("/api/session/{id}/latest", get(controller::latest_message))
+        .route("/api/session/{id}/metadata", get(controller::get_metadata))
+        .route("/api/session/{id}/bundle", get(controller::session_bundle))
+        .route("/api/session/{id}/index", post(controller::index_session))
+        .route("/api/session/{id}/similar", get(controller::similar_sessions))
         .route("/api/session/{id}/chat", post(controller::chat))
+        .route("/api/session/{id}/chat/stream", post(controller::chat_stream))
+        .route("/api/session/{id}/continue", post(controller::continue_message))
+        .route("/api/session/{id}/retitle", post(controller::retitle_session))
+        .route("/api/session/{id}/pin", post(controller::pin_session))
+        .route("/api/session/{id}/unpin", post(controller::unpin_session))
         .route("/api/sessions", get(controller::list_sessions))
+        .route("/api/search/messages", get(controller::search_messages))
 }
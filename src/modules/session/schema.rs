@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::modules::session::model::MessageMeta;
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateSessionRequest {
@@ -17,57 +19,232 @@ pub struct AddMessageRequest {
     pub content: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct BulkMessage {
+    #[validate(length(min = 1, message = "Role cannot be empty"))]
+    pub role: String,
+    #[validate(length(min = 1, message = "Content cannot be empty"))]
+    pub content: String,
+    /// RFC 3339 timestamp to preserve; defaults to now
+    #[validate(custom(function = "validate_timestamp"))]
+    pub timestamp: Option<String>,
+}
+
+fn validate_timestamp(timestamp: &str) -> Result<(), ValidationError> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|_| ())
+        .map_err(|_| {
+            ValidationError::new("timestamp")
+                .with_message("Timestamp must be RFC 3339, e.g. 2026-01-31T09:30:00Z".into())
+        })
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BulkAddMessagesRequest {
+    #[validate(
+        length(min = 1, max = 1000, message = "Between 1 and 1000 messages per import"),
+        nested
+    )]
+    pub messages: Vec<BulkMessage>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct BulkAddMessagesResponse {
+    pub added: usize,
+    pub message_count: usize,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ChatRequest {
     #[validate(length(min = 1, message = "Message cannot be empty"))]
     pub message: String,
     pub model: Option<String>,
     pub system_prompt: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatQuery {
+    /// Use the most semantically relevant indexed messages as context
+    /// instead of the most recent ones. Requires `POST /index` first.
+    pub retrieval: Option<bool>,
+    /// Number of messages to retrieve (default 5)
+    pub top_k: Option<usize>,
+    /// `false` answers from the session's context without saving the
+    /// exchange, e.g. to preview a reply. Non-streaming `chat` only.
+    pub persist: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LatestMessageQuery {
+    /// Only consider messages with this role
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct IndexSessionResponse {
+    pub session_id: String,
+    pub indexed: usize,
+    pub model: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct SessionResponse {
     pub id: String,
     pub title: Option<String>,
     pub session_type: String,
     pub messages: Vec<MessageResponse>,
     pub message_count: usize,
+    pub pinned: bool,
     pub created_at: String,
     pub updated_at: String,
+    /// Served from the last-known copy while the database was unreachable
+    pub stale: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct MessageResponse {
     pub role: String,
     pub content: String,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MessageMeta>,
+    /// Cut short mid-stream; `POST /continue` finishes it
+    pub incomplete: bool,
+    /// The provider's completion id, when it reported one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_response_id: Option<String>,
+    /// Content was cut to `MAX_MESSAGE_CHARS`
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetadataQuery {
+    /// Dot-separated path into the metadata, e.g. `config.theme`
+    pub key: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct SessionMetadataResponse {
+    /// Omitted when the whole metadata object was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct SessionListResponse {
     pub data: Vec<SessionSummary>,
     pub total: u64,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct SessionSummary {
     pub id: String,
     pub title: Option<String>,
     pub session_type: String,
     pub message_count: usize,
+    pub pinned: bool,
     pub created_at: String,
     pub updated_at: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RetitleQuery {
+    /// Longest title to ask for, in words (default 6, at most 20)
+    pub max_words: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct RetitleResponse {
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SimilarSessionsQuery {
+    /// Number of sessions to return (default 5, at most 50)
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct SimilarSessionsResponse {
+    pub data: Vec<SimilarSession>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct SimilarSession {
+    #[serde(flatten)]
+    pub session: SessionSummary,
+    /// Cosine similarity to the requested session, from -1 to 1
+    pub similarity: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct ChatResponse {
     pub session_id: String,
     pub message: MessageResponse,
     pub response: MessageResponse,
     pub model: String,
+    /// `length` means the reply was cut off by `max_tokens`
+    pub finish_reason: Option<String>,
+    /// False when the turn wasn't saved: `persist=false`, `PERSISTENCE=none`,
+    /// or the database was unreachable under `DEGRADED_MODE`
+    pub persisted: bool,
+    /// The model's reasoning, kept out of `response`. Not saved with the turn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteSessionQuery {
+    /// Also delete the session's STT transcriptions
+    #[serde(default)]
+    pub cascade: bool,
 }
 
 #[derive(Debug, Serialize)]
-pub struct MessageResponse2 {
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct DeleteSessionResponse {
     pub message: String,
+    /// Only set with `cascade=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_transcriptions: Option<u64>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct SearchMessagesQuery {
+    /// Full-text query; results are then ordered by relevance
+    pub q: Option<String>,
+    /// Inclusive lower bound (RFC 3339)
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exclusive upper bound (RFC 3339)
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub role: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct SearchMessageResult {
+    pub session_id: String,
+    pub role: String,
+    pub timestamp: String,
+    /// The matching sentence with its neighbours, or the start of the message
+    pub snippet: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
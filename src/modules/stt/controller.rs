@@ -1,25 +1,50 @@
 use axum::{
-    extract::{Multipart, Path, Query, State},
+    extract::{multipart::MultipartError, Multipart, Path, Query, State},
     http::StatusCode,
+    response::sse::{Event, KeepAliveStream, Sse},
     Json,
 };
 use bson::oid::ObjectId;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
-use crate::modules::session::crud::SessionCrud;
+use crate::auth::{ApiKey, EndUser};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::modules::session::controller::{session_crud, spawn_reindex, to_session_summary};
 use crate::modules::session::model::Message;
+use crate::modules::session::schema::SessionListResponse;
 use crate::modules::stt::{
     crud::SttCrud,
-    model::SttTranscription,
+    model::{SttTranscription, TranscriptionMetadata, STATUS_DONE, STATUS_FAILED},
     schema::{
-        MessageResponse, TranscribeQuery, TranscribeResponse,
-        TranscribeWithAiResponse, TranscriptionListResponse,
+        DeleteTranscriptionsResponse, DownloadProgressEvent, FacetValue, LanguageCount,
+        LanguageStatsQuery, LanguageStatsResponse, MeetingQuery,
+        MeetingResponse, MessageResponse, QuickAnswerQuery, QuickAnswerResponse, SegmentEvent,
+        TranscribeAiDoneEvent, TranscribeQuery, TranscribeResponse, TranscribeUrlRequest,
+        TranscribeWithAiResponse, TranscriptionFacetsResponse, TranscriptionListQuery, TranscriptionListResponse,
     },
 };
-use crate::services::llm::LlmClient;
-use crate::services::stt::SttClient;
+use crate::services::limiter::SlotGuard;
+use crate::services::{audio, diarization};
+use crate::services::llm::{normalize_type, LlmClient, StreamChunk, LOW_CONFIDENCE_NOTE};
+use crate::services::stt::{download_audio, DownloadProgress, SttClient, SttResponse};
+use crate::sse::{cancel_on_drop, sse_response};
+use crate::timing::ServerTiming;
 use crate::AppState;
 
-fn to_response(t: &SttTranscription) -> TranscribeResponse {
+/// Transcription access for the caller, limited to their own uploads when
+/// API keys are configured
+pub(crate) fn stt_crud(state: &AppState, api_key: &ApiKey) -> SttCrud {
+    SttCrud::new(&state.db)
+        .with_owner(api_key.owner_scope(&state.config))
+        .with_persistence(state.config.persistence)
+}
+
+pub(crate) fn to_response(t: &SttTranscription) -> TranscribeResponse {
     TranscribeResponse {
         id: t.id.map(|id| id.to_hex()).unwrap_or_default(),
         text: t.text.clone(),
@@ -27,83 +52,221 @@ fn to_response(t: &SttTranscription) -> TranscribeResponse {
         duration: t.duration,
         model: t.model.clone(),
         created_at: t.created_at_rfc3339(),
+        session_message_count: None,
+        filtered_segments: None,
+        status: t.status.clone(),
+        metadata: t.metadata.clone(),
+        speaker_segments: t.speaker_segments.clone(),
+        audio_hints: Vec::new(),
     }
 }
 
-pub async fn transcribe(
-    State(state): State<AppState>,
-    Query(query): Query<TranscribeQuery>,
+/// Seconds a client is told to wait when a session has no free STT slot
+const STT_RETRY_AFTER_SECS: u64 = 2;
+
+/// Reserve one of the session's concurrent transcription slots. Requests
+/// without a session aren't limited here. The slot is freed when the
+/// guard drops.
+///
+/// With `STT_QUEUE_WAIT_MS` set, a busy session's request waits that long
+/// for a slot and then fails with a 503; otherwise it's rejected at once
+/// with a 429.
+async fn acquire_stt_slot(
+    state: &AppState,
+    session_id: Option<&str>,
+) -> Result<Option<SlotGuard>, AppError> {
+    let Some(session_id) = session_id else {
+        return Ok(None);
+    };
+
+    if let Some(wait_ms) = state.config.stt_queue_wait_ms {
+        return state
+            .stt_slots
+            .acquire(session_id, Duration::from_millis(wait_ms))
+            .await
+            .map(Some)
+            .ok_or_else(|| {
+                AppError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "queue_timeout",
+                    format!("No transcription slot for this session freed up within {}ms", wait_ms),
+                )
+                .with_retry_after(STT_RETRY_AFTER_SECS)
+            });
+    }
+
+    state
+        .stt_slots
+        .try_acquire(session_id)
+        .map(Some)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_transcriptions",
+                format!(
+                    "At most {} transcriptions may run at once for a session",
+                    state.config.stt_max_concurrent_per_session
+                ),
+            )
+            .with_retry_after(STT_RETRY_AFTER_SECS)
+        })
+}
+
+fn validate_min_confidence(query: &TranscribeQuery) -> Result<(), AppError> {
+    match query.min_confidence {
+        Some(c) if !(0.0..=1.0).contains(&c) => Err(AppError::bad_request(
+            "min_confidence must be between 0 and 1",
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub struct AudioUpload {
+    pub data: Vec<u8>,
+    /// Defaults to `audio.wav` when the part has no file name
+    pub file_name: String,
+    /// From the optional `title`, `speaker` and `tags` text parts
+    pub metadata: TranscriptionMetadata,
+}
+
+const METADATA_TITLE_MAX_CHARS: usize = 200;
+/// For `speaker` and each tag
+const METADATA_LABEL_MAX_CHARS: usize = 64;
+const METADATA_MAX_TAGS: usize = 20;
+
+/// Trimmed metadata value, `None` if blank
+fn metadata_text(field: &str, value: &str, max_chars: usize) -> Result<Option<String>, AppError> {
+    let value = value.trim();
+    if value.chars().count() > max_chars {
+        return Err(AppError::bad_request(format!(
+            "{} must be at most {} characters",
+            field, max_chars
+        )));
+    }
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+/// A body past the route's `DefaultBodyLimit` is a 413, anything else
+/// reading the upload means it ended early
+fn upload_interrupted(e: MultipartError) -> AppError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return AppError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+            format!("Upload too large: {}", e.body_text()),
+        );
+    }
+    AppError::new(
+        StatusCode::BAD_REQUEST,
+        "upload_interrupted",
+        format!("Upload interrupted: {}", e),
+    )
+}
+
+/// Read the `file` (or `audio`) part of a multipart upload, plus the
+/// `title`, `speaker` and `tags` (comma-separated, may repeat) text parts.
+/// Other parts are ignored. A body that ends early (e.g. client disconnect)
+/// is reported as `upload_interrupted`, one over the body limit as
+/// `payload_too_large`. An unsupported file is rejected by
+/// its extension before its bytes are read, and by its leading bytes after.
+pub async fn read_audio_upload(
+    config: &Config,
     mut multipart: Multipart,
-) -> Result<Json<TranscribeResponse>, (StatusCode, Json<MessageResponse>)> {
-    // Extract audio file from multipart
-    let mut audio_data: Option<Vec<u8>> = None;
-    let mut file_name: Option<String> = None;
-    let mut file_size: Option<u64> = None;
-
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: format!("Failed to read multipart: {}", e) }),
-        )
-    })? {
+) -> Result<AudioUpload, AppError> {
+    let mut audio = None;
+    let mut metadata = TranscriptionMetadata::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(upload_interrupted)? {
         let name = field.name().unwrap_or("").to_string();
 
-        if name == "file" || name == "audio" {
-            file_name = field.file_name().map(|s| s.to_string());
-            let data = field.bytes().await.map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(MessageResponse { message: format!("Failed to read file: {}", e) }),
-                )
-            })?;
-            file_size = Some(data.len() as u64);
-            audio_data = Some(data.to_vec());
+        match name.as_str() {
+            "file" | "audio" => {
+                let file_name = field
+                    .file_name()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "audio.wav".to_string());
+                check_audio_format(config, &file_name)?;
+                let data = field.bytes().await.map_err(upload_interrupted)?;
+                check_audio_signature(&data, &file_name)?;
+                audio = Some((data.to_vec(), file_name));
+            }
+            "title" => {
+                let value = field.text().await.map_err(upload_interrupted)?;
+                metadata.title = metadata_text("title", &value, METADATA_TITLE_MAX_CHARS)?;
+            }
+            "speaker" => {
+                let value = field.text().await.map_err(upload_interrupted)?;
+                metadata.speaker = metadata_text("speaker", &value, METADATA_LABEL_MAX_CHARS)?;
+            }
+            "tags" => {
+                let value = field.text().await.map_err(upload_interrupted)?;
+                for tag in value.split(',') {
+                    if let Some(tag) = metadata_text("Each tag", tag, METADATA_LABEL_MAX_CHARS)? {
+                        if !metadata.tags.contains(&tag) {
+                            metadata.tags.push(tag);
+                        }
+                    }
+                }
+            }
+            _ => {}
         }
     }
 
-    let audio_data = audio_data.ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "No audio file provided".to_string() }),
-        )
-    })?;
+    if metadata.tags.len() > METADATA_MAX_TAGS {
+        return Err(AppError::bad_request(format!(
+            "At most {} tags per transcription",
+            METADATA_MAX_TAGS
+        )));
+    }
 
-    let file_name = file_name.unwrap_or_else(|| "audio.wav".to_string());
+    let (data, file_name) = audio.ok_or_else(|| AppError::bad_request("No audio file provided"))?;
+    Ok(AudioUpload {
+        data,
+        file_name,
+        metadata,
+    })
+}
 
-    // Validate file extension
-    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
-    if !SttClient::supported_formats().contains(&extension.as_str()) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse {
-                message: format!(
-                    "Unsupported audio format. Supported: {:?}",
-                    SttClient::supported_formats()
-                ),
-            }),
+pub async fn transcribe(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<TranscribeQuery>,
+    multipart: Multipart,
+) -> Result<(ServerTiming, Json<TranscribeResponse>), AppError> {
+    let role = query.add_as.clone().unwrap_or_else(|| "user".to_string());
+    if !["user", "assistant", "system"].contains(&role.as_str()) {
+        return Err(AppError::bad_request(
+            "add_as must be one of: user, assistant, system",
         ));
     }
+    validate_min_confidence(&query)?;
+    let _slot = acquire_stt_slot(&state, query.session_id.as_deref()).await?;
+
+    // Read the whole upload before touching the database, so an interrupted
+    // upload never leaves a partial transcription behind
+    let upload = read_audio_upload(&state.config, multipart).await?;
+    let file_size = Some(upload.data.len() as u64);
+    let audio_data = upload.data;
+    let file_name = upload.file_name;
+    let metadata = upload.metadata;
+
+    let audio_hints = if state.config.audio_hints {
+        audio::audio_hints(&audio_data, &file_name)
+    } else {
+        Vec::new()
+    };
 
     // Transcribe
-    let stt = SttClient::new().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let stt = SttClient::new(&state.config)?;
+    let mut timing = ServerTiming::new();
 
-    let result = stt
-        .transcribe(audio_data, &file_name, query.language.as_deref())
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+    let mut result = timing
+        .measure("stt", stt.transcribe(audio_data, &file_name, query.language.as_deref()))
+        .await?;
+    let filtered_segments = query.min_confidence.map(|c| result.filter_low_confidence(c));
 
     // Save to database
-    let crud = SttCrud::new(&state.db);
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
     let transcription = SttTranscription::new(
         result.text.clone(),
         result.language.clone(),
@@ -112,116 +275,128 @@ pub async fn transcribe(
         Some(file_name),
         file_size,
         query.session_id.clone(),
-    );
+    )
+    .with_metadata(metadata)
+    .with_owner(api_key.id());
 
-    let id = crud.create(transcription.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let id = timing.measure("db", crud.create(transcription.clone())).await?;
 
-    // If session_id provided, add to session
+    // If session_id provided, add to session and report its new size
+    let mut session_message_count = None;
     if let Some(session_id) = query.session_id {
         if let Ok(oid) = ObjectId::parse_str(&session_id) {
-            let session_crud = SessionCrud::new(&state.db, state.redis.clone());
-            let message = Message::user(result.text.clone());
-            let _ = session_crud.add_message(&oid, message).await;
+            let session_crud = session_crud(&state, &api_key);
+            let message = Message::new(role, result.text.clone());
+            let added = timing.measure("db", session_crud.add_message(&oid, message)).await;
+            if let Ok(true) = added {
+                spawn_reindex(&state, oid);
+                session_message_count = timing
+                    .measure("db", session_crud.message_count(&oid))
+                    .await
+                    .ok()
+                    .flatten();
+            }
         }
     }
 
-    Ok(Json(TranscribeResponse {
+    Ok((timing, Json(TranscribeResponse {
         id: id.to_hex(),
         text: result.text,
         language: result.language,
         duration: result.duration,
         model: result.model,
         created_at: transcription.created_at_rfc3339(),
-    }))
+        session_message_count,
+        filtered_segments,
+        status: None,
+        metadata: transcription.metadata,
+        speaker_segments: Vec::new(),
+        audio_hints,
+    })))
+}
+
+/// How `transcribe-ai` answers a transcription, given its confidence
+struct AnswerPlan {
+    confidence: Option<f32>,
+    low_confidence: bool,
+    /// What the model is asked, or `None` to not answer at all
+    context: Option<String>,
+}
+
+/// A misheard question gets a confidently wrong answer, so below
+/// `STT_MIN_ANSWER_CONFIDENCE` the model is warned, or not asked at all
+fn answer_plan(config: &Config, result: &SttResponse) -> AnswerPlan {
+    let confidence = result.average_confidence();
+    let low_confidence = confidence
+        .zip(config.stt_min_answer_confidence)
+        .is_some_and(|(confidence, min)| confidence < min);
+
+    let context = if !low_confidence {
+        Some(result.text.clone())
+    } else if config.stt_low_confidence_action == "skip" {
+        None
+    } else {
+        Some(format!("{}\n\n{}", LOW_CONFIDENCE_NOTE, result.text))
+    };
+
+    AnswerPlan { confidence, low_confidence, context }
 }
 
 pub async fn transcribe_and_respond(
     State(state): State<AppState>,
+    api_key: ApiKey,
+    end_user: EndUser,
     Query(query): Query<TranscribeQuery>,
-    mut multipart: Multipart,
-) -> Result<Json<TranscribeWithAiResponse>, (StatusCode, Json<MessageResponse>)> {
-    // Extract audio file from multipart
-    let mut audio_data: Option<Vec<u8>> = None;
-    let mut file_name: Option<String> = None;
-    let mut file_size: Option<u64> = None;
-
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: format!("Failed to read multipart: {}", e) }),
-        )
-    })? {
-        let name = field.name().unwrap_or("").to_string();
+    multipart: Multipart,
+) -> Result<(ServerTiming, Json<TranscribeWithAiResponse>), AppError> {
+    validate_min_confidence(&query)?;
+    let _slot = acquire_stt_slot(&state, query.session_id.as_deref()).await?;
 
-        if name == "file" || name == "audio" {
-            file_name = field.file_name().map(|s| s.to_string());
-            let data = field.bytes().await.map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(MessageResponse { message: format!("Failed to read file: {}", e) }),
-                )
-            })?;
-            file_size = Some(data.len() as u64);
-            audio_data = Some(data.to_vec());
-        }
-    }
+    // Read the whole upload before touching the database, so an interrupted
+    // upload never leaves a partial transcription behind
+    let upload = read_audio_upload(&state.config, multipart).await?;
+    let file_size = Some(upload.data.len() as u64);
+    let audio_data = upload.data;
+    let file_name = upload.file_name;
+    let metadata = upload.metadata;
 
-    let audio_data = audio_data.ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "No audio file provided".to_string() }),
-        )
-    })?;
+    // Transcribe
+    let stt = SttClient::new(&state.config)?;
+    let mut timing = ServerTiming::new();
 
-    let file_name = file_name.unwrap_or_else(|| "audio.wav".to_string());
+    let mut result = timing
+        .measure("stt", stt.transcribe(audio_data, &file_name, query.language.as_deref()))
+        .await?;
+    let filtered_segments = query.min_confidence.map(|c| result.filter_low_confidence(c));
 
-    // Transcribe
-    let stt = SttClient::new().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let plan = answer_plan(&state.config, &result);
+    let ai_result = if let Some(context) = &plan.context {
+        // Get AI response (use Groq for speed, fallback to OpenRouter)
+        let llm = LlmClient::new_groq(&state.config)
+            .or_else(|_| LlmClient::new(&state.config))?
+            .with_user(end_user.0);
 
-    let result = stt
-        .transcribe(audio_data, &file_name, query.language.as_deref())
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
-
-    // Get AI response (use Groq for speed, fallback to OpenRouter)
-    let llm = LlmClient::new_groq()
-        .or_else(|_| LlmClient::new())
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+        let model = llm.default_model().to_string();
 
-    let model = llm.default_model().to_string();
+        // Answer in the requested language, or whatever Whisper detected
+        let language = query
+            .language
+            .as_deref()
+            .or(result.language.as_deref())
+            .filter(|_| state.config.match_language);
 
-    let ai_result = llm
-        .suggest(&result.text, &model, Some("interview"))
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(MessageResponse { message: e.to_string() }),
-            )
-        })?;
+        let ai_result = timing
+            .measure("llm", llm.suggest(context, &model, Some("interview"), language, None))
+            .await?;
+        timing.llm_call(&model, ai_result.usage.as_ref().map(|u| u.total_tokens));
+        Some(ai_result)
+    } else {
+        None
+    };
+    let ai_response = ai_result.as_ref().map(|r| r.content.clone());
 
     // Save to database
-    let crud = SttCrud::new(&state.db);
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
     let mut transcription = SttTranscription::new(
         result.text.clone(),
         result.language.clone(),
@@ -230,77 +405,650 @@ pub async fn transcribe_and_respond(
         Some(file_name),
         file_size,
         query.session_id.clone(),
-    );
-    transcription.ai_response = Some(ai_result.content.clone());
+    )
+    .with_metadata(metadata)
+    .with_owner(api_key.id());
+    transcription.ai_response = ai_response.clone();
 
-    let id = crud.create(transcription.clone()).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let id = timing.measure("db", crud.create(transcription.clone())).await?;
 
     // If session_id provided, add both messages to session
     if let Some(session_id) = query.session_id {
         if let Ok(oid) = ObjectId::parse_str(&session_id) {
-            let session_crud = SessionCrud::new(&state.db, state.redis.clone());
+            let session_crud = session_crud(&state, &api_key);
             let user_msg = Message::user(result.text.clone());
-            let assistant_msg = Message::assistant(ai_result.content.clone());
-            let _ = session_crud.add_message(&oid, user_msg).await;
-            let _ = session_crud.add_message(&oid, assistant_msg).await;
+            let added = timing.measure("db", session_crud.add_message(&oid, user_msg)).await;
+            if let Some(ai_result) = ai_result {
+                let assistant_msg = Message::assistant(ai_result.content)
+                    .with_provider_response_id(ai_result.id);
+                let _ = timing.measure("db", session_crud.add_message(&oid, assistant_msg)).await;
+            }
+            if let Ok(true) = added {
+                spawn_reindex(&state, oid);
+            }
         }
     }
 
-    Ok(Json(TranscribeWithAiResponse {
+    Ok((timing, Json(TranscribeWithAiResponse {
         id: id.to_hex(),
         transcription: result.text,
-        ai_response: ai_result.content,
+        ai_response: ai_response.unwrap_or_default(),
         language: result.language,
         duration: result.duration,
         model: result.model,
         created_at: transcription.created_at_rfc3339(),
-    }))
+        filtered_segments,
+        confidence: plan.confidence,
+        low_confidence: plan.low_confidence,
+    })))
 }
 
-pub async fn get_transcription(
+/// Streaming variant of `transcribe_and_respond`. Once the audio is
+/// transcribed and saved, a `transcription` event carries it, then the
+/// answer arrives as `delta` events and a final `done` with the
+/// transcription id. If the answer fails partway, the transcription and
+/// whatever text arrived are still saved and an `error` event ends the
+/// stream; failures before the transcription is saved are plain HTTP errors.
+pub async fn transcribe_and_respond_stream(
     State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<TranscribeResponse>, (StatusCode, Json<MessageResponse>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+    api_key: ApiKey,
+    end_user: EndUser,
+    Query(query): Query<TranscribeQuery>,
+    multipart: Multipart,
+) -> Result<Sse<KeepAliveStream<UnboundedReceiver<Result<Event, Infallible>>>>, AppError> {
+    validate_min_confidence(&query)?;
+    let slot = acquire_stt_slot(&state, query.session_id.as_deref()).await?;
+
+    let upload = read_audio_upload(&state.config, multipart).await?;
+    let file_size = Some(upload.data.len() as u64);
 
-    let crud = SttCrud::new(&state.db);
+    let stt = SttClient::new(&state.config)?;
+    let llm = LlmClient::new_groq(&state.config)
+        .or_else(|_| LlmClient::new(&state.config))?
+        .with_user(end_user.0);
+
+    let mut result = stt
+        .transcribe(upload.data, &upload.file_name, query.language.as_deref())
+        .await?;
+    let filtered_segments = query.min_confidence.map(|c| result.filter_low_confidence(c));
+    let plan = answer_plan(&state.config, &result);
+
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
+    let mut transcription = SttTranscription::new(
+        result.text.clone(),
+        result.language.clone(),
+        result.duration,
+        result.model.clone(),
+        Some(upload.file_name),
+        file_size,
+        query.session_id.clone(),
+    )
+    .with_metadata(upload.metadata)
+    .with_owner(api_key.id());
+    let id = crud.create(transcription.clone()).await?;
+    transcription.id = Some(id);
+
+    let (tx, rx) = mpsc::unbounded();
+    let mut response = to_response(&transcription);
+    response.filtered_segments = filtered_segments;
+    let event = Event::default().event("transcription").json_data(response);
+    let _ = tx.unbounded_send(Ok(event.unwrap_or_default()));
+
+    tokio::spawn(async move {
+        // Held until the answer is saved, even if the client disconnects
+        let _slot = slot;
+
+        let model = llm.default_model().to_string();
+        let language = query
+            .language
+            .as_deref()
+            .or(result.language.as_deref())
+            .filter(|_| state.config.match_language);
+
+        let mut content = String::new();
+        let mut error = None;
+        let chunks = match &plan.context {
+            Some(context) => {
+                Some(llm.suggest_stream(context, &model, Some("interview"), language, None).await)
+            }
+            None => None,
+        };
+        match chunks {
+            None => {}
+            Some(Ok(chunks)) => {
+                let mut chunks = Box::pin(chunks);
+                loop {
+                    match chunks.next().await {
+                        Some(Ok(StreamChunk::Delta(delta))) => {
+                            content.push_str(&delta);
+                            let event = Event::default()
+                                .event("delta")
+                                .json_data(serde_json::json!({ "content": delta }))
+                                .unwrap_or_default();
+                            // The answer is still saved for a client that went away
+                            let _ = tx.unbounded_send(Ok(event));
+                        }
+                        Some(Ok(StreamChunk::Usage(_))) => {}
+                        Some(Ok(StreamChunk::Done)) | None => break,
+                        Some(Err(e)) => {
+                            error = Some(AppError::from(e));
+                            break;
+                        }
+                    }
+                }
+            }
+            Some(Err(e)) => error = Some(AppError::from(e)),
+        }
+
+        if !content.is_empty() {
+            if let Err(e) = crud.update_ai_response(&id, content.clone()).await {
+                tracing::warn!("Failed to save streamed answer for transcription {}: {}", id, e);
+            }
+        }
+
+        if let Some(oid) = query.session_id.and_then(|s| ObjectId::parse_str(&s).ok()) {
+            let mut messages = vec![Message::user(result.text)];
+            if !content.is_empty() {
+                let incomplete = error.is_some();
+                messages.push(Message::assistant(content.clone()).with_incomplete(incomplete));
+            }
+            if let Ok(true) = session_crud(&state, &api_key).add_messages(&oid, &messages).await {
+                spawn_reindex(&state, oid);
+            }
+        }
+
+        let event = match error {
+            Some(e) => Event::default().event("error").json_data(e.body),
+            None => Event::default().event("done").json_data(TranscribeAiDoneEvent {
+                id: id.to_hex(),
+                ai_response: content,
+                model,
+                low_confidence: plan.low_confidence,
+            }),
+        };
+        let _ = tx.unbounded_send(Ok(event.unwrap_or_default()));
+    });
+
+    Ok(sse_response(rx))
+}
+
+/// Transcribe a meeting recording and label each segment with a speaker,
+/// from `DIARIZATION_URL` if configured or else the pause heuristic (see
+/// `services::diarization` for its limits). The labeled segments are stored
+/// with the transcription.
+pub async fn transcribe_meeting(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<MeetingQuery>,
+    multipart: Multipart,
+) -> Result<(ServerTiming, Json<MeetingResponse>), AppError> {
+    if query.speakers.is_some_and(|s| !(1..=20).contains(&s)) {
+        return Err(AppError::bad_request("speakers must be between 1 and 20"));
+    }
+
+    let upload = read_audio_upload(&state.config, multipart).await?;
+    let file_size = Some(upload.data.len() as u64);
+
+    let stt = SttClient::new(&state.config)?;
+    let mut timing = ServerTiming::new();
+
+    // The service needs the audio too, so keep a copy only when it's used
+    let service_audio = state.config.diarization_url.as_ref().map(|_| upload.data.clone());
+
+    let result = timing
+        .measure("stt", stt.transcribe(upload.data, &upload.file_name, query.language.as_deref()))
+        .await?;
+
+    let turns = match (&state.config.diarization_url, service_audio) {
+        (Some(url), Some(audio)) => {
+            let turns = timing
+                .measure("diarize", diarization::fetch_turns(url, audio, &upload.file_name))
+                .await;
+            turns
+                .inspect_err(|e| tracing::warn!("Diarization failed, labeling by pauses: {}", e))
+                .ok()
+                .filter(|turns| !turns.is_empty())
+        }
+        _ => None,
+    };
+
+    let (segments, method) = match turns {
+        Some(turns) => (diarization::label_segments(&result.segments, &turns), "service"),
+        None => {
+            let speakers = query.speakers.unwrap_or(state.config.diarization_max_speakers);
+            let pause = state.config.diarization_pause_secs;
+            (diarization::diarize_by_pauses(&result.segments, pause, speakers), "pauses")
+        }
+    };
+
+    let mut transcription = SttTranscription::new(
+        result.text,
+        result.language.clone(),
+        result.duration,
+        result.model.clone(),
+        Some(upload.file_name),
+        file_size,
+        None,
+    )
+    .with_metadata(upload.metadata)
+    .with_owner(api_key.id());
+    transcription.speaker_segments = segments.clone();
+
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
+    let id = timing.measure("db", crud.create(transcription.clone())).await?;
+
+    Ok((timing, Json(MeetingResponse {
+        id: id.to_hex(),
+        segments,
+        language: result.language,
+        duration: result.duration,
+        model: result.model,
+        diarization: method.to_string(),
+        created_at: transcription.created_at_rfc3339(),
+        metadata: transcription.metadata,
+    })))
+}
 
-    match crud.find_by_id(&oid).await {
-        Ok(Some(t)) => Ok(Json(to_response(&t))),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse { message: "Transcription not found".to_string() }),
+/// Stateless counterpart to `transcribe_and_respond`: transcribe and answer
+/// without writing a transcription, session message or completion, for
+/// callers that don't want the audio's content stored
+pub async fn quick_answer(
+    State(state): State<AppState>,
+    end_user: EndUser,
+    Query(query): Query<QuickAnswerQuery>,
+    multipart: Multipart,
+) -> Result<(ServerTiming, Json<QuickAnswerResponse>), AppError> {
+    let upload = read_audio_upload(&state.config, multipart).await?;
+
+    let stt = SttClient::new(&state.config)?;
+    let mut timing = ServerTiming::new();
+
+    let result = timing
+        .measure("stt", stt.transcribe(upload.data, &upload.file_name, query.language.as_deref()))
+        .await?;
+
+    let llm = LlmClient::new_groq(&state.config)
+        .or_else(|_| LlmClient::new(&state.config))?
+        .with_user(end_user.0);
+
+    let model = llm.default_model().to_string();
+    let language = query
+        .language
+        .as_deref()
+        .or(result.language.as_deref())
+        .filter(|_| state.config.match_language);
+    let suggestion_type = normalize_type(query.suggestion_type.as_deref()).unwrap_or("interview");
+
+    let answer = timing
+        .measure("llm", llm.suggest(&result.text, &model, Some(suggestion_type), language, None))
+        .await?;
+    timing.llm_call(&model, answer.usage.as_ref().map(|u| u.total_tokens));
+
+    Ok((timing, Json(QuickAnswerResponse {
+        transcription: result.text,
+        answer: answer.content,
+        language: result.language,
+    })))
+}
+
+/// Check a remote audio URL and derive the file name (and so the format)
+/// from its last path segment
+fn file_name_from_url(config: &Config, url: &str) -> Result<String, AppError> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| AppError::bad_request("Invalid URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(AppError::bad_request("URL must use http or https"));
+    }
+
+    let file_name = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("audio.wav")
+        .to_string();
+
+    check_audio_format(config, &file_name)?;
+    Ok(file_name)
+}
+
+/// Reject extensions missing from `AUDIO_FORMATS`
+fn check_audio_format(config: &Config, file_name: &str) -> Result<(), AppError> {
+    match SttClient::mime_type(config, file_name) {
+        Some(_) => Ok(()),
+        None => Err(AppError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_audio_format",
+            format!(
+                "Unsupported audio format. Supported: {:?}",
+                SttClient::supported_formats(config)
+            ),
         )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
+    }
+}
+
+/// Reject uploads whose content contradicts their extension, e.g. a text
+/// file renamed to `.mp3`
+fn check_audio_signature(data: &[u8], file_name: &str) -> Result<(), AppError> {
+    if audio::matches_signature(data, file_name) {
+        return Ok(());
+    }
+    Err(AppError::new(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "unsupported_audio_format",
+        format!("{} does not look like the audio format its extension names", file_name),
+    ))
+}
+
+/// Transcribe downloaded audio, store it and append it to the session
+async fn transcribe_downloaded(
+    state: &AppState,
+    api_key: &ApiKey,
+    payload: &TranscribeUrlRequest,
+    file_name: String,
+    audio_data: Vec<u8>,
+    timing: &mut ServerTiming,
+) -> Result<TranscribeResponse, AppError> {
+    let stt = SttClient::new(&state.config)?;
+    let file_size = audio_data.len() as u64;
+
+    let result = timing
+        .measure("stt", stt.transcribe(audio_data, &file_name, payload.language.as_deref()))
+        .await?;
+
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
+    let transcription = SttTranscription::new(
+        result.text.clone(),
+        result.language.clone(),
+        result.duration,
+        result.model.clone(),
+        Some(file_name),
+        Some(file_size),
+        payload.session_id.clone(),
+    )
+    .with_owner(api_key.id());
+
+    let id = timing.measure("db", crud.create(transcription.clone())).await?;
+    let session_message_count = timing
+        .measure("db", append_to_session(state, api_key, payload.session_id.as_deref(), &result.text))
+        .await;
+
+    Ok(TranscribeResponse {
+        id: id.to_hex(),
+        text: result.text,
+        language: result.language,
+        duration: result.duration,
+        model: result.model,
+        created_at: transcription.created_at_rfc3339(),
+        session_message_count,
+        filtered_segments: None,
+        status: None,
+        metadata: TranscriptionMetadata::default(),
+        speaker_segments: Vec::new(),
+        audio_hints: Vec::new(),
+    })
+}
+
+/// Add a transcript to the session as a user message, returning the new
+/// message count. Best effort: a missing session is ignored.
+async fn append_to_session(
+    state: &AppState,
+    api_key: &ApiKey,
+    session_id: Option<&str>,
+    text: &str,
+) -> Option<usize> {
+    let oid = session_id.and_then(|s| ObjectId::parse_str(s).ok())?;
+    let session_crud = session_crud(state, api_key);
+
+    match session_crud.add_message(&oid, Message::user(text.to_string())).await {
+        Ok(true) => {
+            spawn_reindex(state, oid);
+            session_crud.message_count(&oid).await.ok().flatten()
+        }
+        _ => None,
+    }
+}
+
+pub async fn transcribe_url(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Json(payload): Json<TranscribeUrlRequest>,
+) -> Result<(ServerTiming, Json<TranscribeResponse>), AppError> {
+    let file_name = file_name_from_url(&state.config, &payload.url)?;
+    SttClient::new(&state.config)?;
+    let _slot = acquire_stt_slot(&state, payload.session_id.as_deref()).await?;
+
+    let mut timing = ServerTiming::new();
+    let audio_data = timing
+        .measure(
+            "download",
+            download_audio(&payload.url, state.config.audio_url_allow_private, |_| {}),
+        )
+        .await?;
+
+    let response =
+        transcribe_downloaded(&state, &api_key, &payload, file_name, audio_data, &mut timing).await?;
+    Ok((timing, Json(response)))
+}
+
+type EventSender = UnboundedSender<Result<Event, Infallible>>;
+
+fn progress_event(progress: DownloadProgress) -> Event {
+    let percent = progress
+        .total
+        .filter(|&t| t > 0)
+        .map(|t| (progress.downloaded as f32 / t as f32 * 100.0).min(100.0));
+
+    Event::default()
+        .event("download_progress")
+        .json_data(DownloadProgressEvent {
+            downloaded: progress.downloaded,
+            total: progress.total,
+            percent,
+            indeterminate: progress.total.is_none(),
+        })
+        .unwrap_or_default()
+}
+
+/// SSE variant of `transcribe_url`: `download_progress` events while the
+/// file downloads, `transcribing` once it's done, then a final
+/// `transcription` (or `error`) event. A client disconnect stops the work
+/// and marks the record failed.
+pub async fn transcribe_url_stream(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Json(payload): Json<TranscribeUrlRequest>,
+) -> Result<Sse<KeepAliveStream<impl Stream<Item = Result<Event, Infallible>>>>, AppError> {
+    let file_name = file_name_from_url(&state.config, &payload.url)?;
+    SttClient::new(&state.config)?;
+    let slot = acquire_stt_slot(&state, payload.session_id.as_deref()).await?;
+
+    let (tx, rx) = mpsc::unbounded();
+    let disconnected = CancellationToken::new();
+    let cancelled = disconnected.clone();
+
+    tokio::spawn(async move {
+        // Held until the transcription finishes or is cancelled
+        let _slot = slot;
+        let result =
+            download_and_transcribe(&state, &api_key, &payload, file_name, &tx, &cancelled).await;
+        let event = match result {
+            Ok(response) => Event::default().event("transcription").json_data(response),
+            Err(e) => Event::default().event("error").json_data(e.body),
+        };
+        let _ = tx.unbounded_send(Ok(event.unwrap_or_default()));
+    });
+
+    Ok(sse_response(cancel_on_drop(rx, disconnected)))
+}
+
+/// Streamed variant of `transcribe_url`. The record is created up front as
+/// `processing` (announced in a `started` event), so a reconnecting client
+/// can poll `GET /api/stt/transcription/{id}` for its status. The provider
+/// returns the whole transcription at once, so the `segment` events and
+/// the stored segments only appear when it's done, in a single update.
+async fn download_and_transcribe(
+    state: &AppState,
+    api_key: &ApiKey,
+    payload: &TranscribeUrlRequest,
+    file_name: String,
+    tx: &EventSender,
+    cancelled: &CancellationToken,
+) -> Result<TranscribeResponse, AppError> {
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
+    let mut transcription = SttTranscription::processing(
+        state.config.stt_model.clone(),
+        Some(file_name),
+        payload.session_id.clone(),
+    )
+    .with_owner(api_key.id());
+    let id = crud.create(transcription.clone()).await?;
+    transcription.id = Some(id);
+
+    let started = Event::default()
+        .event("started")
+        .json_data(serde_json::json!({ "id": id.to_hex() }));
+    let _ = tx.unbounded_send(Ok(started.unwrap_or_default()));
+
+    let result = tokio::select! {
+        result = transcribe_segments(state, api_key, payload, transcription, tx) => result,
+        _ = cancelled.cancelled() => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "client_disconnected",
+            "The client disconnected before the transcription finished",
         )),
+    };
+    if result.is_err() {
+        let _ = crud.set_status(&id, STATUS_FAILED).await;
+    }
+    result
+}
+
+async fn transcribe_segments(
+    state: &AppState,
+    api_key: &ApiKey,
+    payload: &TranscribeUrlRequest,
+    mut transcription: SttTranscription,
+    tx: &EventSender,
+) -> Result<TranscribeResponse, AppError> {
+    let crud = SttCrud::new(&state.db).with_persistence(state.config.persistence);
+    let id = transcription.id.unwrap_or_default();
+    let file_name = transcription.file_name.clone().unwrap_or_default();
+
+    let allow_private = state.config.audio_url_allow_private;
+    let audio_data = download_audio(&payload.url, allow_private, |progress| {
+        let _ = tx.unbounded_send(Ok(progress_event(progress)));
+    })
+    .await?;
+    transcription.file_size = Some(audio_data.len() as u64);
+
+    let _ = tx.unbounded_send(Ok(Event::default().event("transcribing").data("{}")));
+
+    let stt = SttClient::new(&state.config)?;
+    let result = stt
+        .transcribe(audio_data, &file_name, payload.language.as_deref())
+        .await?;
+
+    // Providers that don't return segments yield the whole text as one
+    let segments = if result.segments.is_empty() {
+        vec![SegmentEvent {
+            index: 0,
+            start: 0.0,
+            end: result.duration.unwrap_or_default(),
+            text: result.text.clone(),
+        }]
+    } else {
+        result
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(index, s)| SegmentEvent {
+                index,
+                start: s.start,
+                end: s.end,
+                text: s.text.trim().to_string(),
+            })
+            .collect()
+    };
+
+    for segment in segments {
+        transcription.segments.push(segment.text.clone());
+        if let Ok(event) = Event::default().event("segment").json_data(&segment) {
+            let _ = tx.unbounded_send(Ok(event));
+        }
     }
+
+    transcription.text = result.text;
+    transcription.language = result.language;
+    transcription.duration = result.duration;
+    transcription.model = result.model;
+    transcription.status = Some(STATUS_DONE.to_string());
+    crud.complete(&id, &transcription).await?;
+
+    let mut response = to_response(&transcription);
+    response.session_message_count =
+        append_to_session(state, api_key, payload.session_id.as_deref(), &transcription.text).await;
+    Ok(response)
+}
+
+pub async fn get_transcription(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<TranscribeResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let crud = stt_crud(&state, &api_key);
+
+    match crud.find_by_id(&oid).await? {
+        Some(t) => Ok(Json(to_response(&t))),
+        None => Err(AppError::not_found("Transcription not found")),
+    }
+}
+
+/// Most sessions `related_sessions` returns
+const RELATED_SESSIONS_LIMIT: i64 = 50;
+
+/// Sessions with a message containing the transcription's text, e.g. the
+/// conversations a voice capture was added to
+pub async fn related_sessions(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(id): Path<String>,
+) -> Result<Json<SessionListResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let transcription = stt_crud(&state, &api_key)
+        .find_by_id(&oid)
+        .await?
+        .ok_or_else(|| AppError::not_found("Transcription not found"))?;
+
+    let (sessions, total) = session_crud(&state, &api_key)
+        .find_containing(&transcription.text, RELATED_SESSIONS_LIMIT)
+        .await?;
+
+    Ok(Json(SessionListResponse {
+        data: sessions.iter().map(to_session_summary).collect(),
+        total,
+    }))
 }
 
 pub async fn list_transcriptions(
     State(state): State<AppState>,
-) -> Result<Json<TranscriptionListResponse>, (StatusCode, Json<MessageResponse>)> {
-    let crud = SttCrud::new(&state.db);
+    api_key: ApiKey,
+    Query(query): Query<TranscriptionListQuery>,
+) -> Result<Json<TranscriptionListResponse>, AppError> {
+    let crud = stt_crud(&state, &api_key);
 
-    let transcriptions = crud.find_all(50).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let mut filter = bson::Document::new();
+    if let Some(language) = query.language {
+        filter.insert("language", language);
+    }
+    if let Some(model) = query.model {
+        filter.insert("model", model);
+    }
+
+    let transcriptions = crud.find_all(filter.clone(), 50).await?;
 
-    let total = crud.count().await.unwrap_or(0);
+    let total = crud.count(filter).await.unwrap_or(0);
 
     Ok(Json(TranscriptionListResponse {
         data: transcriptions.iter().map(to_response).collect(),
@@ -308,32 +1056,91 @@ pub async fn list_transcriptions(
     }))
 }
 
+/// Distinct languages and models with counts, for building list filters
+pub async fn transcription_facets(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+) -> Result<Json<TranscriptionFacetsResponse>, AppError> {
+    let result = stt_crud(&state, &api_key).facets().await?;
+
+    let values = |facet: &str| {
+        result
+            .get_array(facet)
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(|g| bson::from_bson::<FacetValue>(g.clone()).ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    Ok(Json(TranscriptionFacetsResponse {
+        languages: values("languages"),
+        models: values("models"),
+    }))
+}
+
+/// How many transcriptions were detected in each language, for analytics
+pub async fn language_stats(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<LanguageStatsQuery>,
+) -> Result<Json<LanguageStatsResponse>, AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(AppError::bad_request("from must be before to"));
+        }
+    }
+
+    let groups = stt_crud(&state, &api_key)
+        .language_counts(
+            query.from.map(bson::DateTime::from_chrono),
+            query.to.map(bson::DateTime::from_chrono),
+        )
+        .await?;
+
+    let languages: Vec<LanguageCount> = groups
+        .into_iter()
+        .filter_map(|g| bson::from_document(g).ok())
+        .collect();
+
+    Ok(Json(LanguageStatsResponse {
+        total: languages.iter().map(|l| l.count).sum(),
+        languages,
+    }))
+}
+
 pub async fn delete_transcription(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-) -> Result<Json<MessageResponse>, (StatusCode, Json<MessageResponse>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "Invalid ID format".to_string() }),
-        )
-    })?;
+) -> Result<Json<MessageResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
 
-    let crud = SttCrud::new(&state.db);
+    let crud = stt_crud(&state, &api_key);
 
-    match crud.delete(&oid).await {
-        Ok(true) => Ok(Json(MessageResponse { message: "Deleted successfully".to_string() })),
-        Ok(false) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse { message: "Transcription not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )),
+    if crud.delete(&oid).await? {
+        Ok(Json(MessageResponse { message: "Deleted successfully".to_string() }))
+    } else {
+        Err(AppError::not_found("Transcription not found"))
     }
 }
 
-pub async fn supported_formats() -> Json<Vec<&'static str>> {
-    Json(SttClient::supported_formats())
+/// Remove all transcriptions linked to a session, e.g. after the session
+/// itself was deleted without `cascade`
+pub async fn delete_session_transcriptions(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(session_id): Path<String>,
+) -> Result<Json<DeleteTranscriptionsResponse>, AppError> {
+    ObjectId::parse_str(&session_id).map_err(|_| AppError::invalid_id())?;
+
+    let deleted = stt_crud(&state, &api_key).delete_by_session(&session_id).await?;
+
+    Ok(Json(DeleteTranscriptionsResponse { deleted }))
+}
+
+pub async fn supported_formats(State(state): State<AppState>) -> Json<Vec<String>> {
+    Json(state.discovery.formats(&state.config).to_vec())
 }
@@ -1,36 +1,67 @@
-use crate::modules::stt::model::SttTranscription;
-use bson::{doc, oid::ObjectId};
+use crate::config::Persistence;
+use crate::modules::stt::model::{SttTranscription, STATUS_DONE};
+use bson::{doc, oid::ObjectId, Document};
 use mongodb::{Collection, Database};
 
 const COLLECTION_NAME: &str = "stt_transcriptions";
 
 pub struct SttCrud {
     collection: Collection<SttTranscription>,
+    /// Restricts reads and deletes to this owner's transcriptions
+    owner: Option<String>,
+    store: bool,
 }
 
 impl SttCrud {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection(COLLECTION_NAME),
+            owner: None,
+            store: true,
         }
     }
 
+    /// Only see transcriptions uploaded by this owner (see
+    /// `ApiKey::owner_scope`). `None` sees all.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Under `Persistence::None`, `create` skips the insert and returns a
+    /// fresh id that was never stored. Updates to that id then match nothing.
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.store = persistence.stores();
+        self
+    }
+
+    /// `filter` narrowed to the owner's transcriptions, if scoped
+    fn scoped(&self, mut filter: Document) -> Document {
+        if let Some(owner) = &self.owner {
+            filter.insert("owner", owner);
+        }
+        filter
+    }
+
     pub async fn create(&self, transcription: SttTranscription) -> Result<ObjectId, mongodb::error::Error> {
+        if !self.store {
+            return Ok(ObjectId::new());
+        }
         let result = self.collection.insert_one(transcription).await?;
         Ok(result.inserted_id.as_object_id().unwrap())
     }
 
     pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<SttTranscription>, mongodb::error::Error> {
-        self.collection.find_one(doc! { "_id": id }).await
+        self.collection.find_one(self.scoped(doc! { "_id": id })).await
     }
 
-    pub async fn find_all(&self, limit: i64) -> Result<Vec<SttTranscription>, mongodb::error::Error> {
+    pub async fn find_all(&self, filter: Document, limit: i64) -> Result<Vec<SttTranscription>, mongodb::error::Error> {
         use futures::TryStreamExt;
 
         let cursor = self
             .collection
-            .find(doc! {})
-            .sort(doc! { "created_at": -1 })
+            .find(self.scoped(filter))
+            .sort(doc! { "created_at": -1, "_id": -1 })
             .limit(limit)
             .await?;
 
@@ -42,16 +73,74 @@ impl SttCrud {
 
         let cursor = self
             .collection
-            .find(doc! { "session_id": session_id })
-            .sort(doc! { "created_at": -1 })
+            .find(self.scoped(doc! { "session_id": session_id }))
+            .sort(doc! { "created_at": -1, "_id": -1 })
             .limit(limit)
             .await?;
 
         cursor.try_collect().await
     }
 
-    pub async fn count(&self) -> Result<u64, mongodb::error::Error> {
-        self.collection.count_documents(doc! {}).await
+    pub async fn count(&self, filter: Document) -> Result<u64, mongodb::error::Error> {
+        self.collection.count_documents(self.scoped(filter)).await
+    }
+
+    /// Distinct `language` and `model` values with their counts, most common
+    /// first, in one `$facet` aggregation
+    pub async fn facets(&self) -> Result<Document, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let group_count = |field: &str| {
+            vec![
+                doc! { "$group": { "_id": format!("${}", field), "count": { "$sum": 1 } } },
+                doc! { "$sort": { "count": -1, "_id": 1 } },
+            ]
+        };
+
+        let pipeline = vec![
+            doc! { "$match": self.scoped(doc! {}) },
+            doc! { "$facet": {
+                "languages": group_count("language"),
+                "models": group_count("model"),
+            } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        Ok(cursor.try_next().await?.unwrap_or_default())
+    }
+
+    /// Transcriptions per detected language within `[from, to)`, most common first
+    pub async fn language_counts(
+        &self,
+        from: Option<bson::DateTime>,
+        to: Option<bson::DateTime>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let mut range = Document::new();
+        if let Some(from) = from {
+            range.insert("$gte", from);
+        }
+        if let Some(to) = to {
+            range.insert("$lt", to);
+        }
+        let mut filter = doc! {};
+        if !range.is_empty() {
+            filter.insert("created_at", range);
+        }
+
+        let pipeline = vec![
+            doc! { "$match": self.scoped(filter) },
+            doc! { "$group": { "_id": "$language", "count": { "$sum": 1 } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+        ];
+
+        self.collection.aggregate(pipeline).await?.try_collect().await
+    }
+
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+        Ok(result.deleted_count)
     }
 
     pub async fn update_ai_response(&self, id: &ObjectId, ai_response: String) -> Result<bool, mongodb::error::Error> {
@@ -65,8 +154,46 @@ impl SttCrud {
         Ok(result.modified_count > 0)
     }
 
+    /// Store the final result of a processing transcription, segments
+    /// included, and mark it done
+    pub async fn complete(&self, id: &ObjectId, transcription: &SttTranscription) -> Result<bool, mongodb::error::Error> {
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": {
+                    "text": &transcription.text,
+                    "language": &transcription.language,
+                    "duration": transcription.duration,
+                    "model": &transcription.model,
+                    "file_size": transcription.file_size.map(|s| s as i64),
+                    "segments": &transcription.segments,
+                    "status": STATUS_DONE,
+                } },
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn set_status(&self, id: &ObjectId, status: &str) -> Result<bool, mongodb::error::Error> {
+        let result = self
+            .collection
+            .update_one(doc! { "_id": id }, doc! { "$set": { "status": status } })
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Remove every transcription linked to a session
+    pub async fn delete_by_session(&self, session_id: &str) -> Result<u64, mongodb::error::Error> {
+        let result = self
+            .collection
+            .delete_many(self.scoped(doc! { "session_id": session_id }))
+            .await?;
+        Ok(result.deleted_count)
+    }
+
     pub async fn delete(&self, id: &ObjectId) -> Result<bool, mongodb::error::Error> {
-        let result = self.collection.delete_one(doc! { "_id": id }).await?;
+        let result = self.collection.delete_one(self.scoped(doc! { "_id": id })).await?;
         Ok(result.deleted_count > 0)
     }
 }
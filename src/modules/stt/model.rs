@@ -14,8 +14,47 @@ pub struct SttTranscription {
     pub session_id: Option<String>,
     pub ai_response: Option<String>,
     pub created_at: bson::DateTime,
+    /// `processing`, `done` or `failed` for streamed transcriptions; unset otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Text of each segment of a streamed transcription, stored with its
+    /// final result
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<String>,
+    #[serde(flatten)]
+    pub metadata: TranscriptionMetadata,
+    /// Segments labeled by speaker, for meeting transcriptions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub speaker_segments: Vec<SpeakerSegment>,
+    /// Id of the API key that uploaded the audio (`ApiKey::id`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
 }
 
+/// A transcribed segment attributed to a speaker (`Speaker 1`, ...)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SpeakerSegment {
+    pub speaker: String,
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+/// Context a client attaches to an upload alongside the audio
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TranscriptionMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+pub const STATUS_PROCESSING: &str = "processing";
+pub const STATUS_DONE: &str = "done";
+pub const STATUS_FAILED: &str = "failed";
+
 impl SttTranscription {
     pub fn new(
         text: String,
@@ -37,9 +76,31 @@ impl SttTranscription {
             session_id,
             ai_response: None,
             created_at: bson::DateTime::now(),
+            status: None,
+            segments: Vec::new(),
+            metadata: TranscriptionMetadata::default(),
+            speaker_segments: Vec::new(),
+            owner: None,
         }
     }
 
+    pub fn with_metadata(mut self, metadata: TranscriptionMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Empty record created before a streamed transcription starts
+    pub fn processing(model: String, file_name: Option<String>, session_id: Option<String>) -> Self {
+        let mut transcription = Self::new(String::new(), None, None, model, file_name, None, session_id);
+        transcription.status = Some(STATUS_PROCESSING.to_string());
+        transcription
+    }
+
     pub fn created_at_rfc3339(&self) -> String {
         self.created_at.try_to_rfc3339_string().unwrap_or_default()
     }
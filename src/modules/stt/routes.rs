@@ -1,17 +1,49 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{delete, get, post},
     Router,
 };
 
 use crate::modules::stt::controller;
+use crate::services::stt::MAX_AUDIO_BYTES;
 use crate::AppState;
 
+/// Room for the multipart boundaries and the text fields sent with the audio
+const MULTIPART_OVERHEAD_BYTES: usize = 1024 * 1024;
+
 pub fn routes() -> Router<AppState> {
-    Router::new()
+    // axum caps bodies at 2MB by default, well under what an upload may be
+    let uploads = Router::new()
         .route("/api/stt/transcribe", post(controller::transcribe))
         .route("/api/stt/transcribe-ai", post(controller::transcribe_and_respond))
+        .route(
+            "/api/stt/transcribe-ai/stream",
+            post(controller::transcribe_and_respond_stream),
+        )
+        .route("/api/stt/quick-answer", post(controller::quick_answer))
+        .route("/api/stt/meeting", post(controller::transcribe_meeting))
+        .layer(DefaultBodyLimit::max(MAX_AUDIO_BYTES as usize + MULTIPART_OVERHEAD_BYTES));
+
+    Router::new()
+        .merge(uploads)
+        .route("/api/stt/transcribe-url", post(controller::transcribe_url))
+        .route("/api/stt/transcribe-url/stream", post(controller::transcribe_url_stream))
         .route("/api/stt/transcription/{id}", get(controller::get_transcription))
         .route("/api/stt/transcription/{id}", delete(controller::delete_transcription))
+        .route(
+            "/api/stt/transcription/{id}/related-sessions",
+            get(controller::related_sessions),
+        )
         .route("/api/stt/transcriptions", get(controller::list_transcriptions))
-        .route("/api/stt/formats", get(controller::supported_formats))
+        .route("/api/stt/facets", get(controller::transcription_facets))
+        .route("/api/stt/languages/stats", get(controller::language_stats))
+        .route(
+            "/api/stt/sessions/{session_id}/transcriptions",
+            delete(controller::delete_session_transcriptions),
+        )
+}
+
+/// Served without an API key, outside `auth::require_api_key`
+pub fn public_routes() -> Router<AppState> {
+    Router::new().route("/api/stt/formats", get(controller::supported_formats))
 }
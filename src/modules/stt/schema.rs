@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+use crate::modules::stt::model::{SpeakerSegment, TranscriptionMetadata};
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct TranscribeResponse {
     pub id: String,
     pub text: String,
@@ -8,9 +11,25 @@ pub struct TranscribeResponse {
     pub duration: Option<f32>,
     pub model: String,
     pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_message_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filtered_segments: Option<usize>,
+    /// Set for streamed transcriptions, see `SttTranscription::status`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub metadata: TranscriptionMetadata,
+    /// Set for meeting transcriptions
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub speaker_segments: Vec<SpeakerSegment>,
+    /// Ways to make a WAV upload smaller, e.g. downsampling to 16kHz mono
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub audio_hints: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct TranscribeWithAiResponse {
     pub id: String,
     pub transcription: String,
@@ -19,21 +38,168 @@ pub struct TranscribeWithAiResponse {
     pub duration: Option<f32>,
     pub model: String,
     pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filtered_segments: Option<usize>,
+    /// See `SttResponse::average_confidence`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// Below `STT_MIN_ANSWER_CONFIDENCE`. The answer then carries a caveat,
+    /// or is empty if `STT_LOW_CONFIDENCE_ACTION=skip`.
+    pub low_confidence: bool,
+}
+
+/// Nothing behind it is stored, so there's no id or timestamp
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct QuickAnswerResponse {
+    pub transcription: String,
+    pub answer: String,
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct TranscriptionListResponse {
     pub data: Vec<TranscribeResponse>,
     pub total: u64,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct TranscriptionListQuery {
+    pub language: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct FacetValue {
+    /// `null` for transcriptions without a detected language
+    #[serde(alias = "_id")]
+    pub value: Option<String>,
+    pub count: u64,
+}
+
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct TranscriptionFacetsResponse {
+    pub languages: Vec<FacetValue>,
+    pub models: Vec<FacetValue>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LanguageStatsQuery {
+    /// Inclusive lower bound (RFC 3339)
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exclusive upper bound (RFC 3339)
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct LanguageCount {
+    /// `null` for transcriptions without a detected language
+    #[serde(alias = "_id")]
+    pub language: Option<String>,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct LanguageStatsResponse {
+    pub total: u64,
+    /// Most common first
+    pub languages: Vec<LanguageCount>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct MessageResponse {
     pub message: String,
 }
 
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct DeleteTranscriptionsResponse {
+    pub deleted: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TranscribeQuery {
     pub language: Option<String>,
     pub session_id: Option<String>,
+    /// Role of the message appended to the session (`user`, `assistant` or `system`)
+    pub add_as: Option<String>,
+    /// Drop segments whose speech confidence (1 - no_speech_prob) is below this value
+    pub min_confidence: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeetingQuery {
+    pub language: Option<String>,
+    /// Speakers the pause heuristic rotates through (default
+    /// `DIARIZATION_MAX_SPEAKERS`); ignored by an external service
+    pub speakers: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct MeetingResponse {
+    pub id: String,
+    pub segments: Vec<SpeakerSegment>,
+    pub language: Option<String>,
+    pub duration: Option<f32>,
+    pub model: String,
+    /// `service` or `pauses`, whichever produced the labels
+    pub diarization: String,
+    pub created_at: String,
+    #[serde(flatten)]
+    pub metadata: TranscriptionMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickAnswerQuery {
+    pub language: Option<String>,
+    /// Passed to `suggest`: `code`, `interview`, `general`, ... (default `interview`)
+    pub suggestion_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscribeUrlRequest {
+    /// Publicly reachable http(s) URL of the audio file
+    pub url: String,
+    pub language: Option<String>,
+    pub session_id: Option<String>,
+}
+
+/// Payload of the `download_progress` SSE event. Without a
+/// `Content-Length`, `total` and `percent` are null and `indeterminate` is set.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct DownloadProgressEvent {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub percent: Option<f32>,
+    pub indeterminate: bool,
+}
+
+/// Payload of the `segment` SSE event, sent once the segment is persisted
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct SegmentEvent {
+    pub index: usize,
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+/// Payload of the final `done` event of `transcribe-ai/stream`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct TranscribeAiDoneEvent {
+    /// The saved transcription, which now includes `ai_response`
+    pub id: String,
+    pub ai_response: String,
+    pub model: String,
+    /// Same as `TranscribeWithAiResponse::low_confidence`
+    pub low_confidence: bool,
 }
@@ -1,18 +1,31 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
 use validator::Validate;
 
+use crate::auth::ApiKey;
+use crate::error::AppError;
 use crate::modules::transcription::{
     crud::TranscriptionCrud,
     model::Transcription,
-    schema::{CreateTranscriptionRequest, MessageResponse, TranscriptionListResponse, TranscriptionResponse},
+    schema::{
+        CreateTranscriptionQuery, CreateTranscriptionRequest, MessageResponse,
+        TranscriptionListResponse, TranscriptionResponse,
+    },
 };
 use crate::AppState;
 
+/// Transcriptions scoped to the caller's own, see `ApiKey::owner_scope`
+fn transcription_crud(state: &AppState, api_key: &ApiKey) -> TranscriptionCrud {
+    TranscriptionCrud::new(&state.db)
+        .with_owner(api_key.owner_scope(&state.config))
+        .with_persistence(state.config.persistence)
+}
+
 fn to_response(t: &Transcription) -> TranscriptionResponse {
     TranscriptionResponse {
         id: t.id.map(|id| id.to_hex()).unwrap_or_default(),
@@ -23,70 +36,58 @@ fn to_response(t: &Transcription) -> TranscriptionResponse {
     }
 }
 
+/// `201` with the new transcription, or `200` with an existing one when
+/// `?dedupe=true` finds a recent duplicate
 pub async fn create_transcription(
     State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<CreateTranscriptionQuery>,
     Json(payload): Json<CreateTranscriptionRequest>,
-) -> Result<(StatusCode, Json<TranscriptionResponse>), (StatusCode, Json<MessageResponse>)> {
-    if let Err(e) = payload.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: e.to_string() }),
-        ));
-    }
+) -> Result<(StatusCode, Json<TranscriptionResponse>), AppError> {
+    payload.validate()?;
 
-    let crud = TranscriptionCrud::new(&state.db);
-    let transcription = Transcription::new(payload.text, payload.source);
+    let crud = transcription_crud(&state, &api_key);
 
-    match crud.create(transcription.clone()).await {
-        Ok(id) => {
-            let mut response = to_response(&transcription);
-            response.id = id.to_hex();
-            Ok((StatusCode::CREATED, Json(response)))
+    if query.dedupe.unwrap_or(false) {
+        let window = Duration::seconds(state.config.transcription_dedupe_window_secs as i64);
+        let existing = crud
+            .find_recent_duplicate(&payload.text, payload.source.as_deref(), Utc::now() - window)
+            .await?;
+        if let Some(existing) = existing {
+            return Ok((StatusCode::OK, Json(to_response(&existing))));
         }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )),
     }
+
+    let transcription = Transcription::new(payload.text, payload.source).with_owner(api_key.id());
+
+    let id = crud.create(transcription.clone()).await?;
+    let mut response = to_response(&transcription);
+    response.id = id.to_hex();
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 pub async fn get_transcription(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-) -> Result<Json<TranscriptionResponse>, (StatusCode, Json<MessageResponse>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "Invalid ID format".to_string() }),
-        )
-    })?;
-
-    let crud = TranscriptionCrud::new(&state.db);
-
-    match crud.find_by_id(&oid).await {
-        Ok(Some(t)) => Ok(Json(to_response(&t))),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse { message: "Transcription not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )),
+) -> Result<Json<TranscriptionResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let crud = transcription_crud(&state, &api_key);
+
+    match crud.find_by_id(&oid).await? {
+        Some(t) => Ok(Json(to_response(&t))),
+        None => Err(AppError::not_found("Transcription not found")),
     }
 }
 
 pub async fn list_transcriptions(
     State(state): State<AppState>,
-) -> Result<Json<TranscriptionListResponse>, (StatusCode, Json<MessageResponse>)> {
-    let crud = TranscriptionCrud::new(&state.db);
+    api_key: ApiKey,
+) -> Result<Json<TranscriptionListResponse>, AppError> {
+    let crud = transcription_crud(&state, &api_key);
 
-    let transcriptions = crud.find_all(50).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )
-    })?;
+    let transcriptions = crud.find_all(50).await?;
 
     let total = crud.count().await.unwrap_or(0);
 
@@ -98,26 +99,16 @@ pub async fn list_transcriptions(
 
 pub async fn delete_transcription(
     State(state): State<AppState>,
+    api_key: ApiKey,
     Path(id): Path<String>,
-) -> Result<Json<MessageResponse>, (StatusCode, Json<MessageResponse>)> {
-    let oid = ObjectId::parse_str(&id).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(MessageResponse { message: "Invalid ID format".to_string() }),
-        )
-    })?;
-
-    let crud = TranscriptionCrud::new(&state.db);
-
-    match crud.delete(&oid).await {
-        Ok(true) => Ok(Json(MessageResponse { message: "Deleted successfully".to_string() })),
-        Ok(false) => Err((
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse { message: "Transcription not found".to_string() }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(MessageResponse { message: e.to_string() }),
-        )),
+) -> Result<Json<MessageResponse>, AppError> {
+    let oid = ObjectId::parse_str(&id).map_err(|_| AppError::invalid_id())?;
+
+    let crud = transcription_crud(&state, &api_key);
+
+    if crud.delete(&oid).await? {
+        Ok(Json(MessageResponse { message: "Deleted successfully".to_string() }))
+    } else {
+        Err(AppError::not_found("Transcription not found"))
     }
 }
@@ -1,27 +1,78 @@
+use crate::config::Persistence;
 use crate::modules::transcription::model::Transcription;
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{DateTime, SecondsFormat, Utc};
 use mongodb::{Collection, Database};
 
 const COLLECTION_NAME: &str = "transcriptions";
 
 pub struct TranscriptionCrud {
     collection: Collection<Transcription>,
+    /// Restricts reads and deletes to this owner's transcriptions
+    owner: Option<String>,
+    store: bool,
 }
 
 impl TranscriptionCrud {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection(COLLECTION_NAME),
+            owner: None,
+            store: true,
         }
     }
 
+    /// Only see transcriptions created by this owner (see
+    /// `ApiKey::owner_scope`). `None` sees all.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// `filter` narrowed to the owner's transcriptions, if scoped
+    fn scoped(&self, mut filter: Document) -> Document {
+        if let Some(owner) = &self.owner {
+            filter.insert("owner", owner);
+        }
+        filter
+    }
+
+    /// Under `Persistence::None`, `create` skips the insert and returns a
+    /// fresh id that was never stored
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.store = persistence.stores();
+        self
+    }
+
     pub async fn create(&self, transcription: Transcription) -> Result<ObjectId, mongodb::error::Error> {
+        if !self.store {
+            return Ok(ObjectId::new());
+        }
         let result = self.collection.insert_one(transcription).await?;
         Ok(result.inserted_id.as_object_id().unwrap())
     }
 
     pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<Transcription>, mongodb::error::Error> {
-        self.collection.find_one(doc! { "_id": id }).await
+        self.collection.find_one(self.scoped(doc! { "_id": id })).await
+    }
+
+    /// Most recent transcription with exactly this `text` and `source`
+    /// created at or after `since`. Timestamps are stored as RFC 3339
+    /// strings, so `since` is compared lexicographically.
+    pub async fn find_recent_duplicate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Option<Transcription>, mongodb::error::Error> {
+        self.collection
+            .find_one(self.scoped(doc! {
+                "text": text,
+                "source": source,
+                "created_at": { "$gte": since.to_rfc3339_opts(SecondsFormat::AutoSi, true) },
+            }))
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .await
     }
 
     pub async fn find_all(&self, limit: i64) -> Result<Vec<Transcription>, mongodb::error::Error> {
@@ -29,8 +80,8 @@ impl TranscriptionCrud {
 
         let cursor = self
             .collection
-            .find(doc! {})
-            .sort(doc! { "created_at": -1 })
+            .find(self.scoped(doc! {}))
+            .sort(doc! { "created_at": -1, "_id": -1 })
             .limit(limit)
             .await?;
 
@@ -38,11 +89,16 @@ impl TranscriptionCrud {
     }
 
     pub async fn count(&self) -> Result<u64, mongodb::error::Error> {
-        self.collection.count_documents(doc! {}).await
+        self.collection.count_documents(self.scoped(doc! {})).await
+    }
+
+    pub async fn delete_all(&self) -> Result<u64, mongodb::error::Error> {
+        let result = self.collection.delete_many(doc! {}).await?;
+        Ok(result.deleted_count)
     }
 
     pub async fn delete(&self, id: &ObjectId) -> Result<bool, mongodb::error::Error> {
-        let result = self.collection.delete_one(doc! { "_id": id }).await?;
+        let result = self.collection.delete_one(self.scoped(doc! { "_id": id })).await?;
         Ok(result.deleted_count > 0)
     }
 
@@ -50,7 +106,7 @@ impl TranscriptionCrud {
         let result = self
             .collection
             .update_one(
-                doc! { "_id": id },
+                self.scoped(doc! { "_id": id }),
                 doc! { "$set": { "ai_response": ai_response, "updated_at": bson::DateTime::now() } },
             )
             .await?;
@@ -9,6 +9,10 @@ pub struct Transcription {
     pub text: String,
     pub source: Option<String>,
     pub ai_response: Option<String>,
+    /// Id of the API key that created it (`ApiKey::id`). Missing on
+    /// transcriptions created before transcriptions had owners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -21,8 +25,14 @@ impl Transcription {
             text,
             source,
             ai_response: None,
+            owner: None,
             created_at: now,
             updated_at: now,
         }
     }
+
+    pub fn with_owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
 }
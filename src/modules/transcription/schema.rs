@@ -8,7 +8,16 @@ pub struct CreateTranscriptionRequest {
     pub source: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CreateTranscriptionQuery {
+    /// Return an identical transcription (same `text` and `source`) from
+    /// the last `TRANSCRIPTION_DEDUPE_WINDOW_SECS` instead of storing a
+    /// copy, e.g. when a client retries
+    pub dedupe: Option<bool>,
+}
+
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct TranscriptionResponse {
     pub id: String,
     pub text: String,
@@ -18,12 +27,14 @@ pub struct TranscriptionResponse {
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct TranscriptionListResponse {
     pub data: Vec<TranscriptionResponse>,
     pub total: u64,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
 pub struct MessageResponse {
     pub message: String,
 }
@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+pub const DEFAULT_PER_PAGE: u64 = 20;
+pub const MAX_PER_PAGE: u64 = 100;
+
+/// A validated `page`/`per_page` pair. Pages are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    pub page: u64,
+    pub per_page: u64,
+}
+
+impl Page {
+    /// Missing or zero values fall back to the first page of
+    /// `DEFAULT_PER_PAGE`; `per_page` is capped at `MAX_PER_PAGE`.
+    pub fn new(page: Option<u64>, per_page: Option<u64>) -> Self {
+        Self {
+            page: page.filter(|p| *p > 0).unwrap_or(1),
+            per_page: per_page
+                .filter(|p| *p > 0)
+                .unwrap_or(DEFAULT_PER_PAGE)
+                .min(MAX_PER_PAGE),
+        }
+    }
+
+    /// Records before this page
+    pub fn skip(&self) -> u64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+}
+
+/// One page of a larger result set
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "camelCase", serde(rename_all = "camelCase"))]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+    pub total_pages: u64,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(data: Vec<T>, page: Page, total: u64) -> Self {
+        Self {
+            data,
+            page: page.page,
+            per_page: page.per_page,
+            total,
+            total_pages: total.div_ceil(page.per_page),
+        }
+    }
+}
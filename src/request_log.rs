@@ -0,0 +1,70 @@
+//! Logs only the requests slower than `SLOW_REQUEST_MS`, so latency outliers
+//! stand out without a line for every normal request.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::timing::LlmCall;
+
+/// Time until the response head is ready; for SSE routes that excludes the
+/// stream itself.
+pub async fn log_slow_requests(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(threshold) = config.slow_request_ms.map(Duration::from_millis) else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed();
+
+    if elapsed >= threshold {
+        let call = response.extensions().get::<LlmCall>();
+        tracing::warn!("{}", slow_request_line(&method, &route, response.status(), elapsed, call));
+    }
+
+    response
+}
+
+/// `Slow request: POST /api/ai/complete 200 in 2350ms (model llama-3.1-8b-instant, 412 tokens)`
+pub fn slow_request_line(
+    method: &Method,
+    route: &str,
+    status: StatusCode,
+    elapsed: Duration,
+    call: Option<&LlmCall>,
+) -> String {
+    let mut line = format!(
+        "Slow request: {} {} {} in {}ms",
+        method,
+        route,
+        status.as_u16(),
+        elapsed.as_millis()
+    );
+
+    if let Some(call) = call {
+        match call.tokens {
+            Some(tokens) => line.push_str(&format!(" (model {}, {} tokens)", call.model, tokens)),
+            None => line.push_str(&format!(" (model {})", call.model)),
+        }
+    }
+
+    line
+}
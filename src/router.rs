@@ -0,0 +1,75 @@
+use axum::{http::HeaderValue, middleware, Router};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+
+use crate::config::Config;
+use crate::{auth, error, modules, request_log, AppState};
+
+/// The whole API as served by `main`, with `config` deciding the
+/// cross-cutting layers. A request passes, outermost first, through CORS,
+/// slow request logging, `require_api_key` (all but the public routes),
+/// response compression and tracing before reaching its handler. There is
+/// no rate-limit layer: token quotas are checked by the handlers that spend
+/// them. Integration tests build their server from this too, so they
+/// exercise the same middleware in the same order.
+pub fn build_router(state: AppState, config: &Config) -> Router {
+    let protected = Router::new()
+        .merge(modules::transcription::routes::routes())
+        .merge(modules::ai::routes::routes())
+        .merge(modules::session::routes::routes())
+        .merge(modules::stt::routes::routes())
+        .merge(modules::admin::routes::routes())
+        .merge(modules::health::routes::routes());
+    // Layered before auth, so auth runs first and rejected requests skip them
+    let protected = inner_layers(protected)
+        .route_layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            auth::require_api_key,
+        ));
+
+    // Merged after the auth layer so these stay reachable without a key
+    let public = Router::new()
+        .merge(modules::health::routes::public_routes())
+        .merge(modules::ai::routes::public_routes())
+        .merge(modules::stt::routes::public_routes());
+    let public = inner_layers(public);
+
+    // GET routes answer HEAD too. Set last so it covers every route.
+    protected
+        .merge(public)
+        .method_not_allowed_fallback(error::method_not_allowed)
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            request_log::log_slow_requests,
+        ))
+        .layer(cors_layer(config))
+        .with_state(state)
+}
+
+/// Compression, then tracing closest to the handler. SSE responses are
+/// left uncompressed by the layer's default predicate.
+fn inner_layers(router: Router<AppState>) -> Router<AppState> {
+    router.layer(TraceLayer::new_for_http()).layer(CompressionLayer::new())
+}
+
+/// Any origin unless `CORS_ALLOWED_ORIGINS` lists some. Origins that
+/// aren't valid header values are rejected by `Config::from_env`, and
+/// skipped here if set some other way.
+fn cors_layer(config: &Config) -> CorsLayer {
+    let allow_origin = if config.cors_allowed_origins.is_empty() {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            config
+                .cors_allowed_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(Any)
+        .allow_headers(Any)
+}
@@ -0,0 +1,177 @@
+//! Cheap duration estimates from audio headers, so obviously long uploads
+//! can be rejected before they're sent to the provider, hints about WAV
+//! uploads that are larger than transcription needs, and a check that an
+//! upload's leading bytes fit its extension.
+
+/// Approximate duration in seconds, for formats whose header allows it
+/// (WAV, constant-bitrate MP3). `None` for anything else or unparseable data.
+pub fn estimate_duration(data: &[u8], file_name: &str) -> Option<f32> {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
+
+    match extension.as_str() {
+        "wav" => wav_duration(data),
+        "mp3" => mp3_duration(data),
+        _ => None,
+    }
+}
+
+/// Whether `data` starts the way files with `file_name`'s extension do.
+/// Extensions without a known signature always match.
+pub fn matches_signature(data: &[u8], file_name: &str) -> bool {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
+
+    match extension.as_str() {
+        "wav" => data.starts_with(b"RIFF"),
+        "flac" => data.starts_with(b"fLaC"),
+        "ogg" | "opus" => data.starts_with(b"OggS"),
+        "webm" => data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]),
+        // An ID3v2 tag, or straight into an MPEG frame sync
+        "mp3" => {
+            data.starts_with(b"ID3")
+                || data.get(0..2).is_some_and(|b| b[0] == 0xFF && b[1] & 0xE0 == 0xE0)
+        }
+        "m4a" | "mp4" => data.get(4..8) == Some(b"ftyp"),
+        _ => true,
+    }
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Walk the RIFF chunks for `fmt ` (byte rate) and `data` (length)
+fn wav_duration(data: &[u8]) -> Option<f32> {
+    if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut byte_rate = None;
+    let mut offset = 12;
+
+    while let Some(id) = data.get(offset..offset + 4) {
+        let size = u32_le(data, offset + 4)? as usize;
+        let body = offset + 8;
+
+        match id {
+            b"fmt " => {
+                // audio_format u16, channels u16, sample_rate u32, byte_rate u32
+                byte_rate = Some(u32_le(data, body + 8)?);
+            }
+            b"data" => {
+                // Streaming writers leave the size unset (0 or u32::MAX)
+                let available = data.len() - body;
+                let length = if size == 0 || size > available { available } else { size };
+                let byte_rate = byte_rate.filter(|r| *r > 0)?;
+                return Some(length as f32 / byte_rate as f32);
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        offset = body.checked_add(size + (size & 1))?;
+    }
+
+    None
+}
+
+/// Sample rate Whisper resamples all audio to
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Channel count and sample rate from a WAV `fmt ` chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WavFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// The format of a WAV file, `None` if `data` isn't one
+pub fn wav_format(data: &[u8]) -> Option<WavFormat> {
+    if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut offset = 12;
+    while let Some(id) = data.get(offset..offset + 4) {
+        let size = u32_le(data, offset + 4)? as usize;
+        let body = offset + 8;
+
+        if id == b"fmt " {
+            // audio_format u16, channels u16, sample_rate u32
+            let channels = u16::from_le_bytes(data.get(body + 2..body + 4)?.try_into().ok()?);
+            let sample_rate = u32_le(data, body + 4)?;
+            return Some(WavFormat { channels, sample_rate });
+        }
+
+        offset = body.checked_add(size + (size & 1))?;
+    }
+
+    None
+}
+
+/// Suggestions for a WAV upload recorded at more than transcription uses:
+/// a sample rate above `WHISPER_SAMPLE_RATE` or more than one channel.
+/// Empty for other formats and for WAVs that are already a good fit.
+pub fn audio_hints(data: &[u8], file_name: &str) -> Vec<String> {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
+    let Some(format) = wav_format(data).filter(|_| extension == "wav") else {
+        return Vec::new();
+    };
+
+    let mut hints = Vec::new();
+    if format.sample_rate > WHISPER_SAMPLE_RATE {
+        hints.push(format!(
+            "Audio is sampled at {}Hz but transcribed at {}Hz; downsampling before upload \
+             makes it smaller and faster with no loss of accuracy",
+            format.sample_rate, WHISPER_SAMPLE_RATE
+        ));
+    }
+    if format.channels > 1 {
+        hints.push(format!(
+            "Audio has {} channels but is transcribed as mono; mixing down to one channel \
+             before upload makes it smaller",
+            format.channels
+        ));
+    }
+    hints
+}
+
+/// kbps by bitrate index for MPEG-1 and MPEG-2/2.5 Layer III
+const MP3_BITRATES_V1: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const MP3_BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// Size the audio by the first frame's bitrate. Exact for CBR files, a rough
+/// guess for VBR ones.
+fn mp3_duration(data: &[u8]) -> Option<f32> {
+    let mut offset = 0;
+
+    // Skip an ID3v2 tag; its size is a 28-bit syncsafe integer
+    if data.get(0..3)? == b"ID3" {
+        let size = data
+            .get(6..10)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 7) | (*b as usize & 0x7f));
+        offset = 10 + size;
+    }
+
+    // Find the first frame sync within a small window
+    let end = data.len().min(offset + 4096).saturating_sub(3);
+    let frame = (offset..end).find(|&i| data[i] == 0xff && data[i + 1] & 0xe0 == 0xe0)?;
+
+    let version = (data[frame + 1] >> 3) & 0b11;
+    let layer = (data[frame + 1] >> 1) & 0b11;
+    let bitrate_index = (data[frame + 2] >> 4) as usize;
+
+    // Layer III only (`01`); version `01` is reserved
+    if layer != 0b01 || version == 0b01 || bitrate_index == 0 || bitrate_index == 15 {
+        return None;
+    }
+
+    let kbps = if version == 0b11 {
+        MP3_BITRATES_V1[bitrate_index]
+    } else {
+        MP3_BITRATES_V2[bitrate_index]
+    };
+
+    let audio_bytes = (data.len() - frame) as f32;
+    Some(audio_bytes * 8.0 / (kbps as f32 * 1000.0))
+}
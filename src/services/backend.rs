@@ -0,0 +1,166 @@
+use futures::future::BoxFuture;
+use std::fmt;
+
+use crate::services::llm::{ChatMessage, LlmError, LlmResponse};
+use crate::services::stt::{SttError, SttResponse};
+
+/// A chat completion as handed to a `ClientBackend`
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub user: Option<String>,
+}
+
+/// Replaces the provider HTTP calls of `LlmClient` and `SttClient` when set
+/// as `Config::client_backend`. Clients built from such a config don't need
+/// provider API keys, so handlers can be exercised without network access.
+/// Streaming completions are served from `complete` as a single delta.
+pub trait ClientBackend: fmt::Debug + Send + Sync {
+    fn complete(&self, request: CompletionRequest) -> BoxFuture<'_, Result<LlmResponse, LlmError>>;
+
+    fn embed(&self, inputs: Vec<String>, model: String) -> BoxFuture<'_, Result<Vec<Vec<f32>>, LlmError>>;
+
+    fn transcribe(
+        &self,
+        audio: Vec<u8>,
+        file_name: String,
+        language: Option<String>,
+    ) -> BoxFuture<'_, Result<SttResponse, SttError>>;
+}
+
+#[cfg(feature = "mock")]
+pub mod mock {
+    use futures::future::{self, BoxFuture};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::{ClientBackend, CompletionRequest};
+    use crate::modules::ai::schema::UsageInfo;
+    use crate::services::llm::{LlmError, LlmResponse};
+    use crate::services::stt::{SttError, SttResponse, SttSegment};
+
+    /// Serves canned completions and transcriptions and records what it was
+    /// asked, for hermetic tests
+    #[derive(Debug)]
+    pub struct MockBackend {
+        completion: String,
+        transcription: String,
+        segments: Vec<SttSegment>,
+        delays: HashMap<String, Duration>,
+        requests: Mutex<Vec<CompletionRequest>>,
+    }
+
+    impl Default for MockBackend {
+        fn default() -> Self {
+            Self {
+                completion: "mock completion".to_string(),
+                transcription: "mock transcription".to_string(),
+                segments: Vec::new(),
+                delays: HashMap::new(),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl MockBackend {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn with_completion(mut self, completion: impl Into<String>) -> Self {
+            self.completion = completion.into();
+            self
+        }
+
+        pub fn with_transcription(mut self, transcription: impl Into<String>) -> Self {
+            self.transcription = transcription.into();
+            self
+        }
+
+        /// Segments reported with each transcription. The text is not
+        /// rebuilt from them.
+        pub fn with_segments(mut self, segments: Vec<SttSegment>) -> Self {
+            self.segments = segments;
+            self
+        }
+
+        /// Completions for `model` take `delay` to arrive
+        pub fn with_delay(mut self, model: impl Into<String>, delay: Duration) -> Self {
+            self.delays.insert(model.into(), delay);
+            self
+        }
+
+        /// Completion requests received so far
+        pub fn requests(&self) -> Vec<CompletionRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// Whitespace-separated word count, a stand-in for token counts
+    fn words(text: &str) -> u32 {
+        text.split_whitespace().count() as u32
+    }
+
+    impl ClientBackend for MockBackend {
+        fn complete(&self, request: CompletionRequest) -> BoxFuture<'_, Result<LlmResponse, LlmError>> {
+            let prompt_tokens = request.messages.iter().map(|m| words(&m.content)).sum::<u32>();
+            let completion_tokens = words(&self.completion);
+            let delay = self.delays.get(&request.model).copied();
+            self.requests.lock().unwrap().push(request);
+
+            let response = LlmResponse {
+                id: format!("mock-{}", uuid::Uuid::new_v4()),
+                content: self.completion.clone(),
+                usage: Some(UsageInfo {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                }),
+                finish_reason: Some("stop".to_string()),
+                reasoning: None,
+            };
+            Box::pin(async move {
+                if let Some(delay) = delay {
+                    tokio::time::sleep(delay).await;
+                }
+                Ok(response)
+            })
+        }
+
+        /// Deterministic 8-dimensional vectors derived from the input bytes
+        fn embed(&self, inputs: Vec<String>, _model: String) -> BoxFuture<'_, Result<Vec<Vec<f32>>, LlmError>> {
+            let vectors = inputs
+                .iter()
+                .map(|input| {
+                    let mut vector = vec![0.0f32; 8];
+                    for (i, byte) in input.bytes().enumerate() {
+                        vector[i % 8] += byte as f32 / 255.0;
+                    }
+                    vector
+                })
+                .collect();
+
+            Box::pin(future::ready(Ok(vectors)))
+        }
+
+        fn transcribe(
+            &self,
+            audio: Vec<u8>,
+            _file_name: String,
+            language: Option<String>,
+        ) -> BoxFuture<'_, Result<SttResponse, SttError>> {
+            Box::pin(future::ready(Ok(SttResponse {
+                text: self.transcription.clone(),
+                language: language.or_else(|| Some("en".to_string())),
+                // Pretend the upload is 16kHz 16-bit mono PCM
+                duration: Some(audio.len() as f32 / 32_000.0),
+                model: "mock-whisper".to_string(),
+                segments: self.segments.clone(),
+            })))
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! ZIP archive of a session for `GET /api/session/{id}/bundle`: the
+//! conversation as Markdown and its linked transcriptions as JSON.
+//!
+//! Entries are stored uncompressed; they're small text files, and audio,
+//! the only large content, is already compressed. Uploaded audio isn't kept
+//! after transcription, so the bundle has none to include yet.
+
+use async_zip::base::write::ZipFileWriter;
+use async_zip::error::ZipError;
+use async_zip::{Compression, ZipEntryBuilder};
+use serde::Serialize;
+use tokio::io::AsyncWrite;
+
+use crate::modules::session::model::Session;
+
+pub const CONVERSATION_FILE: &str = "conversation.md";
+pub const TRANSCRIPTIONS_FILE: &str = "transcriptions.json";
+
+/// The session as Markdown: a title, its type and creation time, then one
+/// section per message
+pub fn conversation_markdown(session: &Session) -> String {
+    let title = session.title.as_deref().unwrap_or("Untitled session");
+    let mut markdown = format!(
+        "# {}\n\n- Type: {}\n- Created: {}\n",
+        title,
+        session.session_type,
+        session.created_at_rfc3339()
+    );
+
+    for message in &session.messages {
+        markdown.push_str(&format!(
+            "\n## {} ({})\n\n{}\n",
+            message.role,
+            message.timestamp_rfc3339(),
+            message.content.trim_end()
+        ));
+    }
+
+    markdown
+}
+
+/// Write the bundle to `writer`, e.g. one half of a `tokio::io::duplex`
+/// whose other half is streamed as the response body
+pub async fn write_bundle<W, T>(writer: W, session: &Session, transcriptions: &T) -> Result<(), ZipError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize + ?Sized,
+{
+    let mut zip = ZipFileWriter::with_tokio(writer);
+
+    let conversation = conversation_markdown(session);
+    let entry = ZipEntryBuilder::new(CONVERSATION_FILE.into(), Compression::Stored);
+    zip.write_entry_whole(entry, conversation.as_bytes()).await?;
+
+    let transcriptions = serde_json::to_vec_pretty(transcriptions).unwrap_or_default();
+    let entry = ZipEntryBuilder::new(TRANSCRIPTIONS_FILE.into(), Compression::Stored);
+    zip.write_entry_whole(entry, &transcriptions).await?;
+
+    zip.close().await?;
+    Ok(())
+}
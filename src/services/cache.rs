@@ -0,0 +1,134 @@
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, RedisError};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// Minimum gap between Redis error warnings, so an outage logs once a
+/// minute instead of once per request
+const WARN_INTERVAL_SECS: i64 = 60;
+
+static REDIS_ERRORS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static LAST_WARNED_AT: AtomicI64 = AtomicI64::new(0);
+
+/// Redis command failures since startup. Cache misses are not counted.
+pub fn redis_errors_total() -> u64 {
+    REDIS_ERRORS_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Count a failed Redis command and warn, at most once per interval.
+/// Callers fall back to Mongo, so without this an outage is invisible.
+pub fn record_redis_error(operation: &str, error: &RedisError) {
+    let total = REDIS_ERRORS_TOTAL.fetch_add(1, Ordering::Relaxed) + 1;
+
+    let now = chrono::Utc::now().timestamp();
+    let last = LAST_WARNED_AT.load(Ordering::Relaxed);
+    if now - last >= WARN_INTERVAL_SECS
+        && LAST_WARNED_AT
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    {
+        tracing::warn!(
+            "Redis {} failed: {} ({} Redis errors so far)",
+            operation,
+            error,
+            total
+        );
+    } else {
+        tracing::debug!("Redis {} failed: {}", operation, error);
+    }
+}
+
+/// Cached value for `key`. `None` for both a miss and a Redis error, but
+/// only errors are recorded.
+pub async fn get(redis: &ConnectionManager, key: &str) -> Option<String> {
+    let mut redis = redis.clone();
+    match redis.get::<_, Option<String>>(key).await {
+        Ok(value) => value,
+        Err(e) => {
+            record_redis_error("GET", &e);
+            None
+        }
+    }
+}
+
+pub async fn set_ex(redis: &ConnectionManager, key: &str, value: String, ttl_secs: u64) {
+    let mut redis = redis.clone();
+    if let Err(e) = redis.set_ex::<_, _, ()>(key, value, ttl_secs).await {
+        record_redis_error("SET", &e);
+    }
+}
+
+pub async fn del(redis: &ConnectionManager, key: &str) {
+    let mut redis = redis.clone();
+    if let Err(e) = redis.del::<_, ()>(key).await {
+        record_redis_error("DEL", &e);
+    }
+}
+
+/// Sets `key` only if `generation_key` still holds `generation`, checked
+/// and written atomically. A missing counter counts as 0.
+const SET_IF_GENERATION: &str = r"
+if (redis.call('GET', KEYS[2]) or '0') == ARGV[2] then
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[3])
+    return 1
+end
+return 0
+";
+
+/// Current value of a counter bumped by `bump_generation`, to pass to
+/// `set_ex_if_generation` after reading the value to cache. `None` on a
+/// Redis error, in which case nothing should be cached.
+pub async fn generation(redis: &ConnectionManager, key: &str) -> Option<u64> {
+    let mut redis = redis.clone();
+    match redis.get::<_, Option<u64>>(key).await {
+        Ok(generation) => Some(generation.unwrap_or(0)),
+        Err(e) => {
+            record_redis_error("GET", &e);
+            None
+        }
+    }
+}
+
+/// Mark every read started before now as outdated. Writers call this after
+/// their write and before deleting the cached copy.
+pub async fn bump_generation(redis: &ConnectionManager, key: &str, ttl_secs: u64) {
+    let mut redis = redis.clone();
+    let result = redis::pipe()
+        .incr(key, 1)
+        .ignore()
+        .expire(key, ttl_secs as i64)
+        .ignore()
+        .query_async::<()>(&mut redis)
+        .await;
+    if let Err(e) = result {
+        record_redis_error("INCR", &e);
+    }
+}
+
+/// `set_ex`, unless a writer bumped `generation_key` since `generation` was
+/// read: the value may predate that write, and caching it would hide the
+/// write until the entry expires. Returns whether the value was cached.
+pub async fn set_ex_if_generation(
+    redis: &ConnectionManager,
+    key: &str,
+    value: String,
+    ttl_secs: u64,
+    generation_key: &str,
+    generation: u64,
+) -> bool {
+    let mut redis = redis.clone();
+    let result = redis::Script::new(SET_IF_GENERATION)
+        .key(key)
+        .key(generation_key)
+        .arg(value)
+        .arg(generation)
+        .arg(ttl_secs)
+        .invoke_async::<i64>(&mut redis)
+        .await;
+    match result {
+        Ok(set) => set == 1,
+        Err(e) => {
+            record_redis_error("EVALSHA", &e);
+            false
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Speaker labels for transcribed segments. An external diarization service
+//! is used when `DIARIZATION_URL` is set. Otherwise, or if it fails, a
+//! pause heuristic guesses where turns change.
+//!
+//! The heuristic only sees timing. It assumes speakers take turns in a fixed
+//! rotation, with a pause of at least `DIARIZATION_PAUSE_SECS` between
+//! turns. That goes wrong in several ways:
+//! - quick back-and-forth and overlapping speech merge into one turn;
+//! - a speaker who pauses mid-thought is split across two labels;
+//! - with more than two people, the rotation rarely matches who spoke.
+//!
+//! Labels are only consistent within one recording.
+
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::modules::stt::model::SpeakerSegment;
+use crate::services::stt::SttSegment;
+
+#[derive(Error, Debug)]
+pub enum DiarizationError {
+    #[error("Diarization request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Diarization service returned {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// One speaker's turn, as reported by the diarization service
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpeakerTurn {
+    pub speaker: String,
+    pub start: f32,
+    pub end: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiarizationResponse {
+    turns: Vec<SpeakerTurn>,
+}
+
+fn label(index: usize) -> String {
+    format!("Speaker {}", index + 1)
+}
+
+/// Start a new turn after every pause of at least `pause_secs`, rotating
+/// through `max_speakers` labels. See the module docs for the limitations.
+pub fn diarize_by_pauses(
+    segments: &[SttSegment],
+    pause_secs: f32,
+    max_speakers: usize,
+) -> Vec<SpeakerSegment> {
+    let max_speakers = max_speakers.max(1);
+    let mut speaker = 0;
+    let mut previous_end = None;
+
+    segments
+        .iter()
+        .map(|segment| {
+            if previous_end.is_some_and(|end| segment.start - end >= pause_secs) {
+                speaker = (speaker + 1) % max_speakers;
+            }
+            previous_end = Some(segment.end);
+
+            SpeakerSegment {
+                speaker: label(speaker),
+                start: segment.start,
+                end: segment.end,
+                text: segment.text.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Give each segment the speaker whose turn overlaps it most, or failing
+/// that the turn nearest its midpoint. The service's own speaker ids are
+/// renamed `Speaker 1`, `Speaker 2`, ... in order of first appearance.
+pub fn label_segments(segments: &[SttSegment], turns: &[SpeakerTurn]) -> Vec<SpeakerSegment> {
+    let mut speakers: Vec<&str> = Vec::new();
+    for turn in turns {
+        if !speakers.contains(&turn.speaker.as_str()) {
+            speakers.push(&turn.speaker);
+        }
+    }
+
+    segments
+        .iter()
+        .map(|segment| {
+            let overlap =
+                |turn: &SpeakerTurn| segment.end.min(turn.end) - segment.start.max(turn.start);
+            let middle = (segment.start + segment.end) / 2.0;
+            let distance = |turn: &SpeakerTurn| (middle - (turn.start + turn.end) / 2.0).abs();
+
+            let turn = turns
+                .iter()
+                .filter(|t| overlap(t) > 0.0)
+                .max_by(|a, b| overlap(a).total_cmp(&overlap(b)))
+                .or_else(|| turns.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))));
+
+            let speaker = turn
+                .and_then(|t| speakers.iter().position(|s| *s == t.speaker))
+                .unwrap_or(0);
+
+            SpeakerSegment {
+                speaker: label(speaker),
+                start: segment.start,
+                end: segment.end,
+                text: segment.text.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Send the audio to the diarization service at `url`. It's expected to
+/// accept a multipart `file` and answer `{"turns": [{"speaker", "start", "end"}]}`.
+pub async fn fetch_turns(
+    url: &str,
+    audio_data: Vec<u8>,
+    file_name: &str,
+) -> Result<Vec<SpeakerTurn>, DiarizationError> {
+    let response = super::send_with_retry::<_, DiarizationError>(|| {
+        let part = Part::bytes(audio_data.clone()).file_name(file_name.to_string());
+        Ok(super::http_client().post(url).multipart(Form::new().part("file", part)))
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(DiarizationError::Status(response.status()));
+    }
+
+    Ok(response.json::<DiarizationResponse>().await?.turns)
+}
@@ -0,0 +1,122 @@
+//! In-process caches behind the discovery endpoints (`/api/ai/models`,
+//! `/api/stt/formats`, capabilities). What derives from the config alone is
+//! built once per process; health annotations are kept for
+//! `MODEL_HEALTH_TTL` and refreshed in the background once stale.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::modules::ai::schema::ModelInfo;
+use crate::modules::health::schema::ProviderHealth;
+use crate::services::stt::SttClient;
+
+/// How long model health annotations are served before being refreshed
+pub const MODEL_HEALTH_TTL: Duration = Duration::from_secs(30);
+
+/// What `?health=true` adds to the model list
+#[derive(Debug, Clone, Default)]
+pub struct ModelHealth {
+    /// From the cached deep health check
+    pub providers: Vec<ProviderHealth>,
+    /// Mean benchmark latency by model id
+    pub latencies: BTreeMap<String, f64>,
+}
+
+/// Per-process discovery data, shared through `AppState`
+pub struct Discovery {
+    pub models: OnceLock<Vec<ModelInfo>>,
+    formats: OnceLock<Vec<String>>,
+    pub model_health: Arc<TtlCell<ModelHealth>>,
+}
+
+impl Discovery {
+    pub fn new() -> Self {
+        Self {
+            models: OnceLock::new(),
+            formats: OnceLock::new(),
+            model_health: Arc::new(TtlCell::new(MODEL_HEALTH_TTL)),
+        }
+    }
+}
+
+impl Discovery {
+    /// `SttClient::supported_formats`, as owned strings
+    pub fn formats(&self, config: &Config) -> &[String] {
+        self.formats.get_or_init(|| {
+            SttClient::supported_formats(config)
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        })
+    }
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value that goes stale after `ttl`. A stale value is still served while
+/// one background task fetches its replacement, so only the very first read
+/// waits for `refresh`.
+pub struct TtlCell<T> {
+    ttl: Duration,
+    entry: Mutex<Option<(Instant, T)>>,
+    refreshing: AtomicBool,
+}
+
+impl<T: Clone + Send + 'static> TtlCell<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    /// The cached value, if any, and whether it's still fresh
+    pub fn peek(&self) -> Option<(T, bool)> {
+        let entry = self.entry.lock().unwrap();
+        entry
+            .as_ref()
+            .map(|(stored_at, value)| (value.clone(), stored_at.elapsed() < self.ttl))
+    }
+
+    pub fn set(&self, value: T) {
+        *self.entry.lock().unwrap() = Some((Instant::now(), value));
+    }
+
+    /// The cached value, fetched with `refresh` if there is none. A stale
+    /// value is returned as-is and refreshed in a spawned task, unless a
+    /// refresh is already under way.
+    pub async fn get_or_refresh<F, Fut>(self: &Arc<Self>, refresh: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        match self.peek() {
+            Some((value, true)) => value,
+            Some((value, false)) => {
+                if !self.refreshing.swap(true, Ordering::AcqRel) {
+                    let cell = self.clone();
+                    let fetch = refresh();
+                    tokio::spawn(async move {
+                        cell.set(fetch.await);
+                        cell.refreshing.store(false, Ordering::Release);
+                    });
+                }
+                value
+            }
+            None => {
+                let value = refresh().await;
+                self.set(value.clone());
+                value
+            }
+        }
+    }
+}
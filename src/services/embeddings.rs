@@ -0,0 +1,56 @@
+/// Cosine similarity of two vectors. Returns 0 for mismatched lengths or
+/// zero vectors rather than NaN.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Keys of the `k` candidates most similar to `query`, best first
+pub fn top_k<K: Copy>(query: &[f32], candidates: &[(K, Vec<f32>)], k: usize) -> Vec<K> {
+    top_k_scored(query, candidates, k).into_iter().map(|(key, _)| key).collect()
+}
+
+/// Like `top_k`, with each key's similarity to `query`
+pub fn top_k_scored<K: Copy>(query: &[f32], candidates: &[(K, Vec<f32>)], k: usize) -> Vec<(K, f32)> {
+    let mut scored = candidates
+        .iter()
+        .map(|(key, vector)| (*key, cosine_similarity(query, vector)))
+        .collect::<Vec<_>>();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
+
+/// Element-wise mean of `vectors`, ignoring any whose length differs from
+/// the first. Empty for no vectors.
+pub fn mean(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let Some(first) = vectors.first() else {
+        return Vec::new();
+    };
+
+    let mut sum = vec![0.0; first.len()];
+    let mut count = 0;
+    for vector in vectors.iter().filter(|v| v.len() == first.len()) {
+        for (total, x) in sum.iter_mut().zip(vector) {
+            *total += x;
+        }
+        count += 1;
+    }
+
+    sum.iter().map(|total| total / count as f32).collect()
+}
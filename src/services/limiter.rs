@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Caps how many operations may run at once per key (e.g. per session).
+/// Slots are released when the returned guard is dropped, so every exit
+/// path (success, error, panic, client disconnect) frees them.
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    limit: usize,
+    in_flight: Mutex<HashMap<String, usize>>,
+    /// Wakes `acquire` callers when any slot is released
+    released: Notify,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            in_flight: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+        }
+    }
+
+    /// Take a slot for `key`, or `None` if it already has `limit` in flight
+    pub fn try_acquire(self: &Arc<Self>, key: &str) -> Option<SlotGuard> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let count = in_flight.entry(key.to_string()).or_insert(0);
+        if *count >= self.limit {
+            return None;
+        }
+        *count += 1;
+
+        Some(SlotGuard {
+            limiter: Arc::clone(self),
+            key: key.to_string(),
+        })
+    }
+
+    /// Like `try_acquire`, but wait up to `max_wait` for a slot to free up.
+    /// Waiters retry whenever a slot is released, so a burst is served in
+    /// roughly, not strictly, arrival order. `None` once the wait runs out.
+    pub async fn acquire(self: &Arc<Self>, key: &str, max_wait: Duration) -> Option<SlotGuard> {
+        let deadline = Instant::now() + max_wait;
+
+        loop {
+            // Registered before checking, so a release in between isn't missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if let Some(guard) = self.try_acquire(key) {
+                return Some(guard);
+            }
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    pub fn in_flight(&self, key: &str) -> usize {
+        let in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.get(key).copied().unwrap_or(0)
+    }
+
+    fn release(&self, key: &str) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = in_flight.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(key);
+            }
+        }
+        drop(in_flight);
+        self.released.notify_waiters();
+    }
+}
+
+#[derive(Debug)]
+pub struct SlotGuard {
+    limiter: Arc<ConcurrencyLimiter>,
+    key: String,
+}
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        self.limiter.release(&self.key);
+    }
+}
@@ -1,9 +1,14 @@
+use futures::stream::{self, Stream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::config::{Config, ProviderConfig};
 use crate::modules::ai::schema::UsageInfo;
+use crate::services::backend::{ClientBackend, CompletionRequest};
+use crate::services::{body_snippet, tokens};
 
 #[derive(Error, Debug)]
 pub enum LlmError {
@@ -15,6 +20,72 @@ pub enum LlmError {
     MissingApiKey,
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+    /// The prompt (plus `max_tokens`) doesn't fit the model's context
+    /// window. The sizes are whatever the provider's message reported.
+    #[error("{}", context_length_message(*limit, *requested))]
+    ContextLengthExceeded {
+        limit: Option<u32>,
+        requested: Option<u32>,
+    },
+}
+
+fn context_length_message(limit: Option<u32>, requested: Option<u32>) -> String {
+    let mut message = "Request exceeds the model's context window".to_string();
+    match (limit, requested) {
+        (Some(limit), Some(requested)) => {
+            message.push_str(&format!(" ({} tokens requested, limit {})", requested, limit))
+        }
+        (Some(limit), None) => message.push_str(&format!(" (limit {} tokens)", limit)),
+        (None, Some(requested)) => message.push_str(&format!(" ({} tokens requested)", requested)),
+        (None, None) => {}
+    }
+    message.push_str("; shorten the prompt or use a model with a larger context");
+    message
+}
+
+/// Markers of a context-length error in provider messages, lowercase:
+/// OpenAI-style, OpenRouter and Anthropic (via OpenRouter) respectively
+const CONTEXT_LENGTH_MARKERS: &[&str] = &[
+    "maximum context length",
+    "context length exceeded",
+    "prompt is too long",
+];
+
+/// Classify a provider error body. Context-length errors, recognized by
+/// the `context_length_exceeded` code or the message, become
+/// `ContextLengthExceeded`; anything else is an `ApiError` with the
+/// provider's message (or the raw body).
+pub fn provider_error(body: &str) -> LlmError {
+    let Ok(response) = serde_json::from_str::<ApiErrorResponse>(body) else {
+        return LlmError::ApiError(body.to_string());
+    };
+    let message = response.error.message;
+    let lowercase = message.to_lowercase();
+
+    let code = response.error.code.as_ref().and_then(|c| c.as_str());
+    if code != Some("context_length_exceeded") && !CONTEXT_LENGTH_MARKERS.iter().any(|m| lowercase.contains(m)) {
+        return LlmError::ApiError(message);
+    }
+
+    LlmError::ContextLengthExceeded {
+        limit: number_after(&lowercase, "maximum context length is")
+            .or_else(|| number_after(&lowercase, " > ")),
+        requested: number_after(&lowercase, "requested")
+            .or_else(|| number_after(&lowercase, "prompt is too long:")),
+    }
+}
+
+/// The first number shortly after `marker` in `text`, e.g. 8192 in
+/// "maximum context length is 8192 tokens" or "requested about 9000"
+fn number_after(text: &str, marker: &str) -> Option<u32> {
+    let rest = &text[text.find(marker)? + marker.len()..];
+    let start = rest.find(|c: char| c.is_ascii_digit()).filter(|&i| i <= 8)?;
+    let digits = rest[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == ',')
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    digits.parse().ok()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -23,10 +94,229 @@ pub enum LlmProvider {
     Groq,
 }
 
-#[derive(Debug, Serialize)]
-struct ChatMessage {
-    role: String,
-    content: String,
+impl LlmProvider {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "openrouter" => Some(LlmProvider::OpenRouter),
+            "groq" => Some(LlmProvider::Groq),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProvider::OpenRouter => "openrouter",
+            LlmProvider::Groq => "groq",
+        }
+    }
+}
+
+/// Models known to accept a reasoning effort/budget parameter
+const REASONING_MODELS: &[&str] = &[
+    "nvidia/nemotron-3-nano-30b-a3b:free",
+    "nex-agi/deepseek-v3.1-nex-n1:free",
+];
+
+pub fn supports_reasoning(model: &str) -> bool {
+    REASONING_MODELS.contains(&model)
+}
+
+/// Whisper reports languages either as ISO-639-1 codes or lowercase names
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("hi", "Hindi"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// `(code, name)` of every language with a known name
+pub fn supported_languages() -> &'static [(&'static str, &'static str)] {
+    LANGUAGE_NAMES
+}
+
+/// Human-readable name for a Whisper language code or name
+pub fn language_name(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase();
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(code, name)| *code == language || name.to_lowercase() == language)
+        .map(|(_, name)| *name)
+}
+
+/// Instruction appended to English system prompts so the model answers in
+/// the speaker's language. `None` for English or unrecognised languages.
+pub fn language_instruction(language: &str) -> Option<String> {
+    language_name(language)
+        .filter(|name| *name != "English")
+        .map(|name| format!("\n\nAlways respond in {}.", name))
+}
+
+/// System prompt for session titles, unless `AUTO_TITLE_PROMPT` overrides it
+pub const DEFAULT_TITLE_PROMPT: &str = "Write a short title (at most 6 words) for a conversation that starts with the user's message. Reply with the title only.";
+
+/// Longest title a session accepts
+pub const TITLE_MAX_CHARS: usize = 100;
+
+/// Prompts `LlmClient::complete_many` has in flight at once
+pub const COMPLETE_MANY_CONCURRENCY: usize = 4;
+
+/// Conversations longer than this (estimated) are summarized piecewise
+/// before being titled, see `LlmClient::condense`
+pub const CONDENSE_CHUNK_TOKENS: usize = 3000;
+
+/// Map step of `LlmClient::condense`
+const CONDENSE_PROMPT: &str = "Summarize this part of a conversation in 2-3 sentences, \
+keeping the main topics. Reply with the summary only.";
+
+/// Split `lines` into chunks of at most `max_tokens` (estimated), keeping
+/// lines whole. A line too long for a chunk of its own is cut to fit.
+pub fn chunk_lines(lines: &[String], max_tokens: usize) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+
+    for line in lines {
+        let line = if tokens::estimate_tokens(line) > max_tokens {
+            line.chars().take(max_tokens * 4).collect()
+        } else {
+            line.clone()
+        };
+        let size = tokens::estimate_tokens(&chunk) + tokens::estimate_tokens(&line);
+        if !chunk.is_empty() && size > max_tokens {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(&line);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Temperature for suggestion and analysis types missing from the map
+pub const DEFAULT_TEMPERATURE: f32 = 0.3;
+
+/// Default temperature per suggestion/analysis type, overridable with
+/// `TYPE_TEMPERATURES`. Code wants deterministic answers; meeting replies
+/// read better with a little variety. `general` covers requests without a type.
+pub const DEFAULT_TYPE_TEMPERATURES: &[(&str, f32)] = &[
+    ("general", 0.3),
+    ("interview", 0.2),
+    ("coding_interview", 0.1),
+    ("leetcode", 0.0),
+    ("coding", 0.0),
+    ("meeting", 0.6),
+    ("sentiment", 0.0),
+    ("intent", 0.1),
+    ("summary", 0.3),
+    ("technical", 0.2),
+    ("debug", 0.1),
+];
+
+/// A suggestion or analysis type with surrounding whitespace removed. Blank
+/// types are `None`, the same as leaving the field out.
+pub fn normalize_type(kind: Option<&str>) -> Option<&str> {
+    kind.map(str::trim).filter(|kind| !kind.is_empty())
+}
+
+/// Put ahead of a transcription below `STT_MIN_ANSWER_CONFIDENCE` when the
+/// configured action is `caveat`
+pub const LOW_CONFIDENCE_NOTE: &str = "[This transcription has low confidence and parts of it may \
+be misheard. If the question is unclear, say what you heard and ask for it to be repeated \
+rather than guessing.]";
+
+/// Move `<think>...</think>` blocks out of `content`, for models that inline
+/// their reasoning. Returns the answer and the blocks' text, if any. An
+/// unclosed block (the output was cut off) runs to the end.
+pub fn split_think(content: &str) -> (String, Option<String>) {
+    let mut answer = String::new();
+    let mut thoughts = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("<think>") {
+        answer.push_str(&rest[..start]);
+        let inner = &rest[start + "<think>".len()..];
+        let (thought, after) = match inner.find("</think>") {
+            Some(end) => (&inner[..end], &inner[end + "</think>".len()..]),
+            None => (inner, ""),
+        };
+        if !thought.trim().is_empty() {
+            thoughts.push(thought.trim());
+        }
+        rest = after;
+    }
+    answer.push_str(rest);
+
+    let reasoning = (!thoughts.is_empty()).then(|| thoughts.join("\n\n"));
+    (answer.trim().to_string(), reasoning)
+}
+
+/// Whether a message says enough to title a session from, rather than
+/// being a greeting like "hi"
+pub fn is_substantive(message: &str, min_words: usize) -> bool {
+    message.split_whitespace().count() >= min_words.max(1)
+}
+
+/// Tidy a model-written title: drop a `Title:` prefix, surrounding quotes
+/// and trailing punctuation, and cut it to `TITLE_MAX_CHARS` at a word
+/// boundary. `None` if nothing is left.
+pub fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().find(|l| !l.trim().is_empty())?.trim();
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+
+    let is_quote = |c: char| matches!(c, '"' | '\'' | '`' | '*' | '“' | '”' | '‘' | '’');
+    let title = line
+        .trim()
+        .trim_matches(is_quote)
+        .trim_end_matches(|c: char| matches!(c, '.' | '!' | ',' | ';' | ':') || is_quote(c))
+        .trim();
+
+    let title = if title.chars().count() > TITLE_MAX_CHARS {
+        let cut: String = title.chars().take(TITLE_MAX_CHARS).collect();
+        match cut.rfind(char::is_whitespace) {
+            Some(i) if i > 0 => cut[..i].trim_end().to_string(),
+            _ => cut,
+        }
+    } else {
+        title.to_string()
+    };
+
+    (!title.is_empty()).then_some(title)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: &str) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -37,6 +327,245 @@ struct ChatRequest {
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    /// OpenRouter's unified reasoning parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<ReasoningConfig>,
+    /// Groq/OpenAI-style reasoning parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    /// Stable end-user id for provider-side abuse tracking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    /// Ask for a final chunk carrying the token usage
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamResponse {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+/// A piece of a streamed completion
+#[derive(Debug, Clone)]
+pub enum StreamChunk {
+    Delta(String),
+    /// Only sent by providers that honour `stream_options.include_usage`
+    Usage(UsageInfo),
+    Done,
+}
+
+/// Longest reply `suggest` asks for
+const SUGGEST_MAX_TOKENS: u32 = 800;
+
+/// System and user messages for a suggestion of `suggestion_type`
+fn suggest_messages(
+    context: &str,
+    suggestion_type: Option<&str>,
+    language: Option<&str>,
+) -> Vec<ChatMessage> {
+    let suggestion_type = normalize_type(suggestion_type);
+    let system_prompt = match suggestion_type {
+        Some("interview") | Some("coding_interview") => r#"You are a real-time coding interview coach. Be EXTREMELY concise.
+
+For coding: give optimal solution in code block, then "Time: O(?) | Space: O(?) | Pattern: [name]"
+For behavioral: give 2-3 bullet points max
+For system design: list 3-5 key components
+
+NO lengthy explanations. Direct answers only."#,
+
+        Some("leetcode") | Some("coding") => r#"You are an expert competitive programmer. Give CONCISE answers.
+
+FORMAT:
+```python
+[code]
+```
+Time: O(?) | Space: O(?) | Pattern: [name]
+
+NO explanations unless asked. Code only."#,
+
+        Some("meeting") => r#"You are a meeting assistant providing real-time suggestions.
+
+RULES:
+- Give actionable responses the user can say immediately
+- Keep suggestions brief (1-2 sentences each)
+- Be professional but natural
+- Provide 2-3 options when appropriate"#,
+
+        _ => r#"You are Cleuly, a real-time AI assistant. Be direct, concise, and helpful. Give answers the user can use immediately."#,
+    };
+
+    let prompt = match suggestion_type {
+        Some("interview") | Some("coding_interview") | Some("leetcode") | Some("coding") => {
+            format!("Solve this:\n\n{}", context)
+        }
+        _ => format!("Help with this:\n\n{}", context),
+    };
+
+    let system_prompt = match language.and_then(language_instruction) {
+        Some(instruction) => format!("{}{}", system_prompt, instruction),
+        None => system_prompt.to_string(),
+    };
+
+    vec![ChatMessage::new("system", &system_prompt), ChatMessage::new("user", &prompt)]
+}
+
+/// Parse one line of a provider's SSE stream. Comments (OpenRouter sends
+/// `: OPENROUTER PROCESSING`), blank lines and empty deltas yield nothing.
+pub fn parse_stream_line(line: &str) -> Result<Vec<StreamChunk>, LlmError> {
+    let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+        return Ok(Vec::new());
+    };
+
+    if data == "[DONE]" {
+        return Ok(vec![StreamChunk::Done]);
+    }
+
+    let response: StreamResponse = serde_json::from_str(data).map_err(|e| {
+        LlmError::InvalidResponse(format!(
+            "unexpected stream chunk ({}): {}",
+            e,
+            body_snippet(data)
+        ))
+    })?;
+
+    let mut chunks = response
+        .choices
+        .into_iter()
+        .filter_map(|c| c.delta.content)
+        .filter(|c| !c.is_empty())
+        .map(StreamChunk::Delta)
+        .collect::<Vec<_>>();
+
+    if let Some(u) = response.usage {
+        chunks.push(StreamChunk::Usage(UsageInfo {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+            total_tokens: u.total_tokens,
+        }));
+    }
+
+    Ok(chunks)
+}
+
+/// Extract one score per candidate from a rerank completion shaped like
+/// `{"scores": [0.9, 0.1]}`. Code fences or prose around the JSON object are
+/// tolerated; scores are clamped to `[0, 1]`.
+pub fn parse_rerank_scores(content: &str, expected: usize) -> Result<Vec<f32>, LlmError> {
+    #[derive(Deserialize)]
+    struct Scores {
+        scores: Vec<f32>,
+    }
+
+    let json = match (content.find('{'), content.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &content[start..=end],
+        _ => content,
+    };
+
+    let parsed: Scores = serde_json::from_str(json).map_err(|e| {
+        LlmError::InvalidResponse(format!(
+            "rerank scores are not valid JSON ({}): {}",
+            e,
+            body_snippet(content)
+        ))
+    })?;
+
+    if parsed.scores.len() != expected {
+        return Err(LlmError::InvalidResponse(format!(
+            "Expected {} rerank scores, got {}",
+            expected,
+            parsed.scores.len()
+        )));
+    }
+
+    Ok(parsed.scores.into_iter().map(|s| s.clamp(0.0, 1.0)).collect())
+}
+
+/// Incremental reader over a streaming completion response
+struct StreamState {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    pending: VecDeque<StreamChunk>,
+    finished: bool,
+}
+
+impl StreamState {
+    /// Move every complete line in the buffer into `pending`
+    fn drain_lines(&mut self) -> Result<(), LlmError> {
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line = self.buffer.drain(..=pos).collect::<Vec<_>>();
+            self.push_line(&line)?;
+        }
+        Ok(())
+    }
+
+    fn push_line(&mut self, line: &[u8]) -> Result<(), LlmError> {
+        for chunk in parse_stream_line(&String::from_utf8_lossy(line))? {
+            if matches!(chunk, StreamChunk::Done) {
+                self.finished = true;
+            }
+            self.pending.push_back(chunk);
+        }
+        Ok(())
+    }
+
+    async fn next(mut self) -> Option<(Result<StreamChunk, LlmError>, Self)> {
+        loop {
+            if let Some(chunk) = self.pending.pop_front() {
+                return Some((Ok(chunk), self));
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(bytes)) => {
+                    self.buffer.extend_from_slice(&bytes);
+                    if let Err(e) = self.drain_lines() {
+                        self.finished = true;
+                        return Some((Err(e), self));
+                    }
+                }
+                Ok(None) => {
+                    // Body ended without [DONE]; flush a final unterminated line
+                    self.finished = true;
+                    let rest = std::mem::take(&mut self.buffer);
+                    if let Err(e) = self.push_line(&rest) {
+                        return Some((Err(e), self));
+                    }
+                    self.pending.push_back(StreamChunk::Done);
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some((Err(e.into()), self));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReasoningConfig {
+    effort: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,11 +578,17 @@ struct ChatResponse {
 #[derive(Debug, Deserialize)]
 struct ChatChoice {
     message: ChatMessageResponse,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatMessageResponse {
     content: String,
+    /// OpenRouter's `reasoning`, or `reasoning_content` as DeepSeek-style
+    /// APIs call it
+    #[serde(default, alias = "reasoning_content")]
+    reasoning: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -71,12 +606,39 @@ struct ApiErrorResponse {
 #[derive(Debug, Deserialize)]
 struct ApiErrorDetail {
     message: String,
+    /// A string like `context_length_exceeded` for OpenAI-style providers,
+    /// the HTTP status for OpenRouter
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
 }
 
 pub struct LlmResponse {
     pub id: String,
     pub content: String,
     pub usage: Option<UsageInfo>,
+    /// As reported by the provider: `stop`, `length` (hit `max_tokens`),
+    /// `content_filter`, ...
+    pub finish_reason: Option<String>,
+    /// The model's reasoning, when reported apart from `content` or split
+    /// out of it (see `SPLIT_THINK_BLOCKS`)
+    pub reasoning: Option<String>,
 }
 
 #[derive(Clone)]
@@ -85,42 +647,111 @@ pub struct LlmClient {
     base_url: String,
     api_key: String,
     provider: LlmProvider,
+    default_model: String,
+    type_temperatures: BTreeMap<String, f32>,
+    split_think: bool,
+    user: Option<String>,
+    backend: Option<Arc<dyn ClientBackend>>,
 }
 
 impl LlmClient {
-    pub fn new() -> Result<Self, LlmError> {
-        let api_key = env::var("OPENROUTER_API_KEY").map_err(|_| LlmError::MissingApiKey)?;
-        let base_url =
-            env::var("OPENROUTER_BASE_URL").unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
-
-        Ok(Self {
-            client: Client::new(),
-            base_url,
-            api_key,
-            provider: LlmProvider::OpenRouter,
-        })
+    pub fn new(config: &Config) -> Result<Self, LlmError> {
+        Self::for_provider(config, LlmProvider::OpenRouter)
     }
 
     /// Create a Groq client for faster inference (~500ms vs ~700ms+)
-    pub fn new_groq() -> Result<Self, LlmError> {
-        let api_key = env::var("GROQ_API_KEY").map_err(|_| LlmError::MissingApiKey)?;
-        let base_url =
-            env::var("GROQ_BASE_URL").unwrap_or_else(|_| "https://api.groq.com/openai/v1".to_string());
+    pub fn new_groq(config: &Config) -> Result<Self, LlmError> {
+        Self::for_provider(config, LlmProvider::Groq)
+    }
+
+    pub fn for_provider(config: &Config, provider: LlmProvider) -> Result<Self, LlmError> {
+        let Some(backend) = config.client_backend.clone() else {
+            let settings = config
+                .provider(provider.as_str())
+                .ok_or(LlmError::MissingApiKey)?;
+            return Ok(Self::from_settings(config, settings, provider));
+        };
+
+        let default_model = config
+            .provider(provider.as_str())
+            .map(|p| p.default_model.clone())
+            .unwrap_or_else(|| config.default_model.clone());
 
         Ok(Self {
-            client: Client::new(),
-            base_url,
-            api_key,
-            provider: LlmProvider::Groq,
+            client: super::http_client(),
+            base_url: String::new(),
+            api_key: String::new(),
+            provider,
+            default_model,
+            type_temperatures: config.type_temperatures.clone(),
+            split_think: config.split_think_blocks,
+            user: None,
+            backend: Some(backend),
         })
     }
 
+    fn from_settings(config: &Config, settings: &ProviderConfig, provider: LlmProvider) -> Self {
+        Self {
+            client: super::http_client(),
+            base_url: settings.base_url.clone(),
+            api_key: settings.api_key.clone(),
+            provider,
+            default_model: settings.default_model.clone(),
+            type_temperatures: config.type_temperatures.clone(),
+            split_think: config.split_think_blocks,
+            user: None,
+            backend: None,
+        }
+    }
+
+    /// Temperature `suggest`/`analyze` use for a type (`None` is `general`)
+    pub fn temperature_for(&self, kind: Option<&str>) -> f32 {
+        self.type_temperatures
+            .get(normalize_type(kind).unwrap_or("general"))
+            .copied()
+            .unwrap_or(DEFAULT_TEMPERATURE)
+    }
+
+    /// Attribute chat requests to an end user (the OpenAI `user` field)
+    pub fn with_user(mut self, user: Option<String>) -> Self {
+        self.user = user;
+        self
+    }
+
     /// Get the default fast model for this provider
     pub fn default_model(&self) -> &str {
-        match self.provider {
-            LlmProvider::Groq => "llama-3.1-8b-instant",
-            LlmProvider::OpenRouter => "nvidia/nemotron-3-nano-30b-a3b:free",
+        &self.default_model
+    }
+
+    /// Authenticated no-op request to verify the API key. OpenRouter's model
+    /// list is public, so it checks the key endpoint instead.
+    pub async fn check_connectivity(&self) -> Result<(), LlmError> {
+        if self.backend.is_some() {
+            return Ok(());
+        }
+
+        let path = match self.provider {
+            LlmProvider::OpenRouter => "key",
+            LlmProvider::Groq => "models",
+        };
+
+        let response = self
+            .client
+            .get(format!("{}/{}", self.base_url, path))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            if let Ok(error_response) = serde_json::from_str::<ApiErrorResponse>(&error_text) {
+                return Err(LlmError::ApiError(error_response.error.message));
+            }
+            return Err(LlmError::ApiError(format!("{}: {}", status, error_text)));
         }
+
+        Ok(())
     }
 
     pub async fn complete(
@@ -130,57 +761,201 @@ impl LlmClient {
         system_prompt: Option<&str>,
         max_tokens: Option<u32>,
         temperature: Option<f32>,
+    ) -> Result<LlmResponse, LlmError> {
+        self.complete_with_reasoning(prompt, model, system_prompt, max_tokens, temperature, None)
+            .await
+    }
+
+    /// Complete each of `prompts` with the same system prompt and settings,
+    /// `COMPLETE_MANY_CONCURRENCY` at a time. Results are in input order and
+    /// fail individually.
+    pub async fn complete_many(
+        &self,
+        prompts: &[String],
+        model: &str,
+        system_prompt: Option<&str>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Vec<Result<LlmResponse, LlmError>> {
+        // Built up front: a lazily mapped iterator keeps the future from being
+        // `Send` in handlers
+        let completions = prompts
+            .iter()
+            .map(|prompt| self.complete(prompt, model, system_prompt, max_tokens, temperature))
+            .collect::<Vec<_>>();
+
+        stream::iter(completions)
+            .buffered(COMPLETE_MANY_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Like `complete`, but forwards a reasoning effort ("low"/"medium"/"high")
+    /// to models that support it. Ignored for all other models.
+    pub async fn complete_with_reasoning(
+        &self,
+        prompt: &str,
+        model: &str,
+        system_prompt: Option<&str>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        reasoning_effort: Option<&str>,
     ) -> Result<LlmResponse, LlmError> {
         let mut messages = Vec::new();
 
         if let Some(sys) = system_prompt {
-            messages.push(ChatMessage {
-                role: "system".to_string(),
-                content: sys.to_string(),
-            });
+            messages.push(ChatMessage::new("system", sys));
         }
 
-        messages.push(ChatMessage {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-        });
+        messages.push(ChatMessage::new("user", prompt));
+
+        self.complete_messages(messages, model, max_tokens, temperature, reasoning_effort)
+            .await
+    }
+
+    fn chat_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        model: &str,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        reasoning_effort: Option<&str>,
+    ) -> ChatRequest {
+        let reasoning_effort = reasoning_effort.filter(|_| supports_reasoning(model));
 
-        let request = ChatRequest {
+        ChatRequest {
             model: model.to_string(),
             messages,
             max_tokens,
             temperature,
-        };
+            reasoning: reasoning_effort
+                .filter(|_| self.provider == LlmProvider::OpenRouter)
+                .map(|e| ReasoningConfig { effort: e.to_string() }),
+            reasoning_effort: reasoning_effort
+                .filter(|_| self.provider == LlmProvider::Groq)
+                .map(|e| e.to_string()),
+            stream: false,
+            stream_options: None,
+            user: self.user.clone(),
+        }
+    }
 
-        let mut req = self
-            .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json");
+    /// POST a chat request, turning non-2xx responses into `ApiError`
+    async fn send_chat(&self, request: &ChatRequest) -> Result<reqwest::Response, LlmError> {
+        let response = super::send_with_retry(|| {
+            let mut req = self
+                .client
+                .post(format!("{}/chat/completions", self.base_url))
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json");
 
-        // OpenRouter requires these headers
-        if self.provider == LlmProvider::OpenRouter {
-            req = req
-                .header("HTTP-Referer", "https://cleuly.app")
-                .header("X-Title", "Cleuly");
-        }
+            // OpenRouter requires these headers
+            if self.provider == LlmProvider::OpenRouter {
+                req = req
+                    .header("HTTP-Referer", "https://cleuly.app")
+                    .header("X-Title", "Cleuly");
+            }
 
-        let response = req.json(&request).send().await?;
+            Ok::<_, LlmError>(req.json(request))
+        })
+        .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            if let Ok(error_response) = serde_json::from_str::<ApiErrorResponse>(&error_text) {
-                return Err(LlmError::ApiError(error_response.error.message));
-            }
-            return Err(LlmError::ApiError(error_text));
+            return Err(provider_error(&error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Stream a completion as it's generated. Usage is requested via
+    /// `stream_options.include_usage`; providers that ignore it simply
+    /// never yield a `Usage` chunk. The stream always ends with `Done`.
+    pub async fn complete_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        model: &str,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        reasoning_effort: Option<&str>,
+    ) -> Result<impl Stream<Item = Result<StreamChunk, LlmError>> + Send + 'static, LlmError> {
+        if let Some(backend) = &self.backend {
+            let response = backend
+                .complete(self.completion_request(messages, model, max_tokens, temperature))
+                .await?;
+
+            let mut chunks = vec![StreamChunk::Delta(response.content)];
+            chunks.extend(response.usage.map(StreamChunk::Usage));
+            chunks.push(StreamChunk::Done);
+            return Ok(stream::iter(chunks.into_iter().map(Ok)).left_stream());
         }
 
-        let chat_response: ChatResponse = response.json().await?;
+        let mut request = self.chat_request(messages, model, max_tokens, temperature, reasoning_effort);
+        request.stream = true;
+        request.stream_options = Some(StreamOptions { include_usage: true });
 
-        let content = chat_response
+        let response = self.send_chat(&request).await?;
+
+        let state = StreamState {
+            response,
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        };
+
+        Ok(stream::unfold(state, StreamState::next).right_stream())
+    }
+
+    fn completion_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        model: &str,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> CompletionRequest {
+        CompletionRequest {
+            model: model.to_string(),
+            messages,
+            max_tokens,
+            temperature,
+            user: self.user.clone(),
+        }
+    }
+
+    /// Send a full multi-turn message array to the provider
+    pub async fn complete_messages(
+        &self,
+        messages: Vec<ChatMessage>,
+        model: &str,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        reasoning_effort: Option<&str>,
+    ) -> Result<LlmResponse, LlmError> {
+        if let Some(backend) = &self.backend {
+            return backend
+                .complete(self.completion_request(messages, model, max_tokens, temperature))
+                .await
+                .map(|response| self.split_reasoning(response));
+        }
+
+        let request = self.chat_request(messages, model, max_tokens, temperature, reasoning_effort);
+        let response = self.send_chat(&request).await?;
+
+        let body = response.text().await?;
+        let chat_response: ChatResponse = serde_json::from_str(&body).map_err(|e| {
+            LlmError::InvalidResponse(format!(
+                "{} returned an unexpected body for model {} ({}): {}",
+                self.provider.as_str(),
+                model,
+                e,
+                body_snippet(&body)
+            ))
+        })?;
+
+        let choice = chat_response
             .choices
-            .first()
-            .map(|c| c.message.content.clone())
+            .into_iter()
+            .next()
             .ok_or_else(|| LlmError::InvalidResponse("No choices in response".to_string()))?;
 
         let usage = chat_response.usage.map(|u| UsageInfo {
@@ -189,55 +964,148 @@ impl LlmClient {
             total_tokens: u.total_tokens,
         });
 
-        Ok(LlmResponse {
+        Ok(self.split_reasoning(LlmResponse {
             id: chat_response.id,
-            content,
+            content: choice.message.content,
             usage,
-        })
+            finish_reason: choice.finish_reason,
+            reasoning: choice.message.reasoning.filter(|r| !r.trim().is_empty()),
+        }))
     }
 
-    pub async fn suggest(&self, context: &str, model: &str, suggestion_type: Option<&str>) -> Result<LlmResponse, LlmError> {
-        let system_prompt = match suggestion_type {
-            Some("interview") | Some("coding_interview") => r#"You are a real-time coding interview coach. Be EXTREMELY concise.
+    /// With `SPLIT_THINK_BLOCKS`, move inline `<think>` blocks into
+    /// `reasoning`, after any reasoning the provider reported separately
+    fn split_reasoning(&self, mut response: LlmResponse) -> LlmResponse {
+        if !self.split_think {
+            return response;
+        }
 
-For coding: give optimal solution in code block, then "Time: O(?) | Space: O(?) | Pattern: [name]"
-For behavioral: give 2-3 bullet points max
-For system design: list 3-5 key components
+        let (content, thoughts) = split_think(&response.content);
+        if let Some(thoughts) = thoughts {
+            response.content = content;
+            response.reasoning = Some(match response.reasoning.take() {
+                Some(reasoning) => format!("{}\n\n{}", reasoning, thoughts),
+                None => thoughts,
+            });
+        }
+        response
+    }
 
-NO lengthy explanations. Direct answers only."#,
+    /// Embed each input with an OpenAI-compatible `/embeddings` endpoint.
+    /// Vectors are returned in input order.
+    pub async fn embed(&self, inputs: &[String], model: &str) -> Result<Vec<Vec<f32>>, LlmError> {
+        if let Some(backend) = &self.backend {
+            return backend.embed(inputs.to_vec(), model.to_string()).await;
+        }
 
-            Some("leetcode") | Some("coding") => r#"You are an expert competitive programmer. Give CONCISE answers.
+        let request = EmbeddingRequest { model, input: inputs };
 
-FORMAT:
-```python
-[code]
-```
-Time: O(?) | Space: O(?) | Pattern: [name]
+        let response = super::send_with_retry(|| {
+            Ok::<_, LlmError>(
+                self.client
+                    .post(format!("{}/embeddings", self.base_url))
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .json(&request),
+            )
+        })
+        .await?;
 
-NO explanations unless asked. Code only."#,
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(provider_error(&error_text));
+        }
 
-            Some("meeting") => r#"You are a meeting assistant providing real-time suggestions.
+        let body = response.text().await?;
+        let mut embeddings: EmbeddingResponse = serde_json::from_str(&body).map_err(|e| {
+            LlmError::InvalidResponse(format!(
+                "{} returned an unexpected embeddings body for model {} ({}): {}",
+                self.provider.as_str(),
+                model,
+                e,
+                body_snippet(&body)
+            ))
+        })?;
 
-RULES:
-- Give actionable responses the user can say immediately
-- Keep suggestions brief (1-2 sentences each)
-- Be professional but natural
-- Provide 2-3 options when appropriate"#,
+        if embeddings.data.len() != inputs.len() {
+            return Err(LlmError::InvalidResponse(format!(
+                "Expected {} embeddings, got {}",
+                inputs.len(),
+                embeddings.data.len()
+            )));
+        }
 
-            _ => r#"You are Cleuly, a real-time AI assistant. Be direct, concise, and helpful. Give answers the user can use immediately."#,
-        };
+        embeddings.data.sort_by_key(|d| d.index);
+        Ok(embeddings.data.into_iter().map(|d| d.embedding).collect())
+    }
 
-        let prompt = match suggestion_type {
-            Some("interview") | Some("coding_interview") | Some("leetcode") | Some("coding") => {
-                format!("Solve this:\n\n{}", context)
-            }
-            _ => format!("Help with this:\n\n{}", context),
-        };
+    /// `language` appends a "respond in ..." instruction for non-English speakers.
+    /// `temperature` overrides the type's default, see `temperature_for`.
+    pub async fn suggest(
+        &self,
+        context: &str,
+        model: &str,
+        suggestion_type: Option<&str>,
+        language: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Result<LlmResponse, LlmError> {
+        let messages = suggest_messages(context, suggestion_type, language);
+        let temperature = temperature.unwrap_or_else(|| self.temperature_for(suggestion_type));
+        self.complete_messages(messages, model, Some(SUGGEST_MAX_TOKENS), Some(temperature), None)
+            .await
+    }
 
-        self.complete(&prompt, model, Some(system_prompt), Some(800), Some(0.3)).await
+    /// Streaming variant of `suggest`, with the same prompts and defaults
+    pub async fn suggest_stream(
+        &self,
+        context: &str,
+        model: &str,
+        suggestion_type: Option<&str>,
+        language: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Result<impl Stream<Item = Result<StreamChunk, LlmError>> + Send + 'static, LlmError> {
+        let messages = suggest_messages(context, suggestion_type, language);
+        let temperature = temperature.unwrap_or_else(|| self.temperature_for(suggestion_type));
+        self.complete_stream(messages, model, Some(SUGGEST_MAX_TOKENS), Some(temperature), None)
+            .await
+    }
+
+    /// Score each candidate's relevance to `query`. Returns the scores in
+    /// candidate order along with the raw completion.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        candidates: &[String],
+        model: &str,
+    ) -> Result<(Vec<f32>, LlmResponse), LlmError> {
+        let system_prompt = r#"You rank candidate answers by how well they answer a query.
+Respond with JSON only, exactly in this shape: {"scores": [0.0, ...]}
+Give one score between 0 and 1 per candidate, in the order the candidates are listed. No other text."#;
+
+        let listed = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("[{}] {}", i, c))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = format!("Query:\n{}\n\nCandidates:\n{}", query, listed);
+
+        let max_tokens = 20 + 10 * candidates.len() as u32;
+        let response = self
+            .complete(&prompt, model, Some(system_prompt), Some(max_tokens), Some(0.0))
+            .await?;
+
+        let scores = parse_rerank_scores(&response.content, candidates.len())?;
+        Ok((scores, response))
     }
 
-    pub async fn analyze(&self, text: &str, model: &str, analysis_type: Option<&str>) -> Result<LlmResponse, LlmError> {
+    pub async fn analyze(
+        &self,
+        text: &str,
+        model: &str,
+        analysis_type: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Result<LlmResponse, LlmError> {
+        let analysis_type = normalize_type(analysis_type);
         let system_prompt = match analysis_type {
             Some("sentiment") => "Analyze sentiment briefly. Format: [POSITIVE/NEGATIVE/NEUTRAL] - one line explanation.",
             Some("intent") => "Identify the speaker's intent in one sentence.",
@@ -247,8 +1115,33 @@ RULES:
             _ => "Provide a brief, useful analysis.",
         };
 
-        let prompt = format!("{}", text);
+        let temperature = temperature.unwrap_or_else(|| self.temperature_for(analysis_type));
+        self.complete(text, model, Some(system_prompt), Some(600), Some(temperature)).await
+    }
+
+    /// Raw title for a conversation opening with `message`; see `clean_title`
+    pub async fn title(&self, message: &str, model: &str, prompt: &str) -> Result<LlmResponse, LlmError> {
+        self.complete(message, model, Some(prompt), Some(30), Some(0.3)).await
+    }
+
+    /// Shrink a conversation (one line per message) to fit a single prompt
+    /// of `CONDENSE_CHUNK_TOKENS`: summarize each chunk (through
+    /// `complete_many`, so a long conversation doesn't fire every request at
+    /// once), then the summaries, until one chunk is left. Short
+    /// conversations are returned as is.
+    pub async fn condense(&self, lines: &[String], model: &str) -> Result<String, LlmError> {
+        let mut chunks = chunk_lines(lines, CONDENSE_CHUNK_TOKENS);
+
+        while chunks.len() > 1 {
+            let summaries = self
+                .complete_many(&chunks, model, Some(CONDENSE_PROMPT), Some(150), Some(0.3))
+                .await
+                .into_iter()
+                .map(|s| s.map(|s| s.content.trim().to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            chunks = chunk_lines(&summaries, CONDENSE_CHUNK_TOKENS);
+        }
 
-        self.complete(&prompt, model, Some(system_prompt), Some(600), Some(0.3)).await
+        Ok(chunks.pop().unwrap_or_default())
     }
 }
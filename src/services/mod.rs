@@ -1,2 +1,121 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::util::retry::retry_with_backoff;
+
+pub mod audio;
+pub mod backend;
+pub mod bundle;
+pub mod cache;
+pub mod diarization;
+pub mod discovery;
+pub mod embeddings;
+pub mod limiter;
 pub mod llm;
+pub mod prompts;
+pub mod quota;
+pub mod retention;
 pub mod stt;
+pub mod tokens;
+pub mod warmup;
+
+/// Process-wide HTTP client so provider connections (TLS, DNS) are pooled
+/// across requests instead of being re-established by every handler.
+pub fn http_client() -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(Client::new).clone()
+}
+
+/// Attempts made by `send_with_retry`, including the first one
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Backoff between provider attempts, see `util::retry`
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(4);
+
+/// Whether a provider status is worth retrying: rate limits and 5xx.
+/// Anything else (e.g. a 400 for a malformed request) will fail again.
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Truncate a provider body so it can be embedded in an error message
+pub(crate) fn body_snippet(body: &str) -> String {
+    const MAX_CHARS: usize = 200;
+    if body.chars().count() <= MAX_CHARS {
+        body.to_string()
+    } else {
+        format!("{}...", body.chars().take(MAX_CHARS).collect::<String>())
+    }
+}
+
+/// Whether a failed request is worth retrying. Timeouts and connection
+/// failures are; builder, redirect and body-decode errors are not.
+pub fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.status().is_some_and(is_transient_status)
+}
+
+/// Send a request, retrying transient failures with a jittered exponential
+/// backoff.
+/// `build` is called once per attempt because multipart bodies can't be
+/// cloned. The last response is returned as-is, even if unsuccessful.
+pub async fn send_with_retry<F, E>(build: F) -> Result<Response, E>
+where
+    F: Fn() -> Result<RequestBuilder, E>,
+    E: From<reqwest::Error>,
+{
+    send_with_attempts(MAX_ATTEMPTS, build).await
+}
+
+/// `send_with_retry` with an explicit number of attempts (at least one)
+pub async fn send_with_attempts<F, E>(max_attempts: u32, build: F) -> Result<Response, E>
+where
+    F: Fn() -> Result<RequestBuilder, E>,
+    E: From<reqwest::Error>,
+{
+    let result = retry_with_backoff(
+        max_attempts,
+        RETRY_BASE_DELAY,
+        RETRY_MAX_DELAY,
+        SendFailure::is_transient,
+        || async {
+            let response = build().map_err(SendFailure::Build)?.send().await.map_err(|e| {
+                tracing::debug!("Provider request failed ({})", e);
+                SendFailure::Request(e)
+            })?;
+
+            if is_transient_status(response.status()) {
+                tracing::debug!("Provider returned {}", response.status());
+                return Err(SendFailure::Status(response));
+            }
+            Ok(response)
+        },
+    )
+    .await;
+
+    match result {
+        Ok(response) | Err(SendFailure::Status(response)) => Ok(response),
+        Err(SendFailure::Request(e)) => Err(e.into()),
+        Err(SendFailure::Build(e)) => Err(e),
+    }
+}
+
+/// Why one attempt of `send_with_attempts` didn't produce a final response
+enum SendFailure<E> {
+    /// Building the request failed; never retried
+    Build(E),
+    Request(reqwest::Error),
+    /// A rate limit or 5xx, returned as-is once attempts run out
+    Status(Response),
+}
+
+impl<E> SendFailure<E> {
+    fn is_transient(&self) -> bool {
+        match self {
+            SendFailure::Build(_) => false,
+            SendFailure::Request(e) => is_transient(e),
+            SendFailure::Status(_) => true,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Named prompt templates from `PROMPT_TEMPLATES`, filled in at request time.
+//!
+//! A template refers to variables as `{name}`, where a name is letters,
+//! digits and underscores. Any other brace is literal text, so JSON examples
+//! inside a prompt come through unchanged; `{{` and `}}` write a literal
+//! brace around something that would otherwise be a variable.
+
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("Unknown prompt template: {0}")]
+    UnknownTemplate(String),
+    #[error("Prompt template variable not provided: {0}")]
+    MissingVariable(String),
+}
+
+/// Name of the variable starting at the `{` that begins `rest`, if any
+fn variable_name(rest: &str) -> Option<&str> {
+    let end = rest[1..].find('}')? + 1;
+    let name = &rest[1..end];
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+/// Fill `template`'s variables from `variables`. A missing variable is an
+/// error when `strict`, and an empty string otherwise.
+pub fn render(
+    template: &str,
+    variables: &BTreeMap<String, String>,
+    strict: bool,
+) -> Result<String, TemplateError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(at) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..at]);
+        rest = &rest[at..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            rendered.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        match variable_name(rest).filter(|_| rest.starts_with('{')) {
+            Some(name) => {
+                match variables.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None if strict => return Err(TemplateError::MissingVariable(name.to_string())),
+                    None => {}
+                }
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                rendered.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Render the template called `name` from `templates`
+pub fn render_named(
+    templates: &BTreeMap<String, String>,
+    name: &str,
+    variables: &BTreeMap<String, String>,
+    strict: bool,
+) -> Result<String, TemplateError> {
+    let template = templates
+        .get(name)
+        .ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))?;
+    render(template, variables, strict)
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    /// Label used in the Redis key, e.g. `2026-10` or `2026-10-18`
+    fn label(&self, now: DateTime<Utc>) -> String {
+        match self {
+            QuotaPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            QuotaPeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// Start of the next period, when the counter expires
+    pub fn resets_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = match self {
+            QuotaPeriod::Daily => now.date_naive() + Duration::days(1),
+            QuotaPeriod::Monthly => {
+                let (year, month) = if now.month() == 12 {
+                    (now.year() + 1, 1)
+                } else {
+                    (now.year(), now.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+            }
+        };
+        Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+    }
+}
+
+pub struct QuotaStatus {
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaStatus {
+    pub fn exceeded(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used >= limit)
+    }
+}
+
+/// Per-key token budget tracked in Redis. Counters are keyed by key id and
+/// period and expire at the period boundary, which is what resets them.
+pub struct QuotaTracker {
+    redis: ConnectionManager,
+    period: QuotaPeriod,
+    limit: Option<u64>,
+}
+
+impl QuotaTracker {
+    /// `DAILY_TOKEN_QUOTA` takes precedence over `MONTHLY_TOKEN_QUOTA`.
+    /// With neither set, usage is still tracked monthly but never rejected.
+    pub fn new(redis: ConnectionManager, config: &Config) -> Self {
+        let (period, limit) = match (config.daily_token_quota, config.monthly_token_quota) {
+            (Some(limit), _) => (QuotaPeriod::Daily, Some(limit)),
+            (None, limit) => (QuotaPeriod::Monthly, limit),
+        };
+
+        Self { redis, period, limit }
+    }
+
+    fn key(&self, key_id: &str, now: DateTime<Utc>) -> String {
+        format!("quota:{}:{}", key_id, self.period.label(now))
+    }
+
+    pub async fn status(&self, key_id: &str) -> QuotaStatus {
+        let now = Utc::now();
+        let mut redis = self.redis.clone();
+        let used: u64 = redis
+            .get::<_, Option<u64>>(self.key(key_id, now))
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+
+        QuotaStatus {
+            used,
+            limit: self.limit,
+            resets_at: self.period.resets_at(now),
+        }
+    }
+
+    pub async fn record(&self, key_id: &str, tokens: u32) {
+        if tokens == 0 {
+            return;
+        }
+
+        let now = Utc::now();
+        let key = self.key(key_id, now);
+        let mut redis = self.redis.clone();
+        let _: Result<u64, _> = redis.incr(&key, tokens).await;
+        let _: Result<bool, _> = redis
+            .expire_at(&key, self.period.resets_at(now).timestamp())
+            .await;
+    }
+}
@@ -0,0 +1,35 @@
+//! Background deletion of old `ai_completions`, enabled by
+//! `AI_COMPLETION_RETENTION_DAYS`.
+//!
+//! Completion timestamps are stored as RFC 3339 strings, which a Mongo TTL
+//! index can't expire, so a task deletes them periodically instead.
+
+use chrono::{Duration as ChronoDuration, Utc};
+use mongodb::Database;
+use std::time::Duration;
+
+use crate::modules::ai::crud::AiCrud;
+
+/// How often expired completions are deleted
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Delete completions older than `retention_days` now and then every
+/// `CLEANUP_INTERVAL`, until the process exits. Failures are logged and
+/// retried on the next run.
+pub fn spawn(db: Database, retention_days: u32) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let crud = AiCrud::new(&db);
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - ChronoDuration::days(retention_days.into());
+            match crud.delete_older_than(cutoff).await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!("Retention: deleted {} old completions", deleted),
+                Err(e) => tracing::warn!("Retention: failed to delete old completions: {}", e),
+            }
+        }
+    })
+}
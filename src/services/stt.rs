@@ -1,9 +1,18 @@
+use reqwest::header::LOCATION;
 use reqwest::multipart::{Form, Part};
-use reqwest::Client;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
 use serde::Deserialize;
-use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::config::Config;
+use crate::services::audio;
+use crate::services::backend::ClientBackend;
+use crate::services::body_snippet;
+
 #[derive(Error, Debug)]
 pub enum SttError {
     #[error("HTTP request failed: {0}")]
@@ -16,8 +25,281 @@ pub enum SttError {
     InvalidResponse(String),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+    #[error("Audio is about {duration:.0}s long; the limit is {limit}s")]
+    AudioTooLong { duration: f32, limit: u32 },
+    /// The provider doesn't know `STT_MODEL`, usually because it was
+    /// renamed or deprecated
+    #[error("{}", model_unavailable_message(model, suggestion.as_deref()))]
+    ModelUnavailable {
+        model: String,
+        suggestion: Option<String>,
+    },
+}
+
+/// Whisper models the provider is known to serve, most preferred first.
+/// Suggested when the configured one is unavailable.
+pub const KNOWN_STT_MODELS: &[&str] = &["whisper-large-v3-turbo", "whisper-large-v3"];
+
+/// Error codes and lowercase message markers of an unknown or retired model.
+/// A marker only counts in a message that names the requested model, so
+/// e.g. an unsupported language isn't mistaken for a missing model.
+const MODEL_UNAVAILABLE_CODES: &[&str] = &["model_not_found", "model_decommissioned"];
+const MODEL_UNAVAILABLE_MARKERS: &[&str] = &["does not exist", "decommissioned"];
+
+fn model_unavailable_message(model: &str, suggestion: Option<&str>) -> String {
+    let mut message = format!(
+        "STT model {} is not available from the provider; it may have been renamed or deprecated",
+        model
+    );
+    if let Some(suggestion) = suggestion {
+        message.push_str(&format!(". Set STT_MODEL to a supported model such as {}", suggestion));
+    }
+    message
+}
+
+/// Classify a provider error body for a request to `model`. An unknown or
+/// retired model becomes `ModelUnavailable`, anything else `ApiError`.
+pub fn provider_error(body: &str, model: &str) -> SttError {
+    let Ok(response) = serde_json::from_str::<ApiErrorResponse>(body) else {
+        return SttError::ApiError(body.to_string());
+    };
+    let message = response.error.message;
+    let lowercase = message.to_lowercase();
+
+    let code = response.error.code.as_ref().and_then(|c| c.as_str());
+    let unavailable = code.is_some_and(|c| MODEL_UNAVAILABLE_CODES.contains(&c))
+        || (lowercase.contains(&model.to_lowercase())
+            && MODEL_UNAVAILABLE_MARKERS.iter().any(|m| lowercase.contains(m)));
+    if !unavailable {
+        return SttError::ApiError(message);
+    }
+
+    SttError::ModelUnavailable {
+        model: model.to_string(),
+        suggestion: KNOWN_STT_MODELS
+            .iter()
+            .find(|&&known| known != model)
+            .map(|known| known.to_string()),
+    }
+}
+
+static REUPLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Audio bytes sent again because an earlier upload attempt failed,
+/// since process start
+pub fn reuploaded_bytes_total() -> u64 {
+    REUPLOADED_BYTES.load(Ordering::Relaxed)
+}
+
+/// Upload formats accepted unless `AUDIO_FORMATS` says otherwise: the
+/// extension and the MIME type sent to the provider for it
+pub const DEFAULT_AUDIO_FORMATS: &[(&str, &str)] = &[
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("webm", "audio/webm"),
+    ("ogg", "audio/ogg"),
+    ("m4a", "audio/m4a"),
+    ("flac", "audio/flac"),
+    ("mp4", "audio/mp4"),
+];
+
+fn find_mime_type<'a>(formats: &'a [(String, String)], file_name: &str) -> Option<&'a str> {
+    let extension = file_name
+        .rsplit('.')
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    formats
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| mime.as_str())
+}
+
+/// Largest file the transcription API accepts
+pub const MAX_AUDIO_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Progress of a remote audio download. `total` is `None` when the remote
+/// doesn't send a `Content-Length`.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// Redirects `download_audio` follows before giving up
+const MAX_DOWNLOAD_REDIRECTS: usize = 5;
+
+/// Whether `ip` is reachable on the public internet. Loopback, private,
+/// link-local (including the 169.254.169.254 cloud metadata endpoint),
+/// shared, reserved and multicast ranges are not.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let [a, b, ..] = ip.segments();
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || (a == 0x2001 && b == 0xdb8))
+            }
+        },
+    }
+}
+
+/// Client for one download hop. Unless `allow_private`, the host must
+/// resolve to public addresses only, and the client is pinned to them so a
+/// second DNS answer can't swap in a private one before connecting.
+async fn download_client(url: &Url, allow_private: bool) -> Result<Client, SttError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(SttError::DownloadFailed(format!("unsupported URL scheme {}", url.scheme())));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| SttError::DownloadFailed("URL has no host".to_string()))?;
+
+    let builder = Client::builder().redirect(Policy::none());
+    if allow_private {
+        return Ok(builder.build()?);
+    }
+
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| SttError::DownloadFailed(format!("could not resolve {}: {}", host, e)))?
+            .collect(),
+    };
+
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(SttError::DownloadFailed(format!(
+            "{} resolves to a non-public address ({})",
+            host,
+            addr.ip()
+        )));
+    }
+    if addrs.is_empty() {
+        return Err(SttError::DownloadFailed(format!("could not resolve {}", host)));
+    }
+
+    Ok(builder.resolve_to_addrs(host, &addrs).build()?)
 }
 
+/// Fetch remote audio, reporting progress roughly every 64KB.
+/// Fails early if the declared or actual size exceeds `MAX_AUDIO_BYTES`.
+/// Redirects are followed by hand so each hop goes through
+/// `download_client`'s address check.
+pub async fn download_audio<F>(
+    url: &str,
+    allow_private: bool,
+    mut on_progress: F,
+) -> Result<Vec<u8>, SttError>
+where
+    F: FnMut(DownloadProgress),
+{
+    const REPORT_EVERY: u64 = 64 * 1024;
+
+    let mut url =
+        Url::parse(url).map_err(|e| SttError::DownloadFailed(format!("invalid URL: {}", e)))?;
+    let mut redirects = 0;
+    let mut response = loop {
+        let client = download_client(&url, allow_private).await?;
+        let response = client.get(url.clone()).send().await?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        redirects += 1;
+        if redirects > MAX_DOWNLOAD_REDIRECTS {
+            return Err(SttError::DownloadFailed("too many redirects".to_string()));
+        }
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or_else(|| {
+                SttError::DownloadFailed(format!(
+                    "remote returned {} without a location",
+                    response.status()
+                ))
+            })?;
+        url = url
+            .join(location)
+            .map_err(|e| SttError::DownloadFailed(format!("invalid redirect: {}", e)))?;
+    };
+
+    if !response.status().is_success() {
+        return Err(SttError::DownloadFailed(format!(
+            "remote returned {}",
+            response.status()
+        )));
+    }
+
+    let total = response.content_length();
+    if total.is_some_and(|t| t > MAX_AUDIO_BYTES) {
+        return Err(SttError::DownloadFailed(format!(
+            "file is larger than {} bytes",
+            MAX_AUDIO_BYTES
+        )));
+    }
+
+    let mut data = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut last_reported = 0;
+    on_progress(DownloadProgress { downloaded: 0, total });
+
+    while let Some(chunk) = response.chunk().await? {
+        data.extend_from_slice(&chunk);
+        let downloaded = data.len() as u64;
+
+        if downloaded > MAX_AUDIO_BYTES {
+            return Err(SttError::DownloadFailed(format!(
+                "file is larger than {} bytes",
+                MAX_AUDIO_BYTES
+            )));
+        }
+
+        if downloaded - last_reported >= REPORT_EVERY {
+            last_reported = downloaded;
+            on_progress(DownloadProgress { downloaded, total });
+        }
+    }
+
+    on_progress(DownloadProgress {
+        downloaded: data.len() as u64,
+        total,
+    });
+
+    Ok(data)
+}
+
+/// `response_format` values `STT_RESPONSE_FORMAT` accepts
+pub const STT_RESPONSE_FORMATS: &[&str] = &["verbose_json", "json", "text"];
+
+/// What `transcribe-ai` does with a transcription below
+/// `STT_MIN_ANSWER_CONFIDENCE`: answer with a warning that it may be
+/// misheard, or not answer at all
+pub const LOW_CONFIDENCE_ACTIONS: &[&str] = &["caveat", "skip"];
+
 #[derive(Debug, Deserialize)]
 struct WhisperResponse {
     text: String,
@@ -25,6 +307,36 @@ struct WhisperResponse {
     language: Option<String>,
     #[serde(default)]
     duration: Option<f32>,
+    #[serde(default)]
+    segments: Vec<SttSegment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SttSegment {
+    #[serde(default)]
+    pub id: u32,
+    #[serde(default)]
+    pub start: f32,
+    #[serde(default)]
+    pub end: f32,
+    pub text: String,
+    #[serde(default)]
+    pub avg_logprob: Option<f32>,
+    #[serde(default)]
+    pub no_speech_prob: Option<f32>,
+}
+
+impl SttSegment {
+    /// Probability that the segment contains actual speech
+    pub fn confidence(&self) -> f32 {
+        1.0 - self.no_speech_prob.unwrap_or(0.0)
+    }
+
+    /// How sure Whisper is of the words: `confidence` scaled by the mean
+    /// token probability, `exp(avg_logprob)`
+    pub fn transcript_confidence(&self) -> f32 {
+        self.confidence() * self.avg_logprob.map_or(1.0, f32::exp)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,6 +347,52 @@ struct ApiErrorResponse {
 #[derive(Debug, Deserialize)]
 struct ApiErrorDetail {
     message: String,
+    /// A string like `model_not_found` for Groq, but left untyped
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+/// Read a transcription body in the requested `response_format`. `text`
+/// bodies are the bare transcript, without duration, language or segments.
+/// `provider` and `model` only name the source in errors.
+pub fn parse_transcription(
+    body: &str,
+    response_format: &str,
+    provider: &str,
+    model: &str,
+) -> Result<SttResponse, SttError> {
+    if response_format == "text" {
+        return Ok(SttResponse {
+            text: body.trim().to_string(),
+            language: None,
+            duration: None,
+            model: model.to_string(),
+            segments: Vec::new(),
+        });
+    }
+
+    let whisper_response: WhisperResponse = serde_json::from_str(body).map_err(|e| {
+        SttError::InvalidResponse(format!(
+            "{} returned an unexpected body for model {} ({}): {}",
+            provider,
+            model,
+            e,
+            body_snippet(body)
+        ))
+    })?;
+
+    // Stored as a null duration, and the duration limit can't be checked
+    if response_format == "verbose_json" && whisper_response.duration.is_none() {
+        tracing::debug!("verbose_json transcription from {} has no duration", model);
+    }
+
+    Ok(SttResponse {
+        text: whisper_response.text,
+        language: whisper_response.language,
+        duration: whisper_response.duration,
+        model: model.to_string(),
+        segments: whisper_response.segments,
+    })
 }
 
 pub struct SttResponse {
@@ -42,106 +400,211 @@ pub struct SttResponse {
     pub language: Option<String>,
     pub duration: Option<f32>,
     pub model: String,
+    pub segments: Vec<SttSegment>,
+}
+
+impl SttResponse {
+    /// Mean `transcript_confidence` of the segments. `None` without
+    /// segments, e.g. for `json` and `text` response formats.
+    pub fn average_confidence(&self) -> Option<f32> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let total = self.segments.iter().map(SttSegment::transcript_confidence).sum::<f32>();
+        Some(total / self.segments.len() as f32)
+    }
+
+    /// Drop segments whose speech confidence is below `min_confidence` and
+    /// rebuild `text` from the remaining ones. Returns the number dropped.
+    pub fn filter_low_confidence(&mut self, min_confidence: f32) -> usize {
+        let before = self.segments.len();
+        self.segments.retain(|s| s.confidence() >= min_confidence);
+        let filtered = before - self.segments.len();
+
+        if filtered > 0 {
+            self.text = self
+                .segments
+                .iter()
+                .map(|s| s.text.trim())
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+
+        filtered
+    }
 }
 
 #[derive(Clone)]
 pub struct SttClient {
     client: Client,
+    /// Name of the configured provider, for error messages
+    provider: &'static str,
     base_url: String,
     api_key: String,
     model: String,
+    large_file_bytes: u64,
+    max_attempts: u32,
+    max_attempts_large: u32,
+    max_duration_secs: Option<u32>,
+    audio_formats: Vec<(String, String)>,
+    response_format: String,
+    backend: Option<Arc<dyn ClientBackend>>,
 }
 
 impl SttClient {
-    pub fn new() -> Result<Self, SttError> {
-        let api_key = env::var("GROQ_API_KEY").map_err(|_| SttError::MissingApiKey)?;
-
-        if api_key.is_empty() {
-            return Err(SttError::MissingApiKey);
-        }
-
-        let base_url = env::var("GROQ_BASE_URL")
-            .unwrap_or_else(|_| "https://api.groq.com/openai/v1".to_string());
-        let model = env::var("STT_MODEL")
-            .unwrap_or_else(|_| "whisper-large-v3-turbo".to_string());
+    pub fn new(config: &Config) -> Result<Self, SttError> {
+        let provider_config = config.provider("groq");
+        let (provider, base_url, api_key) = match (provider_config, &config.client_backend) {
+            (Some(groq), _) => (groq.name, groq.base_url.clone(), groq.api_key.clone()),
+            (None, Some(_)) => ("test backend", String::new(), String::new()),
+            (None, None) => return Err(SttError::MissingApiKey),
+        };
 
         Ok(Self {
-            client: Client::new(),
+            client: super::http_client(),
+            provider,
             base_url,
             api_key,
-            model,
+            model: config.stt_model.clone(),
+            large_file_bytes: config.stt_large_file_bytes,
+            max_attempts: config.stt_max_attempts.max(1),
+            max_attempts_large: config.stt_max_attempts_large.max(1),
+            max_duration_secs: config.max_audio_duration_secs,
+            audio_formats: config.audio_formats.clone(),
+            response_format: config.stt_response_format.clone(),
+            backend: config.client_backend.clone(),
         })
     }
 
+    /// Upload attempts allowed for a file of `len` bytes. Re-sending a large
+    /// file is expensive, so those get fewer tries.
+    pub fn max_attempts_for(&self, len: usize) -> u32 {
+        if len as u64 >= self.large_file_bytes {
+            self.max_attempts_large
+        } else {
+            self.max_attempts
+        }
+    }
+
     pub async fn transcribe(
         &self,
         audio_data: Vec<u8>,
         file_name: &str,
         language: Option<&str>,
     ) -> Result<SttResponse, SttError> {
-        let mime_type = Self::get_mime_type(file_name);
+        if let Some(duration) = audio::estimate_duration(&audio_data, file_name) {
+            self.check_duration(duration)?;
+        }
 
-        let file_part = Part::bytes(audio_data)
-            .file_name(file_name.to_string())
-            .mime_str(&mime_type)
-            .map_err(|e| SttError::InvalidResponse(e.to_string()))?;
+        let response = self.send(audio_data, file_name, language).await?;
 
-        let mut form = Form::new()
-            .part("file", file_part)
-            .text("model", self.model.clone())
-            .text("response_format", "verbose_json");
+        // Formats we can't size up front are only caught after the fact
+        if let Err(e) = response.duration.map_or(Ok(()), |d| self.check_duration(d)) {
+            tracing::warn!("Transcribed audio over the duration limit ({}), result discarded", e);
+            return Err(e);
+        }
+
+        Ok(response)
+    }
+
+    /// Enforce `MAX_AUDIO_DURATION_SECS`, if configured
+    fn check_duration(&self, duration: f32) -> Result<(), SttError> {
+        match self.max_duration_secs {
+            Some(limit) if duration > limit as f32 => Err(SttError::AudioTooLong { duration, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    async fn send(
+        &self,
+        audio_data: Vec<u8>,
+        file_name: &str,
+        language: Option<&str>,
+    ) -> Result<SttResponse, SttError> {
+        if let Some(backend) = &self.backend {
+            return backend
+                .transcribe(audio_data, file_name.to_string(), language.map(str::to_string))
+                .await;
+        }
+
+        let mime_type = find_mime_type(&self.audio_formats, file_name)
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let attempts = AtomicU32::new(0);
+
+        let max_attempts = self.max_attempts_for(audio_data.len());
+
+        let response = super::send_with_attempts::<_, SttError>(max_attempts, || {
+            if attempts.fetch_add(1, Ordering::Relaxed) > 0 {
+                REUPLOADED_BYTES.fetch_add(audio_data.len() as u64, Ordering::Relaxed);
+            }
+
+            let file_part = Part::bytes(audio_data.clone())
+                .file_name(file_name.to_string())
+                .mime_str(&mime_type)
+                .map_err(|e| SttError::InvalidResponse(e.to_string()))?;
+
+            let mut form = Form::new()
+                .part("file", file_part)
+                .text("model", self.model.clone())
+                .text("response_format", self.response_format.clone());
+
+            if let Some(lang) = language {
+                form = form.text("language", lang.to_string());
+            }
+
+            Ok(self
+                .client
+                .post(format!("{}/audio/transcriptions", self.base_url))
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .multipart(form))
+        })
+        .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(provider_error(&error_text, &self.model));
+        }
+
+        let body = response.text().await?;
+        parse_transcription(&body, &self.response_format, self.provider, &self.model)
+    }
 
-        if let Some(lang) = language {
-            form = form.text("language", lang.to_string());
+    /// Authenticated no-op request (lists models) to verify the key and
+    /// establish a pooled connection
+    pub async fn check_connectivity(&self) -> Result<(), SttError> {
+        if self.backend.is_some() {
+            return Ok(());
         }
 
         let response = self
             .client
-            .post(format!("{}/audio/transcriptions", self.base_url))
+            .get(format!("{}/models", self.base_url))
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .multipart(form)
             .send()
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
             if let Ok(error_response) = serde_json::from_str::<ApiErrorResponse>(&error_text) {
                 return Err(SttError::ApiError(error_response.error.message));
             }
-            return Err(SttError::ApiError(error_text));
+            return Err(SttError::ApiError(format!("{}: {}", status, error_text)));
         }
 
-        let whisper_response: WhisperResponse = response.json().await?;
-
-        Ok(SttResponse {
-            text: whisper_response.text,
-            language: whisper_response.language,
-            duration: whisper_response.duration,
-            model: self.model.clone(),
-        })
+        Ok(())
     }
 
-    fn get_mime_type(file_name: &str) -> String {
-        let extension = file_name
-            .rsplit('.')
-            .next()
-            .unwrap_or("")
-            .to_lowercase();
-
-        match extension.as_str() {
-            "mp3" => "audio/mpeg",
-            "wav" => "audio/wav",
-            "webm" => "audio/webm",
-            "ogg" => "audio/ogg",
-            "m4a" => "audio/m4a",
-            "flac" => "audio/flac",
-            "mp4" => "audio/mp4",
-            _ => "application/octet-stream",
-        }
-        .to_string()
+    /// MIME type uploaded for `file_name`'s extension, `None` if the format
+    /// isn't accepted
+    pub fn mime_type<'a>(config: &'a Config, file_name: &str) -> Option<&'a str> {
+        find_mime_type(&config.audio_formats, file_name)
     }
 
-    pub fn supported_formats() -> Vec<&'static str> {
-        vec!["mp3", "wav", "webm", "ogg", "m4a", "flac", "mp4"]
+    /// Accepted extensions, from `AUDIO_FORMATS`
+    pub fn supported_formats(config: &Config) -> Vec<&str> {
+        config.audio_formats.iter().map(|(ext, _)| ext.as_str()).collect()
     }
 }
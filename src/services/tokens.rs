@@ -0,0 +1,43 @@
+//! Token counts for budgeting. With the `tokenizer` feature, OpenAI-family
+//! models are counted with their own BPE encoding; other models, and every
+//! model without the feature, fall back to a characters/4 estimate.
+
+/// Rough count: about four characters per token in English text. Code and
+/// non-Latin scripts usually need more.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Tokens `text` takes up for `model`, exact where a tokenizer is known
+pub fn count_tokens(text: &str, model: &str) -> usize {
+    exact_count(text, model).unwrap_or_else(|| estimate_tokens(text))
+}
+
+/// Whether `count_tokens` is exact for `model` rather than an estimate
+pub fn has_tokenizer(model: &str) -> bool {
+    exact_count("", model).is_some()
+}
+
+#[cfg(feature = "tokenizer")]
+fn exact_count(text: &str, model: &str) -> Option<usize> {
+    use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+
+    // OpenRouter names models `provider/model`
+    let name = model.rsplit('/').next().unwrap_or(model);
+
+    // The singletons build each encoding once per process
+    let bpe = match get_tokenizer(name)? {
+        Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+        Tokenizer::P50kBase => tiktoken_rs::p50k_base_singleton(),
+        Tokenizer::P50kEdit => tiktoken_rs::p50k_edit_singleton(),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => tiktoken_rs::r50k_base_singleton(),
+    };
+
+    Some(bpe.encode_with_special_tokens(text).len())
+}
+
+#[cfg(not(feature = "tokenizer"))]
+fn exact_count(_text: &str, _model: &str) -> Option<usize> {
+    None
+}
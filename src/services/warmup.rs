@@ -0,0 +1,45 @@
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::services::llm::LlmClient;
+use crate::services::stt::SttClient;
+
+/// Prime the shared connection pool by making a trivial request to every
+/// configured provider. Failures are logged, not fatal, so a bad key shows
+/// up at boot instead of on the first user request.
+pub async fn run(config: &Config) {
+    let llm_clients = [
+        ("groq", LlmClient::new_groq(config)),
+        ("openrouter", LlmClient::new(config)),
+    ];
+
+    for (provider, client) in llm_clients {
+        let Ok(client) = client else {
+            tracing::info!("Warm-up: {} not configured, skipping", provider);
+            continue;
+        };
+
+        let start = Instant::now();
+        let model = client.default_model().to_string();
+        match client.complete("ping", &model, None, Some(1), None).await {
+            Ok(_) => tracing::info!(
+                "Warm-up: {} ({}) ok in {}ms",
+                provider,
+                model,
+                start.elapsed().as_millis()
+            ),
+            Err(e) => tracing::error!("Warm-up: {} ({}) failed: {}", provider, model, e),
+        }
+    }
+
+    match SttClient::new(config) {
+        Ok(stt) => {
+            let start = Instant::now();
+            match stt.check_connectivity().await {
+                Ok(()) => tracing::info!("Warm-up: stt ok in {}ms", start.elapsed().as_millis()),
+                Err(e) => tracing::error!("Warm-up: stt failed: {}", e),
+            }
+        }
+        Err(_) => tracing::info!("Warm-up: stt not configured, skipping"),
+    }
+}
@@ -0,0 +1,36 @@
+use axum::response::sse::{Event, KeepAlive, KeepAliveStream, Sse};
+use futures::{Stream, StreamExt};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Gap after which an idle stream emits a `: keepalive` comment. Proxies and
+/// load balancers commonly drop connections idle for 30-60s.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Wrap an event stream in an SSE response that emits keepalive comments
+/// between events. Every SSE endpoint should go through this.
+pub fn sse_response<S, E>(stream: S) -> Sse<KeepAliveStream<S>>
+where
+    S: Stream<Item = Result<Event, E>> + Send + 'static,
+    E: Into<axum::BoxError>,
+{
+    sse_response_with_interval(stream, KEEPALIVE_INTERVAL)
+}
+
+pub fn sse_response_with_interval<S, E>(stream: S, interval: Duration) -> Sse<KeepAliveStream<S>>
+where
+    S: Stream<Item = Result<Event, E>> + Send + 'static,
+    E: Into<axum::BoxError>,
+{
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(interval).text("keepalive"))
+}
+
+/// `stream` that cancels `token` once it's dropped, which is when the
+/// client disconnects, so the work feeding it can stop early
+pub fn cancel_on_drop<S: Stream>(stream: S, token: CancellationToken) -> impl Stream<Item = S::Item> {
+    let guard = token.drop_guard();
+    stream.map(move |item| {
+        let _guard = &guard;
+        item
+    })
+}
@@ -0,0 +1,80 @@
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::{IntoResponseParts, ResponseParts};
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+pub const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// Per-phase durations of a request (`db`, `llm`, `stt`, ...), sent back as a
+/// `Server-Timing` header so clients can tell provider latency from ours.
+/// Returned from a handler alongside the body, e.g. `(timing, Json(body))`.
+#[derive(Debug, Default)]
+pub struct ServerTiming {
+    phases: Vec<(&'static str, Duration)>,
+    llm_call: Option<LlmCall>,
+}
+
+/// The model call behind a response, put in the response extensions for
+/// `request_log` to report on slow LLM routes
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmCall {
+    pub model: String,
+    /// Total tokens, when the provider reported usage
+    pub tokens: Option<u32>,
+}
+
+impl ServerTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `duration` to the phase, keeping first-seen order
+    pub fn record(&mut self, name: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|(phase, _)| *phase == name) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((name, duration)),
+        }
+    }
+
+    /// Await `future`, recording how long it took under `name`
+    pub async fn measure<F: Future>(&mut self, name: &'static str, future: F) -> F::Output {
+        let started = Instant::now();
+        let output = future.await;
+        self.record(name, started.elapsed());
+        output
+    }
+
+    /// Note which model served the request, for the slow request log
+    pub fn llm_call(&mut self, model: &str, tokens: Option<u32>) {
+        self.llm_call = Some(LlmCall {
+            model: model.to_string(),
+            tokens,
+        });
+    }
+
+    /// `llm;dur=512, db;dur=8`, in whole milliseconds
+    pub fn header_value(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(name, duration)| format!("{};dur={}", name, duration.as_millis()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl IntoResponseParts for ServerTiming {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if !self.phases.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&self.header_value()) {
+                res.headers_mut().insert(SERVER_TIMING, value);
+            }
+        }
+        if let Some(call) = self.llm_call {
+            res.extensions_mut().insert(call);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,78 @@
+//! Retries with capped exponential backoff and full jitter, shared by every
+//! caller that retries a transient failure so they back off the same way.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Ceiling of the wait before retry `retry` (0 for the first retry):
+/// `base_delay * 2^retry`, capped at `max_delay`
+pub fn backoff_ceiling(retry: u32, base_delay: Duration, max_delay: Duration) -> Duration {
+    base_delay
+        .checked_mul(2u32.saturating_pow(retry))
+        .unwrap_or(max_delay)
+        .min(max_delay)
+}
+
+/// "Full jitter": a wait picked uniformly up to the ceiling, so clients that
+/// failed together don't retry together. `random` is in `[0, 1)`.
+pub fn jittered_delay(retry: u32, base_delay: Duration, max_delay: Duration, random: f64) -> Duration {
+    backoff_ceiling(retry, base_delay, max_delay).mul_f64(random.clamp(0.0, 1.0))
+}
+
+/// Uniform in `[0, 1)`. Each `RandomState` is freshly keyed, which is random
+/// enough to spread retries without pulling in an RNG.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().hash_one(0u8);
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Run `op` up to `attempts` times (at least once), waiting a jittered
+/// backoff between attempts while `is_retryable` accepts the error. The last
+/// error is returned once attempts run out or an error isn't retryable.
+pub async fn retry_with_backoff<F, Fut, T, E>(
+    attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    is_retryable: impl Fn(&E) -> bool,
+    op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    retry_with_sleep(attempts, is_retryable, op, |retry| {
+        tokio::time::sleep(jittered_delay(retry, base_delay, max_delay, random_fraction()))
+    })
+    .await
+}
+
+/// `retry_with_backoff` with the wait supplied by the caller: `sleep` gets
+/// the retry number and waits however it likes. Lets tests run on a fake
+/// clock and check the delays without real sleeps or randomness.
+pub async fn retry_with_sleep<F, Fut, T, E, S, SFut>(
+    attempts: u32,
+    is_retryable: impl Fn(&E) -> bool,
+    mut op: F,
+    mut sleep: S,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    S: FnMut(u32) -> SFut,
+    SFut: Future<Output = ()>,
+{
+    let attempts = attempts.max(1);
+    let mut retry = 0;
+
+    loop {
+        match op().await {
+            Err(e) if retry + 1 < attempts && is_retryable(&e) => {
+                sleep(retry).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use cleuly::router::build_router;
+use cleuly::config::Config;
+use cleuly::{config, AppState};
+use serde_json::json;
+
+async fn setup_test_server() -> TestServer {
+    dotenvy::dotenv().ok();
+
+    let settings = Config::from_env().expect("Invalid configuration");
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+
+    let state = AppState::new(db, redis, settings.clone());
+
+    let app = build_router(state, &settings);
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn test_purge_requires_admin_key() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/admin/purge")
+        .json(&json!({
+            "collection": "sessions",
+            "confirm": "YES"
+        }))
+        .expect_failure()
+        .await;
+
+    let status = response.status_code();
+    assert!(status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_purge_rejects_wrong_admin_key() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/admin/purge")
+        .add_header("x-api-key", "definitely-not-the-admin-key")
+        .json(&json!({
+            "collection": "sessions",
+            "confirm": "YES"
+        }))
+        .await;
+
+    response.assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_reembed_requires_admin_key() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/admin/reembed")
+        .add_query_param("collection", "messages")
+        .expect_failure()
+        .await;
+
+    let status = response.status_code();
+    assert!(status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN);
+}
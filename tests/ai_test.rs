@@ -1,20 +1,33 @@
 use axum::http::StatusCode;
-use axum::Router;
 use axum_test::TestServer;
-use cleuly::{config, modules, AppState};
+use cleuly::router::build_router;
+use cleuly::error::AppError;
+use cleuly::modules::ai::controller::{analyze_cache_key, configured_models, truncate_for_display};
+use cleuly::modules::ai::crud::AiCrud;
+use cleuly::modules::ai::model::AiCompletion;
+use cleuly::modules::ai::schema::ChatCompletionRequest;
+use cleuly::config::{Config, ProviderConfig};
+use cleuly::services::llm::{language_instruction, language_name};
+use cleuly::{config, AppState};
 use serde_json::json;
+use validator::Validate;
 
 async fn setup_test_server() -> TestServer {
+    setup_test_server_with(|_| {}).await
+}
+
+async fn setup_test_server_with(configure: impl FnOnce(&mut Config)) -> TestServer {
     dotenvy::dotenv().ok();
 
-    let db = config::database::connect().await;
-    let redis = config::redis::connect().await;
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    configure(&mut settings);
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
 
-    let state = AppState { db, redis };
+    let state = AppState::new(db, redis, settings.clone());
 
-    let app = Router::new()
-        .merge(modules::ai::routes::routes())
-        .with_state(state);
+    let app = build_router(state, &settings);
 
     TestServer::new(app).unwrap()
 }
@@ -30,14 +43,74 @@ async fn test_list_models() {
     let body: serde_json::Value = response.json();
     assert!(body["models"].is_array());
 
+    // Only models whose provider key is configured
+    let settings = Config::from_env().unwrap();
     let models = body["models"].as_array().unwrap();
-    assert_eq!(models.len(), 5);
+    assert!(models
+        .iter()
+        .all(|m| settings.provider(m["provider"].as_str().unwrap()).is_some()));
+    assert!(models.iter().all(|m| m.get("available").is_none()));
 
     // Check first model has expected fields
     assert!(models[0]["id"].is_string());
     assert!(models[0]["name"].is_string());
     assert!(models[0]["description"].is_string());
     assert!(models[0]["context_length"].is_number());
+    assert!(models[0]["supports_reasoning"].is_boolean());
+}
+
+#[test]
+fn test_configured_models_skip_unconfigured_providers() {
+    let mut config = Config::default();
+    assert!(configured_models(&config).is_empty());
+
+    config.providers.insert(
+        "groq",
+        ProviderConfig {
+            name: "groq",
+            base_url: "http://localhost".to_string(),
+            api_key: "test".to_string(),
+            default_model: "llama-3.1-8b-instant".to_string(),
+        },
+    );
+    let models = configured_models(&config);
+
+    assert_eq!(models.len(), 2);
+    assert!(models.iter().all(|m| m.provider == "groq" && m.context_length > 0));
+}
+
+#[tokio::test]
+async fn test_complete_invalid_reasoning_effort_fails() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/complete")
+        .json(&json!({
+            "prompt": "Think hard",
+            "reasoning_effort": "extreme"
+        }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = response.json();
+    assert!(body["fields"]["reasoning_effort"].is_array());
+}
+
+#[tokio::test]
+async fn test_get_quota() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/ai/quota")
+        .add_header("x-api-key", "test-quota-key")
+        .await;
+
+    response.assert_status(StatusCode::OK);
+
+    let body: serde_json::Value = response.json();
+    assert!(body["used"].is_number());
+    assert!(body["resets_at"].is_string());
 }
 
 #[tokio::test]
@@ -68,6 +141,21 @@ async fn test_suggest_empty_context_fails() {
     response.assert_status(StatusCode::BAD_REQUEST);
 }
 
+#[tokio::test]
+async fn test_suggest_unknown_session_not_found() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/suggest")
+        .json(&json!({
+            "context": "What is a hash map?",
+            "session_id": "507f1f77bcf86cd799439011"
+        }))
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_analyze_empty_text_fails() {
     let server = setup_test_server().await;
@@ -179,3 +267,368 @@ async fn test_complete_with_system_prompt() {
     let body: serde_json::Value = response.json();
     assert!(body["content"].is_string());
 }
+
+#[test]
+fn test_truncate_for_display_cuts_at_line_boundary() {
+    let content = "def solve():\n    return 42\n# trailing explanation";
+
+    let (display, truncated) = truncate_for_display(content, Some(30));
+    assert!(truncated);
+    assert_eq!(display, "def solve():\n    return 42");
+
+    let (display, truncated) = truncate_for_display(content, Some(500));
+    assert!(!truncated);
+    assert_eq!(display, content);
+
+    let (display, truncated) = truncate_for_display(content, None);
+    assert!(!truncated);
+    assert_eq!(display, content);
+}
+
+#[test]
+fn test_analyze_cache_key_separates_owner_type_and_model() {
+    let key = analyze_cache_key("owner", Some("sentiment"), "llama", "I love it");
+    assert!(key.starts_with("analyze:owner:sentiment:llama:"));
+    assert_eq!(key, analyze_cache_key("owner", Some("sentiment"), "llama", "I love it"));
+
+    assert_ne!(key, analyze_cache_key("other", Some("sentiment"), "llama", "I love it"));
+    assert_ne!(key, analyze_cache_key("owner", Some("intent"), "llama", "I love it"));
+    assert_ne!(key, analyze_cache_key("owner", Some("sentiment"), "gpt", "I love it"));
+    assert_ne!(key, analyze_cache_key("owner", Some("sentiment"), "llama", "I hate it"));
+    assert!(analyze_cache_key("owner", None, "llama", "x").starts_with("analyze:owner:general:llama:"));
+    assert_eq!(
+        analyze_cache_key("owner", Some(" "), "llama", "x"),
+        analyze_cache_key("owner", None, "llama", "x")
+    );
+}
+
+#[tokio::test]
+async fn test_get_completion_not_found() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/api/ai/completions/507f1f77bcf86cd799439011").await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_chat_request_must_end_with_user_turn() {
+    let request: ChatCompletionRequest = serde_json::from_value(json!({
+        "messages": [
+            { "role": "user", "content": "Hi" },
+            { "role": "assistant", "content": "Hello!" }
+        ]
+    }))
+    .unwrap();
+
+    let error = AppError::from(request.validate().unwrap_err());
+    assert_eq!(error.body.code, "validation_failed");
+    assert!(error.body.fields.unwrap().contains_key("__all__"));
+
+    let request: ChatCompletionRequest = serde_json::from_value(json!({
+        "messages": [
+            { "role": "narrator", "content": "Once upon a time" },
+            { "role": "user", "content": "Hi" }
+        ]
+    }))
+    .unwrap();
+    let error = AppError::from(request.validate().unwrap_err());
+    assert!(error.body.fields.unwrap().contains_key("messages[0].role"));
+
+    let request: ChatCompletionRequest = serde_json::from_value(json!({
+        "messages": [
+            { "role": "system", "content": "Be brief" },
+            { "role": "user", "content": "Hi" }
+        ]
+    }))
+    .unwrap();
+    assert!(request.validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_chat_empty_messages_fails() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/chat")
+        .json(&json!({
+            "messages": []
+        }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_language_instruction_skips_english() {
+    assert_eq!(language_name("es"), Some("Spanish"));
+    assert_eq!(language_name("spanish"), Some("Spanish"));
+    assert_eq!(language_name("xx"), None);
+
+    assert!(language_instruction("en").is_none());
+    assert!(language_instruction("english").is_none());
+    assert!(language_instruction("de").unwrap().contains("respond in German"));
+}
+
+#[tokio::test]
+async fn test_analyze_batch_empty_texts_fails() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/analyze/batch")
+        .json(&json!({ "texts": [] }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_complete_batch_rejects_empty_prompts() {
+    let server = setup_test_server().await;
+
+    for prompts in [json!([]), json!(["What is Rust?", " "])] {
+        server
+            .post("/api/ai/complete/batch")
+            .json(&json!({ "prompts": prompts }))
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_analyze_batch_keeps_order_and_isolates_failures() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/analyze/batch")
+        .json(&json!({
+            "texts": ["I love this product", "", "This is terrible"],
+            "analysis_type": "sentiment"
+        }))
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["input"], "I love this product");
+    assert!(results[0]["content"].is_string());
+    assert!(results[1]["content"].is_null());
+    assert!(results[1]["error"].is_string());
+    assert_eq!(results[2]["input"], "This is terrible");
+}
+
+#[tokio::test]
+async fn test_get_stats() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/ai/stats")
+        .add_query_param("from", "2020-01-01T00:00:00Z")
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert!(body["total_completions"].is_u64());
+    assert!(body["by_request_type"].is_object());
+    assert!(body["by_model"].is_object());
+}
+
+#[tokio::test]
+async fn test_get_stats_only_counts_own_completions() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let crud = AiCrud::new(&db);
+    let other = AiCompletion::new(
+        "Someone else's prompt".to_string(),
+        None,
+        "any-model".to_string(),
+        "Their answer".to_string(),
+        None,
+        "complete".to_string(),
+    )
+    .with_owner("someone-else".to_string());
+    crud.create(other).await.unwrap();
+
+    let key = format!("stats-key-{}", uuid::Uuid::new_v4());
+    let server = setup_test_server_with(|config| config.api_keys = vec![key.clone()]).await;
+
+    let response = server.get("/api/ai/stats").add_header("x-api-key", &key).await;
+
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total_completions"], 0);
+    assert!(body["by_model"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_stats_rejects_inverted_range() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/ai/stats")
+        .add_query_param("from", "2026-02-01T00:00:00Z")
+        .add_query_param("to", "2026-01-01T00:00:00Z")
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_completion_content_not_found() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/ai/completions/507f1f77bcf86cd799439011/content")
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_replay_missing_completion() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/completions/507f1f77bcf86cd799439011/replay")
+        .add_query_param("model", "llama-3.1-8b-instant")
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_estimate_tokens() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/estimate")
+        .json(&json!({ "text": "Reverse a linked list", "model": "llama-3.1-8b-instant" }))
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["tokens"], 6);
+    assert_eq!(body["exact"], false);
+}
+
+#[tokio::test]
+async fn test_benchmark_requires_models() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/benchmark")
+        .json(&json!({ "models": [] }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_benchmark_history() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/ai/benchmark/history")
+        .add_query_param("model", "llama-3.1-8b-instant")
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = response.json();
+    assert!(body["points"].is_array());
+}
+
+#[tokio::test]
+async fn test_rerank_requires_candidates() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/ai/rerank")
+        .json(&json!({ "query": "Which is best?", "candidates": [] }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_delete_completions_older_than() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let crud = AiCrud::new(&config::database::connect(&settings).await);
+
+    let completion = |age_days: i64| {
+        let mut completion = AiCompletion::new(
+            "retention".to_string(),
+            None,
+            "model".to_string(),
+            "reply".to_string(),
+            None,
+            "complete".to_string(),
+        );
+        completion.created_at -= chrono::Duration::days(age_days);
+        completion
+    };
+    let old = crud.create(completion(40)).await.unwrap();
+    let recent = crud.create(completion(1)).await.unwrap();
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(30);
+    assert!(crud.delete_older_than(cutoff).await.unwrap() >= 1);
+    assert!(crud.find_by_id(&old).await.unwrap().is_none());
+    assert!(crud.find_by_id(&recent).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_watch_created_completions() {
+    use futures::StreamExt;
+
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let crud = AiCrud::new(&config::database::connect(&settings).await);
+
+    let changes = match crud.watch_created().await {
+        Ok(changes) => changes,
+        // Standalone servers have no change streams; the endpoint reports 501
+        Err(e) => {
+            assert!(config::database::is_change_stream_unsupported(&e), "{}", e);
+            return;
+        }
+    };
+    let mut changes = Box::pin(changes);
+
+    let prompt = format!("tail {}", uuid::Uuid::new_v4());
+    let id = crud
+        .create(AiCompletion::new(
+            prompt.clone(),
+            None,
+            "model".to_string(),
+            "reply".to_string(),
+            None,
+            "complete".to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let next = tokio::time::timeout(std::time::Duration::from_secs(10), changes.next())
+        .await
+        .expect("no change within 10s")
+        .unwrap()
+        .unwrap();
+    assert_eq!(next.id, Some(id));
+    assert_eq!(next.prompt, prompt);
+}
+
+#[tokio::test]
+async fn test_tail_completions_requires_admin_key() {
+    let server = setup_test_server_with(|config| {
+        config.api_keys = vec!["user-key".to_string()];
+        config.admin_api_key = Some("admin-key".to_string());
+    })
+    .await;
+
+    server
+        .get("/api/ai/completions/tail")
+        .add_header("x-api-key", "user-key")
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
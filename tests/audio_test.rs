@@ -0,0 +1,87 @@
+use cleuly::services::audio::{
+    audio_hints, estimate_duration, matches_signature, wav_format, WavFormat,
+};
+
+/// 16-bit mono PCM WAV with `seconds` of silence
+fn wav(sample_rate: u32, seconds: u32) -> Vec<u8> {
+    wav_with_channels(sample_rate, 1, seconds)
+}
+
+/// 16-bit PCM WAV with `seconds` of silence
+fn wav_with_channels(sample_rate: u32, channels: u16, seconds: u32) -> Vec<u8> {
+    let byte_rate = sample_rate * 2 * channels as u32;
+    let data_size = byte_rate * seconds;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&byte_rate.to_le_bytes());
+    bytes.extend_from_slice(&(2 * channels).to_le_bytes()); // block align
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    bytes.resize(bytes.len() + data_size as usize, 0);
+    bytes
+}
+
+#[test]
+fn test_wav_duration() {
+    let duration = estimate_duration(&wav(16_000, 3), "clip.WAV").unwrap();
+    assert!((duration - 3.0).abs() < 0.01);
+
+    assert!(estimate_duration(b"not a wav file at all", "clip.wav").is_none());
+}
+
+#[test]
+fn test_cbr_mp3_duration() {
+    // MPEG-1 Layer III, 128kbps, 44.1kHz frame header, after a 10-byte empty ID3 tag
+    let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+    bytes.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+    bytes.resize(10 + 16_000 * 5, 0);
+
+    let duration = estimate_duration(&bytes, "talk.mp3").unwrap();
+    assert!((duration - 5.0).abs() < 0.01);
+}
+
+#[test]
+fn test_unknown_formats_are_not_estimated() {
+    assert!(estimate_duration(&wav(16_000, 1), "clip.ogg").is_none());
+}
+
+#[test]
+fn test_wav_audio_hints() {
+    let format = wav_format(&wav_with_channels(44_100, 2, 1)).unwrap();
+    assert_eq!(format, WavFormat { channels: 2, sample_rate: 44_100 });
+
+    let hints = audio_hints(&wav_with_channels(44_100, 2, 1), "clip.wav");
+    assert_eq!(hints.len(), 2);
+    assert!(hints[0].contains("44100Hz"));
+    assert!(hints[1].contains("2 channels"));
+
+    // Already 16kHz mono, or not a WAV at all
+    assert!(audio_hints(&wav(16_000, 1), "clip.wav").is_empty());
+    assert!(audio_hints(&wav_with_channels(44_100, 2, 1), "clip.mp3").is_empty());
+    assert!(audio_hints(b"not a wav file at all", "clip.wav").is_empty());
+}
+
+#[test]
+fn test_matches_signature() {
+    assert!(matches_signature(&wav(16_000, 1), "clip.wav"));
+    assert!(matches_signature(b"ID3\x04\x00", "song.MP3"));
+    assert!(matches_signature(&[0xFF, 0xFB, 0x90, 0x00], "song.mp3"));
+    assert!(matches_signature(b"\x00\x00\x00\x20ftypM4A ", "memo.m4a"));
+    assert!(matches_signature(b"OggS\x00", "voice.opus"));
+
+    assert!(!matches_signature(b"hello world", "clip.wav"));
+    assert!(!matches_signature(b"hello world", "song.mp3"));
+    assert!(!matches_signature(b"", "clip.flac"));
+
+    // Nothing to check an unknown signature against
+    assert!(matches_signature(b"anything", "clip.mpga"));
+}
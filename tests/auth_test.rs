@@ -0,0 +1,123 @@
+use axum::http::StatusCode;
+use axum::{middleware, routing::get, Router};
+use axum_test::TestServer;
+use cleuly::auth::{require_api_key, ApiKey, EndUser};
+use cleuly::config::Config;
+use cleuly::router::build_router;
+use cleuly::{config, AppState};
+use std::sync::Arc;
+
+fn server(config: Config) -> TestServer {
+    let app = Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .route_layer(middleware::from_fn_with_state(Arc::new(config), require_api_key))
+        .merge(Router::new().route("/public", get(|| async { "ok" })));
+
+    TestServer::new(app).unwrap()
+}
+
+fn config_with_keys() -> Config {
+    Config {
+        api_keys: vec!["client-key".to_string()],
+        admin_api_key: Some("admin-key".to_string()),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn test_auth_disabled_without_api_keys() {
+    let server = server(Config::default());
+
+    server.get("/protected").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_protected_route_requires_key() {
+    let server = server(config_with_keys());
+
+    server
+        .get("/protected")
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    server
+        .get("/protected")
+        .add_header("x-api-key", "wrong")
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    server
+        .get("/protected")
+        .add_header("x-api-key", "client-key")
+        .await
+        .assert_status_ok();
+
+    server
+        .get("/protected")
+        .add_header("authorization", "Bearer admin-key")
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_public_router_bypasses_auth() {
+    let server = server(config_with_keys());
+
+    server.get("/public").await.assert_status_ok();
+}
+
+#[test]
+fn test_end_user_identity() {
+    let key = ApiKey(Some("client-key".to_string()));
+    let key_id = key.id();
+
+    assert_eq!(EndUser::new(&key, None).0, Some(key_id.clone()));
+    assert_eq!(EndUser::new(&key, Some(" user-42 ")).0, Some(format!("{}:user-42", key_id)));
+    assert_eq!(EndUser::new(&key, Some("")).0, Some(key_id));
+
+    let anonymous = ApiKey(None);
+    assert_eq!(EndUser::new(&anonymous, None).0, None);
+    assert_eq!(EndUser::new(&anonymous, Some("user-42")).0, Some("user-42".to_string()));
+}
+
+#[test]
+fn test_owner_scope() {
+    let config = config_with_keys();
+    let key = ApiKey(Some("client-key".to_string()));
+
+    assert_eq!(key.owner_scope(&config), Some(key.id()));
+    assert_eq!(ApiKey(Some("admin-key".to_string())).owner_scope(&config), None);
+    assert_eq!(key.owner_scope(&Config::default()), None);
+}
+
+/// Served without a key by the modules' `public_routes()`
+const PUBLIC_PATHS: &[&str] = &[
+    "/health",
+    "/api/version",
+    "/api/capabilities",
+    "/api/ai/models",
+    "/api/stt/formats",
+];
+
+#[tokio::test]
+async fn test_public_routes_answer_without_key() {
+    dotenvy::dotenv().ok();
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    settings.api_keys = vec!["client-key".to_string()];
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+    let server = TestServer::new(build_router(AppState::new(db, redis, settings.clone()), &settings)).unwrap();
+
+    for path in PUBLIC_PATHS {
+        let status = server.get(path).await.status_code();
+        assert!(status.is_success(), "{} answered {}", path, status);
+    }
+    server
+        .get("/api/sessions")
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,40 @@
+use async_zip::base::read::mem::ZipFileReader;
+use cleuly::modules::session::model::{Message, Session};
+use cleuly::services::bundle::{conversation_markdown, write_bundle, CONVERSATION_FILE, TRANSCRIPTIONS_FILE};
+use serde_json::json;
+
+fn interview() -> Session {
+    let mut session = Session::new(Some("Interview".to_string()), Some("interview".to_string()), None);
+    session.messages.push(Message::user("Tell me about yourself.".to_string()));
+    session.messages.push(Message::assistant("I build backend systems.\n".to_string()));
+    session
+}
+
+#[test]
+fn test_conversation_markdown() {
+    let markdown = conversation_markdown(&interview());
+
+    assert!(markdown.starts_with("# Interview\n\n- Type: interview\n"));
+    assert!(markdown.contains("\n## user ("));
+    assert!(markdown.ends_with(")\n\nI build backend systems.\n"));
+}
+
+#[tokio::test]
+async fn test_bundle_entries() {
+    let mut archive = Vec::new();
+    let transcriptions = json!([{ "id": "t1", "text": "Tell me about yourself." }]);
+    write_bundle(&mut archive, &interview(), &transcriptions).await.unwrap();
+
+    let zip = ZipFileReader::new(archive).await.unwrap();
+    let names = zip
+        .file()
+        .entries()
+        .iter()
+        .map(|e| e.filename().as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, [CONVERSATION_FILE, TRANSCRIPTIONS_FILE]);
+
+    let mut json = String::new();
+    zip.reader_with_entry(1).await.unwrap().read_to_string_checked(&mut json).await.unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap(), transcriptions);
+}
@@ -0,0 +1,16 @@
+use cleuly::services::cache::{record_redis_error, redis_errors_total};
+use redis::RedisError;
+
+#[test]
+fn test_redis_errors_are_counted() {
+    let before = redis_errors_total();
+
+    let error = RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::ConnectionRefused,
+        "connection refused",
+    ));
+    record_redis_error("GET", &error);
+    record_redis_error("GET", &error);
+
+    assert_eq!(redis_errors_total(), before + 2);
+}
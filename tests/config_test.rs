@@ -0,0 +1,126 @@
+use cleuly::config::{settings::ConfigError, Config, Persistence};
+use std::env;
+
+// Single test so the environment isn't mutated concurrently
+#[test]
+fn test_config_from_env() {
+    env::set_var("MONGODB_URI", "mongodb://db:27017");
+    env::set_var("REDIS_URI", "redis://cache:6379");
+    env::set_var("PORT", "9090");
+    env::set_var("GROQ_API_KEY", "");
+    env::set_var("OPENROUTER_API_KEY", "or-key");
+    env::set_var("OPENROUTER_MODEL", "google/gemma-3-27b-it:free");
+    env::set_var("MONTHLY_TOKEN_QUOTA", "100000");
+
+    let config = Config::from_env().unwrap();
+    assert_eq!(config.mongodb_uri, "mongodb://db:27017");
+    assert_eq!(config.port, 9090);
+    assert_eq!(config.mongodb_database, "cleuly");
+    // Empty values count as unset
+    assert!(config.provider("groq").is_none());
+    let openrouter = config.provider("openrouter").unwrap();
+    assert_eq!(openrouter.api_key, "or-key");
+    assert_eq!(openrouter.base_url, "https://openrouter.ai/api/v1");
+    assert_eq!(openrouter.default_model, "google/gemma-3-27b-it:free");
+    assert_eq!(config.monthly_token_quota, Some(100000));
+
+    let schema_path = env::temp_dir().join("cleuly_metadata_schema.json");
+    std::fs::write(&schema_path, r#"{"type":"object","required":["team"]}"#).unwrap();
+    env::set_var("SESSION_METADATA_SCHEMA", &schema_path);
+    let config = Config::from_env().unwrap();
+    let schema = config.session_metadata_schema.unwrap();
+    assert!(schema.is_valid(&serde_json::json!({ "team": "sales" })));
+    assert!(!schema.is_valid(&serde_json::json!({})));
+
+    env::set_var("SESSION_METADATA_SCHEMA", "/nonexistent/schema.json");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "SESSION_METADATA_SCHEMA", .. })
+    ));
+    env::remove_var("SESSION_METADATA_SCHEMA");
+
+    let templates_path = env::temp_dir().join("cleuly_prompt_templates.json");
+    std::fs::write(&templates_path, r#"{"recap":"Summarize {notes}"}"#).unwrap();
+    env::set_var("PROMPT_TEMPLATES", &templates_path);
+    let config = Config::from_env().unwrap();
+    assert_eq!(config.prompt_templates["recap"], "Summarize {notes}");
+
+    std::fs::write(&templates_path, r#"{"recap":1}"#).unwrap();
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "PROMPT_TEMPLATES", .. })
+    ));
+    env::remove_var("PROMPT_TEMPLATES");
+
+    env::set_var("AUDIO_FORMATS", "mp3=audio/mpeg, .AAC=audio/aac");
+    let config = Config::from_env().unwrap();
+    assert_eq!(
+        config.audio_formats,
+        [("mp3", "audio/mpeg"), ("aac", "audio/aac")].map(|(e, m)| (e.to_string(), m.to_string()))
+    );
+
+    env::set_var("AUDIO_FORMATS", "mp3");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "AUDIO_FORMATS", .. })
+    ));
+    env::remove_var("AUDIO_FORMATS");
+
+    env::set_var("STT_RESPONSE_FORMAT", "srt");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "STT_RESPONSE_FORMAT", .. })
+    ));
+    env::set_var("STT_RESPONSE_FORMAT", "text");
+    assert_eq!(Config::from_env().unwrap().stt_response_format, "text");
+    env::remove_var("STT_RESPONSE_FORMAT");
+
+    assert_eq!(Config::from_env().unwrap().persistence, Persistence::Full);
+    env::set_var("PERSISTENCE", "none");
+    assert_eq!(Config::from_env().unwrap().persistence, Persistence::None);
+    env::set_var("PERSISTENCE", "off");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "PERSISTENCE", .. })
+    ));
+    env::remove_var("PERSISTENCE");
+
+    env::set_var("TYPE_TEMPERATURES", "meeting=0.8, coding=0");
+    let config = Config::from_env().unwrap();
+    assert_eq!(config.type_temperatures["meeting"], 0.8);
+    assert_eq!(config.type_temperatures["coding"], 0.0);
+    assert_eq!(config.type_temperatures["interview"], 0.2);
+
+    env::set_var("TYPE_TEMPERATURES", "meeting=3");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "TYPE_TEMPERATURES", .. })
+    ));
+    env::remove_var("TYPE_TEMPERATURES");
+
+    env::set_var("PORT", "not-a-port");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "PORT", .. })
+    ));
+
+    env::remove_var("PORT");
+
+    env::set_var("CORS_ALLOWED_ORIGINS", "https://app.example.com,https://bad\norigin");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::Invalid { var: "CORS_ALLOWED_ORIGINS", .. })
+    ));
+    env::set_var("CORS_ALLOWED_ORIGINS", "https://app.example.com, https://admin.example.com");
+    assert_eq!(Config::from_env().unwrap().cors_allowed_origins.len(), 2);
+    env::remove_var("CORS_ALLOWED_ORIGINS");
+
+    env::remove_var("MONGODB_URI");
+    assert!(matches!(Config::from_env(), Err(ConfigError::Missing("MONGODB_URI"))));
+
+    env::remove_var("REDIS_URI");
+    env::remove_var("GROQ_API_KEY");
+    env::remove_var("OPENROUTER_API_KEY");
+    env::remove_var("OPENROUTER_MODEL");
+    env::remove_var("MONTHLY_TOKEN_QUOTA");
+}
@@ -0,0 +1,23 @@
+use bson::Bson;
+use cleuly::config::database::{is_unavailable, number};
+
+#[test]
+fn test_io_errors_mean_unavailable() {
+    let io_error = mongodb::error::Error::from(std::io::Error::new(
+        std::io::ErrorKind::ConnectionRefused,
+        "connection refused",
+    ));
+    assert!(is_unavailable(&io_error));
+
+    let custom = mongodb::error::Error::custom("not a connectivity problem");
+    assert!(!is_unavailable(&custom));
+}
+
+#[test]
+fn test_number_reads_any_numeric_width() {
+    assert_eq!(number(Some(&Bson::Int32(3))), 3);
+    assert_eq!(number(Some(&Bson::Int64(5_000_000_000))), 5_000_000_000);
+    assert_eq!(number(Some(&Bson::Double(2.0))), 2);
+    assert_eq!(number(Some(&Bson::String("3".to_string()))), 0);
+    assert_eq!(number(None), 0);
+}
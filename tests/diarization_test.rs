@@ -0,0 +1,87 @@
+use axum::{routing::post, Json, Router};
+use cleuly::services::diarization::{diarize_by_pauses, fetch_turns, label_segments, SpeakerTurn};
+use cleuly::services::stt::SttSegment;
+use serde_json::json;
+
+fn segment(start: f32, end: f32, text: &str) -> SttSegment {
+    SttSegment {
+        id: 0,
+        start,
+        end,
+        text: format!(" {}", text),
+        avg_logprob: None,
+        no_speech_prob: None,
+    }
+}
+
+fn turn(speaker: &str, start: f32, end: f32) -> SpeakerTurn {
+    SpeakerTurn {
+        speaker: speaker.to_string(),
+        start,
+        end,
+    }
+}
+
+fn speakers(segments: &[cleuly::modules::stt::model::SpeakerSegment]) -> Vec<&str> {
+    segments.iter().map(|s| s.speaker.as_str()).collect()
+}
+
+#[test]
+fn test_long_pauses_rotate_speakers() {
+    let segments = [
+        segment(0.0, 2.0, "Shall we start?"),
+        segment(2.3, 4.0, "Agenda first."),
+        segment(6.0, 8.0, "Sounds good."),
+        segment(10.5, 12.0, "Item one."),
+    ];
+
+    let labeled = diarize_by_pauses(&segments, 1.5, 2);
+
+    assert_eq!(speakers(&labeled), ["Speaker 1", "Speaker 1", "Speaker 2", "Speaker 1"]);
+    assert_eq!(labeled[0].text, "Shall we start?");
+    assert_eq!((labeled[2].start, labeled[2].end), (6.0, 8.0));
+}
+
+#[test]
+fn test_single_speaker_never_changes() {
+    let segments = [segment(0.0, 1.0, "a"), segment(5.0, 6.0, "b")];
+    assert_eq!(speakers(&diarize_by_pauses(&segments, 1.0, 1)), ["Speaker 1", "Speaker 1"]);
+}
+
+#[test]
+fn test_label_segments_by_overlap() {
+    let segments = [
+        segment(0.0, 3.0, "Hello"),
+        segment(3.0, 5.0, "Hi there"),
+        segment(9.0, 10.0, "Bye"),
+    ];
+    let turns = [turn("SPK_07", 0.0, 2.8), turn("SPK_02", 2.8, 6.0)];
+
+    // The last segment overlaps nothing and takes the nearest turn
+    assert_eq!(
+        speakers(&label_segments(&segments, &turns)),
+        ["Speaker 1", "Speaker 2", "Speaker 2"]
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_turns_from_service() {
+    let service = Router::new().route(
+        "/diarize",
+        post(|| async { Json(json!({ "turns": [{ "speaker": "A", "start": 0.0, "end": 1.5 }] })) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, service).await.unwrap() });
+
+    let turns = fetch_turns(&format!("http://{}/diarize", addr), vec![0; 16], "meeting.wav")
+        .await
+        .unwrap();
+
+    assert_eq!(turns.len(), 1);
+    assert_eq!(turns[0].speaker, "A");
+    assert_eq!(turns[0].end, 1.5);
+
+    let missing = fetch_turns(&format!("http://{}/nope", addr), vec![0; 16], "meeting.wav").await;
+    assert!(missing.is_err());
+}
@@ -0,0 +1,24 @@
+use cleuly::services::discovery::TtlCell;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_ttl_cell_serves_fresh_value() {
+    let cell = Arc::new(TtlCell::new(Duration::from_secs(60)));
+
+    assert_eq!(cell.get_or_refresh(|| async { 1 }).await, 1);
+    // Still fresh, so the refresh isn't run
+    assert_eq!(cell.get_or_refresh(|| async { 2 }).await, 1);
+}
+
+#[tokio::test]
+async fn test_ttl_cell_refreshes_stale_value_in_background() {
+    let cell = Arc::new(TtlCell::new(Duration::from_millis(10)));
+    cell.set(1);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // The stale value is served while the new one is fetched
+    assert_eq!(cell.get_or_refresh(|| async { 2 }).await, 1);
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert_eq!(cell.peek(), Some((2, true)));
+}
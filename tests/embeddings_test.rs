@@ -0,0 +1,39 @@
+use cleuly::services::embeddings::{cosine_similarity, mean, top_k, top_k_scored};
+
+#[test]
+fn test_cosine_similarity() {
+    assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+    assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+    assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+
+    // Degenerate inputs score 0 instead of NaN
+    assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+}
+
+#[test]
+fn test_top_k_orders_by_similarity() {
+    let candidates = vec![
+        (0, vec![0.0, 1.0]),
+        (1, vec![1.0, 0.1]),
+        (2, vec![1.0, 0.0]),
+        (3, vec![-1.0, 0.0]),
+    ];
+
+    assert_eq!(top_k(&[1.0, 0.0], &candidates, 2), vec![2, 1]);
+    assert_eq!(top_k(&[1.0, 0.0], &candidates, 10).len(), 4);
+}
+
+#[test]
+fn test_top_k_scored_and_mean() {
+    let candidates = vec![(0, vec![0.0, 1.0]), (1, vec![1.0, 0.0])];
+    let scored = top_k_scored(&[1.0, 0.0], &candidates, 1);
+    assert_eq!(scored.len(), 1);
+    assert_eq!(scored[0].0, 1);
+    assert!((scored[0].1 - 1.0).abs() < 1e-6);
+
+    assert_eq!(mean(&[vec![1.0, 0.0], vec![3.0, 2.0]]), vec![2.0, 1.0]);
+    // Vectors of another length are skipped
+    assert_eq!(mean(&[vec![1.0, 1.0], vec![5.0]]), vec![1.0, 1.0]);
+    assert!(mean(&[]).is_empty());
+}
@@ -0,0 +1,106 @@
+use axum::http::{header, Method, StatusCode};
+use axum::response::IntoResponse;
+use axum::{routing::get, Router};
+use axum_test::TestServer;
+use cleuly::error::{method_not_allowed, AppError};
+use cleuly::services::llm::LlmError;
+use cleuly::services::stt::SttError;
+use validator::Validate;
+
+#[derive(Validate)]
+struct Payload {
+    #[validate(length(min = 1, message = "Text cannot be empty"))]
+    text: String,
+    #[validate(length(max = 3))]
+    tag: String,
+}
+
+#[test]
+fn test_validation_errors_are_grouped_by_field() {
+    let payload = Payload {
+        text: String::new(),
+        tag: "toolong".to_string(),
+    };
+
+    let error = AppError::from(payload.validate().unwrap_err());
+
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.body.code, "validation_failed");
+
+    let fields = error.body.fields.unwrap();
+    assert_eq!(fields["text"], vec!["Text cannot be empty".to_string()]);
+    // Falls back to the validator code when no message is given
+    assert_eq!(fields["tag"], vec!["length".to_string()]);
+}
+
+#[test]
+fn test_provider_parse_failures_map_to_bad_gateway() {
+    let llm = AppError::from(LlmError::InvalidResponse("groq returned garbage".to_string()));
+    assert_eq!(llm.status, StatusCode::BAD_GATEWAY);
+    assert_eq!(llm.body.code, "invalid_provider_response");
+
+    let stt = AppError::from(SttError::InvalidResponse("groq returned garbage".to_string()));
+    assert_eq!(stt.status, StatusCode::BAD_GATEWAY);
+
+    let missing_key = AppError::from(LlmError::MissingApiKey);
+    assert_eq!(missing_key.status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn test_audio_too_long_is_payload_too_large() {
+    let error = AppError::from(SttError::AudioTooLong { duration: 7260.0, limit: 3600 });
+    assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(error.body.code, "audio_too_long");
+    assert_eq!(error.body.message, "Audio is about 7260s long; the limit is 3600s");
+}
+
+#[test]
+fn test_context_length_exceeded_is_payload_too_large() {
+    let error = AppError::from(LlmError::ContextLengthExceeded {
+        limit: Some(8192),
+        requested: Some(9000),
+    });
+    assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(error.body.code, "context_length_exceeded");
+    assert!(error.body.message.contains("9000 tokens requested, limit 8192"));
+}
+
+#[test]
+fn test_unavailable_stt_model_is_service_unavailable() {
+    let error = AppError::from(SttError::ModelUnavailable {
+        model: "whisper-large-v3-turbo".to_string(),
+        suggestion: Some("whisper-large-v3".to_string()),
+    });
+    assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.body.code, "stt_model_unavailable");
+    assert!(error.body.message.contains("whisper-large-v3-turbo"));
+    assert!(error.body.message.contains("such as whisper-large-v3"));
+}
+
+#[test]
+fn test_retry_after_header() {
+    let response = AppError::new(StatusCode::TOO_MANY_REQUESTS, "too_many_transcriptions", "Busy")
+        .with_retry_after(2)
+        .into_response();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "2");
+}
+
+#[tokio::test]
+async fn test_method_not_allowed_and_head() {
+    let app: Router = Router::new()
+        .route("/api/sessions", get(|| async { "listing" }))
+        .method_not_allowed_fallback(method_not_allowed);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.put("/api/sessions").expect_failure().await;
+    response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "method_not_allowed");
+    assert!(response.headers()[header::ALLOW].to_str().unwrap().contains("GET"));
+
+    let response = server.method(Method::HEAD, "/api/sessions").await;
+    response.assert_status_ok();
+    assert!(response.as_bytes().is_empty());
+}
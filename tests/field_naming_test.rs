@@ -0,0 +1,34 @@
+use cleuly::modules::session::schema::SessionSummary;
+use cleuly::pagination::{Page, Paginated};
+
+fn summary() -> serde_json::Value {
+    let summary = SessionSummary {
+        id: "507f1f77bcf86cd799439011".to_string(),
+        title: None,
+        session_type: "general".to_string(),
+        message_count: 2,
+        pinned: false,
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+        updated_at: "2026-01-01T00:00:00Z".to_string(),
+    };
+    serde_json::to_value(Paginated::new(vec![summary], Page::new(None, None), 1)).unwrap()
+}
+
+#[cfg(not(feature = "camelCase"))]
+#[test]
+fn test_responses_are_snake_case_by_default() {
+    let body = summary();
+    assert_eq!(body["total_pages"], 1);
+    assert_eq!(body["data"][0]["message_count"], 2);
+    assert_eq!(body["data"][0]["session_type"], "general");
+}
+
+#[cfg(feature = "camelCase")]
+#[test]
+fn test_responses_are_camel_case_with_feature() {
+    let body = summary();
+    assert_eq!(body["totalPages"], 1);
+    assert_eq!(body["data"][0]["messageCount"], 2);
+    assert_eq!(body["data"][0]["sessionType"], "general");
+    assert!(body["data"][0].get("session_type").is_none());
+}
@@ -0,0 +1,74 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use cleuly::router::build_router;
+use cleuly::config::Config;
+use cleuly::{config, AppState};
+
+async fn setup_test_server() -> TestServer {
+    dotenvy::dotenv().ok();
+
+    let settings = Config::from_env().expect("Invalid configuration");
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+
+    let state = AppState::new(db, redis, settings.clone());
+
+    let app = build_router(state, &settings);
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn test_health() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/health").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["mongodb"], true);
+    assert_eq!(body["redis"], true);
+}
+
+#[tokio::test]
+async fn test_deep_health_requires_admin_key() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/health/deep").expect_failure().await;
+
+    assert_ne!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_public_routes_are_served() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/api/version").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["name"], "cleuly");
+}
+
+#[tokio::test]
+async fn test_capabilities() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/api/capabilities").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["providers"].is_array());
+    assert!(body["audio_formats"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("wav")));
+    assert!(body["max_upload_bytes"].as_u64().unwrap() > 0);
+    assert!(!body["models"].as_array().unwrap().is_empty());
+    assert!(body["languages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|l| l["code"] == "es"));
+}
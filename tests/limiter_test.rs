@@ -0,0 +1,52 @@
+use cleuly::services::limiter::ConcurrencyLimiter;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_slots_are_limited_per_key_and_released_on_drop() {
+    let limiter = Arc::new(ConcurrencyLimiter::new(2));
+
+    let first = limiter.try_acquire("session-a").unwrap();
+    let _second = limiter.try_acquire("session-a").unwrap();
+    assert!(limiter.try_acquire("session-a").is_none());
+
+    // Other sessions are unaffected
+    let _other = limiter.try_acquire("session-b").unwrap();
+
+    drop(first);
+    assert_eq!(limiter.in_flight("session-a"), 1);
+    assert!(limiter.try_acquire("session-a").is_some());
+}
+
+#[tokio::test]
+async fn test_burst_waits_for_slots_instead_of_failing() {
+    let limiter = Arc::new(ConcurrencyLimiter::new(2));
+    let started = Instant::now();
+
+    // Five requests against two slots, each holding its slot for 50ms
+    let burst = (0..5).map(|_| {
+        let limiter = limiter.clone();
+        tokio::spawn(async move {
+            let slot = limiter.acquire("session-a", Duration::from_secs(2)).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            slot.is_some()
+        })
+    });
+    let acquired = futures::future::join_all(burst).await;
+
+    assert!(acquired.into_iter().all(|a| a.unwrap()));
+    // Three rounds of 50ms; none of them rejected outright
+    assert!(started.elapsed() >= Duration::from_millis(150));
+    assert_eq!(limiter.in_flight("session-a"), 0);
+}
+
+#[tokio::test]
+async fn test_queued_acquire_gives_up_after_max_wait() {
+    let limiter = Arc::new(ConcurrencyLimiter::new(1));
+    let _held = limiter.try_acquire("session-a").unwrap();
+    let started = Instant::now();
+
+    assert!(limiter.acquire("session-a", Duration::from_millis(100)).await.is_none());
+    let waited = started.elapsed();
+    assert!(waited >= Duration::from_millis(100) && waited < Duration::from_secs(1));
+}
@@ -0,0 +1,315 @@
+use axum::{routing::post, Json, Router};
+use cleuly::config::{Config, ProviderConfig};
+use cleuly::services::llm::{
+    chunk_lines, clean_title, is_substantive, normalize_type, parse_rerank_scores,
+    parse_stream_line, provider_error, split_think, LlmClient, LlmError, StreamChunk,
+    TITLE_MAX_CHARS,
+};
+use futures::StreamExt;
+
+#[test]
+fn test_parse_stream_line() {
+    let chunks = parse_stream_line(r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#).unwrap();
+    assert!(matches!(&chunks[..], [StreamChunk::Delta(d)] if d == "Hel"));
+
+    let chunks = parse_stream_line(
+        r#"data: {"choices":[],"usage":{"prompt_tokens":5,"completion_tokens":2,"total_tokens":7}}"#,
+    )
+    .unwrap();
+    assert!(matches!(&chunks[..], [StreamChunk::Usage(u)] if u.total_tokens == 7));
+
+    assert!(matches!(&parse_stream_line("data: [DONE]").unwrap()[..], [StreamChunk::Done]));
+    assert!(parse_stream_line(": OPENROUTER PROCESSING").unwrap().is_empty());
+    assert!(parse_stream_line("").unwrap().is_empty());
+    assert!(parse_stream_line("data: {not json").is_err());
+}
+
+/// Serve a canned SSE completion on `/chat/completions`
+async fn mock_provider(body: &'static str) -> Config {
+    mock_provider_with(Router::new().route("/chat/completions", post(move || async move { body }))).await
+}
+
+async fn mock_provider_with(app: Router) -> Config {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let mut config = Config::default();
+    config.providers.insert(
+        "groq",
+        ProviderConfig {
+            name: "groq",
+            base_url: format!("http://{}", addr),
+            api_key: "test-key".to_string(),
+            default_model: "llama-3.1-8b-instant".to_string(),
+        },
+    );
+    config
+}
+
+async fn collect(config: &Config) -> Vec<StreamChunk> {
+    let llm = LlmClient::new_groq(config).unwrap();
+    llm.complete_stream(Vec::new(), "llama-3.1-8b-instant", None, None, None)
+        .await
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn test_complete_stream_includes_usage() {
+    let config = mock_provider(concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n",
+        "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"total_tokens\":5}}\n\n",
+        "data: [DONE]\n\n",
+    ))
+    .await;
+
+    let chunks = collect(&config).await;
+
+    let text: String = chunks
+        .iter()
+        .filter_map(|c| match c {
+            StreamChunk::Delta(d) => Some(d.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(text, "Hello world");
+    assert!(chunks.iter().any(|c| matches!(c, StreamChunk::Usage(u) if u.total_tokens == 5)));
+    assert!(matches!(chunks.last(), Some(StreamChunk::Done)));
+}
+
+#[tokio::test]
+async fn test_complete_stream_without_usage() {
+    // Provider ignores stream_options and never sends [DONE]
+    let config = mock_provider("data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n").await;
+
+    let chunks = collect(&config).await;
+
+    assert!(!chunks.iter().any(|c| matches!(c, StreamChunk::Usage(_))));
+    assert!(matches!(&chunks[..], [StreamChunk::Delta(_), StreamChunk::Done]));
+}
+
+#[tokio::test]
+async fn test_user_field_is_forwarded() {
+    // Echo the request's `user` field back as the completion text
+    let config = mock_provider_with(Router::new().route(
+        "/chat/completions",
+        post(|Json(body): Json<serde_json::Value>| async move {
+            let chunk = serde_json::json!({ "choices": [{ "delta": { "content": body["user"] } }] });
+            format!("data: {}\n\n", chunk)
+        }),
+    ))
+    .await;
+
+    let llm = LlmClient::new_groq(&config)
+        .unwrap()
+        .with_user(Some("key-id:user-42".to_string()));
+    let chunks: Vec<_> = llm
+        .complete_stream(Vec::new(), "llama-3.1-8b-instant", None, None, None)
+        .await
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect()
+        .await;
+
+    assert!(matches!(&chunks[0], StreamChunk::Delta(d) if d == "key-id:user-42"));
+}
+
+#[tokio::test]
+async fn test_finish_reason_is_reported() {
+    let config = mock_provider_with(Router::new().route(
+        "/chat/completions",
+        post(|| async {
+            Json(serde_json::json!({
+                "id": "cmpl-1",
+                "choices": [{ "message": { "content": "def solve(" }, "finish_reason": "length" }]
+            }))
+        }),
+    ))
+    .await;
+
+    let response = LlmClient::new_groq(&config)
+        .unwrap()
+        .complete("Solve two-sum", "llama-3.1-8b-instant", None, Some(3), None)
+        .await
+        .unwrap();
+
+    assert_eq!(response.content, "def solve(");
+    assert_eq!(response.finish_reason.as_deref(), Some("length"));
+}
+
+#[test]
+fn test_chunk_lines() {
+    let lines = ["a".repeat(40), "b".repeat(40), "c".repeat(40)];
+    // 10 tokens each: two fit in a chunk of 25, the third starts another
+    let chunks = chunk_lines(&lines, 25);
+    assert_eq!(chunks, [format!("{}\n{}", lines[0], lines[1]), lines[2].clone()]);
+
+    // An oversized line is cut to fit on its own
+    let chunks = chunk_lines(&["x".repeat(100)], 5);
+    assert_eq!(chunks, ["x".repeat(20)]);
+
+    assert!(chunk_lines(&[], 25).is_empty());
+}
+
+#[test]
+fn test_split_think() {
+    let (answer, reasoning) = split_think("<think>\nThey want a number.\n</think>\n\n42");
+    assert_eq!(answer, "42");
+    assert_eq!(reasoning.as_deref(), Some("They want a number."));
+
+    let (answer, reasoning) = split_think("<think>a</think>First <think>b</think>second");
+    assert_eq!(answer, "First second");
+    assert_eq!(reasoning.as_deref(), Some("a\n\nb"));
+
+    // Cut off mid-thought
+    let (answer, reasoning) = split_think("<think>Still working it o");
+    assert_eq!(answer, "");
+    assert_eq!(reasoning.as_deref(), Some("Still working it o"));
+
+    assert_eq!(split_think("No thinking here"), ("No thinking here".to_string(), None));
+}
+
+#[tokio::test]
+async fn test_reasoning_is_kept_out_of_content() {
+    let app = || {
+        Router::new().route(
+            "/chat/completions",
+            post(|| async {
+                Json(serde_json::json!({
+                    "id": "cmpl-1",
+                    "choices": [{
+                        "message": {
+                            "content": "<think>Inline thoughts</think>Paris",
+                            "reasoning_content": "Reported thoughts"
+                        },
+                        "finish_reason": "stop"
+                    }]
+                }))
+            }),
+        )
+    };
+    let complete = |config: Config| async move {
+        LlmClient::new_groq(&config)
+            .unwrap()
+            .complete("Capital of France?", "llama-3.1-8b-instant", None, None, None)
+            .await
+            .unwrap()
+    };
+
+    // Inline blocks are left alone by default
+    let response = complete(mock_provider_with(app()).await).await;
+    assert_eq!(response.content, "<think>Inline thoughts</think>Paris");
+    assert_eq!(response.reasoning.as_deref(), Some("Reported thoughts"));
+
+    let mut config = mock_provider_with(app()).await;
+    config.split_think_blocks = true;
+    let response = complete(config).await;
+    assert_eq!(response.content, "Paris");
+    assert_eq!(response.reasoning.as_deref(), Some("Reported thoughts\n\nInline thoughts"));
+}
+
+#[test]
+fn test_parse_rerank_scores() {
+    assert_eq!(parse_rerank_scores(r#"{"scores": [0.9, 0.2]}"#, 2).unwrap(), vec![0.9, 0.2]);
+
+    // Fenced output and out-of-range values
+    let fenced = "```json\n{\"scores\": [1.5, -0.1, 0.5]}\n```";
+    assert_eq!(parse_rerank_scores(fenced, 3).unwrap(), vec![1.0, 0.0, 0.5]);
+
+    assert!(parse_rerank_scores(r#"{"scores": [0.9]}"#, 2).is_err());
+    assert!(parse_rerank_scores("the first one is best", 2).is_err());
+}
+
+#[test]
+fn test_trivial_messages_are_not_titled() {
+    assert!(!is_substantive("hi", 3));
+    assert!(!is_substantive("  hello there  ", 3));
+    assert!(is_substantive("How do I reverse a linked list?", 3));
+    assert!(!is_substantive("", 0));
+}
+
+#[test]
+fn test_clean_title() {
+    assert_eq!(clean_title("\"Reversing a Linked List.\"").as_deref(), Some("Reversing a Linked List"));
+    assert_eq!(clean_title("Title: System Design Prep!").as_deref(), Some("System Design Prep"));
+    assert_eq!(clean_title("\n**Binary Search Basics**\nextra").as_deref(), Some("Binary Search Basics"));
+    assert_eq!(clean_title(" \"\" "), None);
+
+    let long = "word ".repeat(40);
+    let title = clean_title(&long).unwrap();
+    assert!(title.chars().count() <= TITLE_MAX_CHARS);
+    assert!(title.ends_with("word"));
+}
+
+#[tokio::test]
+async fn test_temperature_for_type() {
+    let mut config = mock_provider("").await;
+    config.type_temperatures.insert("meeting".to_string(), 0.9);
+    let llm = LlmClient::new_groq(&config).unwrap();
+
+    assert_eq!(llm.temperature_for(Some("meeting")), 0.9);
+    assert_eq!(llm.temperature_for(Some("coding")), 0.0);
+    assert_eq!(llm.temperature_for(Some("unknown")), llm.temperature_for(Some("general")));
+    assert_eq!(llm.temperature_for(None), 0.3);
+    assert_eq!(llm.temperature_for(Some("")), 0.3);
+    assert_eq!(llm.temperature_for(Some(" meeting ")), 0.9);
+}
+
+#[test]
+fn test_normalize_type() {
+    assert_eq!(normalize_type(None), None);
+    assert_eq!(normalize_type(Some("")), None);
+    assert_eq!(normalize_type(Some("  ")), None);
+    assert_eq!(normalize_type(Some(" coding\n")), Some("coding"));
+}
+
+#[test]
+fn test_provider_error_detects_context_length() {
+    let openai = r#"{"error":{"message":"This model's maximum context length is 8192 tokens. However, you requested 9000 tokens (8000 in the messages, 1000 in the completion).","code":"context_length_exceeded"}}"#;
+    assert!(matches!(
+        provider_error(openai),
+        LlmError::ContextLengthExceeded { limit: Some(8192), requested: Some(9000) }
+    ));
+
+    let openrouter = r#"{"error":{"message":"This endpoint's maximum context length is 131072 tokens. However, you requested about 140,512 tokens.","code":400}}"#;
+    assert!(matches!(
+        provider_error(openrouter),
+        LlmError::ContextLengthExceeded { limit: Some(131072), requested: Some(140512) }
+    ));
+
+    let anthropic = r#"{"error":{"message":"prompt is too long: 210000 tokens > 200000 maximum","code":400}}"#;
+    assert!(matches!(
+        provider_error(anthropic),
+        LlmError::ContextLengthExceeded { limit: Some(200000), requested: Some(210000) }
+    ));
+
+    let groq = r#"{"error":{"message":"Please reduce the length of the messages or completion.","code":"context_length_exceeded"}}"#;
+    assert!(matches!(
+        provider_error(groq),
+        LlmError::ContextLengthExceeded { limit: None, requested: None }
+    ));
+
+    let other = r#"{"error":{"message":"Invalid model","code":"model_not_found"}}"#;
+    assert!(matches!(provider_error(other), LlmError::ApiError(m) if m == "Invalid model"));
+    assert!(matches!(provider_error("Bad gateway"), LlmError::ApiError(m) if m == "Bad gateway"));
+}
+
+#[tokio::test]
+async fn test_complete_reports_context_length_exceeded() {
+    let config = mock_provider_with(Router::new().route(
+        "/chat/completions",
+        post(|| async {
+            let body = r#"{"error":{"message":"Please reduce the length of the messages or completion.","code":"context_length_exceeded"}}"#;
+            (axum::http::StatusCode::BAD_REQUEST, body)
+        }),
+    ))
+    .await;
+
+    let llm = LlmClient::new_groq(&config).unwrap();
+    let result = llm.complete("very long prompt", "llama-3.1-8b-instant", None, None, None).await;
+    assert!(matches!(result, Err(LlmError::ContextLengthExceeded { .. })));
+}
@@ -0,0 +1,991 @@
+//! Handlers and clients against `MockBackend`. Run with `cargo test --features mock`.
+#![cfg(feature = "mock")]
+
+use axum::Router;
+use axum_test::TestServer;
+use cleuly::router::build_router;
+use cleuly::config::{Config, Persistence};
+use cleuly::modules::session::{crud::SessionCrud, model::Message};
+use cleuly::services::backend::mock::MockBackend;
+use cleuly::services::llm::{ChatMessage, LlmClient, StreamChunk};
+use cleuly::services::stt::{SttClient, SttError, SttSegment};
+use cleuly::{config, AppState};
+use futures::StreamExt;
+use serde_json::json;
+use std::future::IntoFuture;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn mock_config(backend: Arc<MockBackend>) -> Config {
+    Config {
+        client_backend: Some(backend),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn test_llm_client_without_provider_keys() {
+    let backend = Arc::new(MockBackend::new().with_completion("canned answer"));
+    let config = mock_config(backend.clone());
+
+    let llm = LlmClient::new(&config).unwrap().with_user(Some("user-1".to_string()));
+    let response = llm
+        .complete_messages(vec![ChatMessage::new("user", "two words")], "any-model", None, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(response.content, "canned answer");
+    assert_eq!(response.usage.unwrap().prompt_tokens, 2);
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].model, "any-model");
+    assert_eq!(requests[0].user.as_deref(), Some("user-1"));
+}
+
+#[tokio::test]
+async fn test_stream_from_mock() {
+    let config = mock_config(Arc::new(MockBackend::new().with_completion("streamed")));
+
+    let chunks: Vec<_> = LlmClient::new_groq(&config)
+        .unwrap()
+        .complete_stream(Vec::new(), "any-model", None, None, None)
+        .await
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect()
+        .await;
+
+    assert!(matches!(&chunks[0], StreamChunk::Delta(d) if d == "streamed"));
+    assert!(matches!(chunks[1], StreamChunk::Usage(_)));
+    assert!(matches!(chunks[2], StreamChunk::Done));
+}
+
+#[tokio::test]
+async fn test_stt_client_without_provider_keys() {
+    let config = mock_config(Arc::new(MockBackend::new().with_transcription("hello there")));
+
+    let response = SttClient::new(&config)
+        .unwrap()
+        .transcribe(vec![0; 64_000], "clip.wav", Some("es"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.text, "hello there");
+    assert_eq!(response.language.as_deref(), Some("es"));
+    assert_eq!(response.duration, Some(2.0));
+}
+
+#[tokio::test]
+async fn test_duration_cap_after_transcription() {
+    // The mock reports 2s for 64KB; OGG can't be sized up front
+    let config = Config {
+        max_audio_duration_secs: Some(1),
+        ..mock_config(Arc::new(MockBackend::new()))
+    };
+
+    let result = SttClient::new(&config)
+        .unwrap()
+        .transcribe(vec![0; 64_000], "clip.ogg", None)
+        .await;
+
+    assert!(matches!(result, Err(SttError::AudioTooLong { limit: 1, .. })));
+}
+
+/// Mongo and Redis still come from the environment; only providers are mocked
+async fn setup_test_server(backend: Arc<MockBackend>) -> TestServer {
+    setup_test_server_with(backend, |_| {}).await
+}
+
+async fn setup_test_server_with(backend: Arc<MockBackend>, configure: impl FnOnce(&mut Config)) -> TestServer {
+    dotenvy::dotenv().ok();
+
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    settings.client_backend = Some(backend);
+    configure(&mut settings);
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+
+    let state = AppState::new(db, redis, settings.clone());
+
+    let app = build_router(state, &settings);
+
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn test_complete_handler_with_mock() {
+    let backend = Arc::new(MockBackend::new().with_completion("mocked reply"));
+    let server = setup_test_server(backend.clone()).await;
+
+    let response = server
+        .post("/api/ai/complete")
+        .json(&json!({ "prompt": "Say something" }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["content"], "mocked reply");
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_cached_analysis_is_not_billed() {
+    let backend = Arc::new(MockBackend::new().with_completion("NEUTRAL - just facts"));
+    let server = setup_test_server_with(backend.clone(), |config| {
+        config.analyze_cache_ttl_secs = Some(60);
+    })
+    .await;
+    let text = format!("The meeting is at {}", uuid::Uuid::new_v4());
+
+    let fresh: serde_json::Value = server
+        .post("/api/ai/analyze")
+        .json(&json!({ "text": text, "analysis_type": "sentiment" }))
+        .await
+        .json();
+    assert_eq!(fresh["cached"], false);
+    assert_eq!(fresh["billed_tokens"], fresh["usage"]["total_tokens"]);
+
+    let cached: serde_json::Value = server
+        .post("/api/ai/analyze")
+        .json(&json!({ "text": text, "analysis_type": "sentiment" }))
+        .await
+        .json();
+    assert_eq!(cached["cached"], true);
+    assert_eq!(cached["billed_tokens"], 0);
+    assert_eq!(cached["usage"], fresh["usage"]);
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_suggest_stream_is_saved() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion("Use a hash map"))).await;
+
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let session_id = created["id"].as_str().unwrap();
+
+    let text = server
+        .post("/api/ai/suggest/stream")
+        .json(&json!({
+            "context": "Two sum?",
+            "suggestion_type": "coding",
+            "session_id": session_id,
+        }))
+        .await
+        .text();
+    assert!(text.contains("event: delta"));
+
+    let done_at = text.find("event: done").unwrap();
+    let done = text[done_at..].lines().find_map(|l| l.strip_prefix("data: ")).unwrap();
+    let done: serde_json::Value = serde_json::from_str(done).unwrap();
+    let completion: serde_json::Value = server
+        .get(&format!("/api/ai/completions/{}", done["id"].as_str().unwrap()))
+        .await
+        .json();
+    assert_eq!(completion["content"], "Use a hash map");
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", session_id)).await.json();
+    assert_eq!(session["messages"][0]["content"], "Use a hash map");
+
+    server.delete(&format!("/api/session/{}", session_id)).await;
+}
+
+#[tokio::test]
+async fn test_blank_suggestion_type_is_no_type() {
+    let backend = Arc::new(MockBackend::new().with_completion("ok"));
+    let server = setup_test_server(backend.clone()).await;
+
+    for suggestion_type in [json!(null), json!(""), json!("  ")] {
+        server
+            .post("/api/ai/suggest")
+            .json(&json!({ "context": "Tell me about yourself", "suggestion_type": suggestion_type }))
+            .await
+            .assert_status_ok();
+    }
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 3);
+    let prompts = |i: usize| requests[i].messages.iter().map(|m| m.content.clone()).collect::<Vec<_>>();
+    for i in 1..3 {
+        assert_eq!(prompts(i), prompts(0));
+        assert_eq!(requests[i].temperature, requests[0].temperature);
+    }
+}
+
+#[tokio::test]
+async fn test_full_content_after_truncated_display() {
+    let answer = "First line of the answer\nSecond line with the rest of the details";
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion(answer))).await;
+
+    let shown: serde_json::Value = server
+        .post("/api/ai/complete")
+        .add_query_param("max_display_chars", 30)
+        .json(&json!({ "prompt": "Explain" }))
+        .await
+        .json();
+    assert_eq!(shown["truncated"], true);
+    let id = shown["id"].as_str().unwrap();
+
+    let full: serde_json::Value = server
+        .get(&format!("/api/ai/completions/{}/content", id))
+        .await
+        .json();
+    assert_eq!(full["content"], answer);
+
+    let text = server
+        .get(&format!("/api/ai/completions/{}/content", id))
+        .add_header("accept", "text/plain")
+        .await;
+    assert_eq!(text.header("content-type"), "text/plain; charset=utf-8");
+    assert_eq!(text.text(), answer);
+}
+
+#[tokio::test]
+async fn test_replay_completion_on_another_model() {
+    let backend = Arc::new(MockBackend::new().with_completion("same answer"));
+    let server = setup_test_server(backend.clone()).await;
+
+    let original: serde_json::Value = server
+        .post("/api/ai/complete")
+        .json(&json!({ "prompt": "Explain tries", "system_prompt": "Be brief", "model": "model-a" }))
+        .await
+        .json();
+    let id = original["id"].as_str().unwrap();
+
+    let response = server
+        .post(&format!("/api/ai/completions/{}/replay", id))
+        .add_query_param("model", "model-b")
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["original"]["id"], id);
+    assert_eq!(body["original"]["model"], "model-a");
+    assert_eq!(body["replay"]["model"], "model-b");
+    assert_ne!(body["replay"]["id"], id);
+
+    let requests = backend.requests();
+    let replayed = requests.last().unwrap();
+    assert_eq!(replayed.model, "model-b");
+    assert_eq!(replayed.messages[0].content, "Be brief");
+}
+
+#[tokio::test]
+async fn test_completions_are_scoped_to_their_owner() {
+    let (owner, other) = ("owner-key", "other-key");
+    let backend = Arc::new(MockBackend::new().with_completion("private answer"));
+    let server = setup_test_server_with(backend, |config| {
+        config.api_keys = vec![owner.to_string(), other.to_string()];
+    })
+    .await;
+
+    let created: serde_json::Value = server
+        .post("/api/ai/complete")
+        .add_header("x-api-key", owner)
+        .json(&json!({ "prompt": "Something private" }))
+        .await
+        .json();
+    let path = format!("/api/ai/completions/{}", created["id"].as_str().unwrap());
+
+    server.get(&path).add_header("x-api-key", owner).await.assert_status_ok();
+    for response in [
+        server.get(&path).add_header("x-api-key", other).await,
+        server.get(&format!("{}/content", path)).add_header("x-api-key", other).await,
+        server.post(&format!("{}/replay", path)).add_header("x-api-key", other).await,
+    ] {
+        response.assert_status(axum::http::StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn test_streamed_chat_is_saved() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion("streamed reply"))).await;
+
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let id = created["id"].as_str().unwrap();
+
+    let body = server
+        .post(&format!("/api/session/{}/chat/stream", id))
+        .json(&json!({ "message": "Hello there" }))
+        .await
+        .text();
+    assert!(body.contains("event: delta"));
+    assert!(body.contains("event: done"));
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["messages"][1]["content"], "streamed reply");
+    assert_eq!(session["messages"][1]["incomplete"], false);
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_similar_sessions() {
+    let server = setup_test_server(Arc::new(MockBackend::new())).await;
+
+    let mut ids = Vec::new();
+    for content in ["Design a rate limiter", "Design a rate limiter", "~~~ 0123 ~~~"] {
+        let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+        let id = created["id"].as_str().unwrap().to_string();
+        server
+            .post(&format!("/api/session/{}/message", id))
+            .json(&json!({ "role": "user", "content": content }))
+            .await
+            .assert_status_ok();
+        ids.push(id);
+    }
+    for id in &ids[1..] {
+        server.post(&format!("/api/session/{}/index", id)).await.assert_status_ok();
+    }
+
+    // The target is indexed on demand
+    let response = server.get(&format!("/api/session/{}/similar?limit=50", ids[0])).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let data = body["data"].as_array().unwrap();
+    assert!(data.iter().all(|s| s["id"] != ids[0].as_str()));
+    assert_eq!(data[0]["id"], ids[1].as_str());
+    assert!(data[0]["similarity"].as_f64().unwrap() > 0.999);
+    let unrelated = data.iter().find(|s| s["id"] == ids[2].as_str()).unwrap();
+    assert!(unrelated["similarity"].as_f64().unwrap() < 0.999);
+
+    for id in &ids {
+        server.delete(&format!("/api/session/{}", id)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_new_messages_are_indexed_in_background() {
+    let server = setup_test_server_with(Arc::new(MockBackend::new()), |config| {
+        config.auto_index_sessions = true;
+    })
+    .await;
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+        let id = created["id"].as_str().unwrap().to_string();
+        server
+            .post(&format!("/api/session/{}/message", id))
+            .json(&json!({ "role": "user", "content": "Design a URL shortener" }))
+            .await
+            .assert_status_ok();
+        ids.push(id);
+    }
+
+    // Neither session was indexed by hand
+    let mut found = false;
+    for _ in 0..20 {
+        let body: serde_json::Value =
+            server.get(&format!("/api/session/{}/similar?limit=50", ids[0])).await.json();
+        found = body["data"].as_array().unwrap().iter().any(|s| s["id"] == ids[1].as_str());
+        if found {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(found);
+
+    for id in &ids {
+        server.delete(&format!("/api/session/{}", id)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_chat_preview_is_not_saved() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion("preview reply"))).await;
+
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let id = created["id"].as_str().unwrap();
+
+    let response = server
+        .post(&format!("/api/session/{}/chat", id))
+        .add_query_param("persist", false)
+        .json(&json!({ "message": "What should I say next?" }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["response"]["content"], "preview reply");
+    assert_eq!(body["persisted"], false);
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["message_count"], 0);
+    assert!(session["title"].is_null());
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_chat_keeps_provider_response_id() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion("traced reply"))).await;
+
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let id = created["id"].as_str().unwrap();
+
+    let body: serde_json::Value = server
+        .post(&format!("/api/session/{}/chat", id))
+        .json(&json!({ "message": "Which request was this?" }))
+        .await
+        .json();
+    let provider_id = body["response"]["provider_response_id"].as_str().unwrap();
+    assert!(provider_id.starts_with("mock-"));
+    assert!(body["message"].get("provider_response_id").is_none());
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["messages"][1]["provider_response_id"], provider_id);
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_no_store_mode_answers_without_saving() {
+    let backend = Arc::new(MockBackend::new().with_completion("private reply"));
+    let server = setup_test_server_with(backend, |config| {
+        config.persistence = Persistence::None;
+    })
+    .await;
+
+    let completed: serde_json::Value = server
+        .post("/api/ai/complete")
+        .json(&json!({ "prompt": "Something confidential" }))
+        .await
+        .json();
+    assert_eq!(completed["content"], "private reply");
+    let id = completed["id"].as_str().unwrap();
+    server
+        .get(&format!("/api/ai/completions/{}", id))
+        .await
+        .assert_status_not_found();
+
+    // The session itself exists, but the turn isn't kept
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let session_id = created["id"].as_str().unwrap();
+    let response = server
+        .post(&format!("/api/session/{}/chat", session_id))
+        .json(&json!({ "message": "Remember this for later" }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["response"]["content"], "private reply");
+    assert_eq!(body["persisted"], false);
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", session_id)).await.json();
+    assert_eq!(session["message_count"], 0);
+
+    let missing = bson::oid::ObjectId::new().to_hex();
+    server
+        .post(&format!("/api/session/{}/message", missing))
+        .json(&json!({ "role": "user", "content": "hello" }))
+        .await
+        .assert_status_not_found();
+
+    server.delete(&format!("/api/session/{}", session_id)).await;
+}
+
+#[tokio::test]
+async fn test_retitle_long_session() {
+    let backend = Arc::new(MockBackend::new().with_completion("Title: Borrowing in Rust."));
+    let server = setup_test_server(backend.clone()).await;
+
+    let created: serde_json::Value = server
+        .post("/api/session")
+        .json(&json!({ "title": "Old title" }))
+        .await
+        .json();
+    let id = created["id"].as_str().unwrap();
+
+    // Too long for one prompt, so it is summarized in pieces first
+    let messages = (0..40)
+        .map(|i| json!({ "role": "user", "content": format!("{} {}", i, "borrow ".repeat(200)) }))
+        .collect::<Vec<_>>();
+    server
+        .post(&format!("/api/session/{}/messages/bulk", id))
+        .json(&json!({ "messages": messages }))
+        .await
+        .assert_status_ok();
+
+    server
+        .post(&format!("/api/session/{}/retitle", id))
+        .add_query_param("max_words", 0)
+        .await
+        .assert_status_bad_request();
+
+    let response = server
+        .post(&format!("/api/session/{}/retitle", id))
+        .add_query_param("max_words", 4)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Borrowing in Rust");
+
+    let requests = backend.requests();
+    assert!(requests.len() > 2);
+    let title_request = requests.last().unwrap();
+    assert!(title_request.messages[0].content.contains("at most 4 words"));
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["title"], "Borrowing in Rust");
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_continue_incomplete_reply() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion(" the rest."))).await;
+
+    let created: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let id = created["id"].as_str().unwrap();
+
+    // As left by a stream the client dropped
+    let settings = Config::from_env().unwrap();
+    let crud = SessionCrud::new(
+        &config::database::connect(&settings).await,
+        config::redis::connect(&settings).await,
+    );
+    let oid = bson::oid::ObjectId::parse_str(id).unwrap();
+    let partial = [
+        Message::user("Explain borrowing".to_string()),
+        Message::assistant("Borrowing lets you".to_string()).with_incomplete(true),
+    ];
+    crud.add_messages(&oid, &partial).await.unwrap();
+
+    let response = server.post(&format!("/api/session/{}/continue", id)).await;
+    response.assert_status_ok();
+    let message: serde_json::Value = response.json();
+    assert_eq!(message["content"], "Borrowing lets you the rest.");
+    assert_eq!(message["incomplete"], false);
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["messages"][1]["content"], "Borrowing lets you the rest.");
+    assert_eq!(session["messages"][1]["incomplete"], false);
+
+    let again = server.post(&format!("/api/session/{}/continue", id)).await;
+    again.assert_status(axum::http::StatusCode::CONFLICT);
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_rerank_handler_with_mock() {
+    let backend = Arc::new(MockBackend::new().with_completion(r#"{"scores": [0.2, 0.8]}"#));
+    let server = setup_test_server(backend).await;
+
+    let response = server
+        .post("/api/ai/rerank")
+        .json(&json!({
+            "query": "Reverse a linked list",
+            "candidates": ["Use a hash map", "Walk the list flipping next pointers"]
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["results"][0]["index"], 1);
+    assert_eq!(body["results"][1]["index"], 0);
+}
+
+#[tokio::test]
+async fn test_benchmark_is_recorded_in_history() {
+    let backend = Arc::new(MockBackend::new().with_completion("def is_prime(n): ..."));
+    let server = setup_test_server(backend.clone()).await;
+
+    let model = format!("bench-{}", uuid::Uuid::new_v4());
+    let response = server
+        .post("/api/ai/benchmark")
+        .json(&json!({ "models": [model, "other-model"], "max_tokens": 50 }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["results"][0]["model"], model.as_str());
+    assert_eq!(body["results"].as_array().unwrap().len(), 2);
+    assert_eq!(backend.requests()[0].max_tokens, Some(50));
+
+    let history: serde_json::Value = server
+        .get("/api/ai/benchmark/history")
+        .add_query_param("model", &model)
+        .await
+        .json();
+    let points = history["points"].as_array().unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0]["runs"], 1);
+    assert_eq!(points[0]["errors"], 0);
+}
+
+#[tokio::test]
+async fn test_complete_many_keeps_order_and_system_prompt() {
+    let backend = Arc::new(MockBackend::new());
+    let llm = LlmClient::new(&mock_config(backend.clone())).unwrap();
+
+    let prompts = vec!["first question".to_string(), "second".to_string()];
+    let results = llm
+        .complete_many(&prompts, "any-model", Some("Answer briefly"), Some(100), None)
+        .await;
+    assert_eq!(results.len(), 2);
+    // Prompt token counts tell the results apart: system prompt plus prompt
+    assert_eq!(results[0].as_ref().unwrap().usage.as_ref().unwrap().prompt_tokens, 4);
+    assert_eq!(results[1].as_ref().unwrap().usage.as_ref().unwrap().prompt_tokens, 3);
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request.messages[0].role, "system");
+        assert_eq!(request.messages[0].content, "Answer briefly");
+        assert_eq!(request.max_tokens, Some(100));
+    }
+}
+
+#[tokio::test]
+async fn test_complete_batch_endpoint() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_completion("an answer"))).await;
+
+    let response = server
+        .post("/api/ai/complete/batch")
+        .json(&json!({
+            "prompts": ["What is Rust?", "What is Go?"],
+            "system_prompt": "You are interviewing for a backend role"
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["prompt"], "What is Rust?");
+    assert_eq!(results[1]["prompt"], "What is Go?");
+    assert_eq!(results[1]["content"], "an answer");
+    assert!(results[1]["id"].is_string());
+}
+
+#[tokio::test]
+async fn test_benchmark_reports_slow_models() {
+    let backend = MockBackend::new().with_delay("slow-model", Duration::from_secs(30));
+    let server = setup_test_server_with(Arc::new(backend), |config| {
+        config.benchmark_model_timeout_ms = 100;
+        config.benchmark_deadline_ms = 5_000;
+    })
+    .await;
+
+    let body: serde_json::Value = server
+        .post("/api/ai/benchmark")
+        .json(&json!({ "models": ["slow-model", "fast-model"] }))
+        .await
+        .json();
+    assert_eq!(body["results"][0]["model"], "slow-model");
+    assert_eq!(body["results"][0]["error"], "timeout");
+    assert!(body["results"][0]["latency_ms"].as_u64().unwrap() >= 100);
+    assert_eq!(body["results"][1]["model"], "fast-model");
+    assert!(body["results"][1].get("error").is_none());
+    assert_eq!(body["partial"], false);
+
+    // Past the deadline the rest are abandoned, without waiting out their timeout
+    let server = setup_test_server_with(
+        Arc::new(MockBackend::new().with_delay("hung-model", Duration::from_secs(30))),
+        |config| {
+            config.benchmark_model_timeout_ms = 10_000;
+            config.benchmark_deadline_ms = 100;
+        },
+    )
+    .await;
+    let body: serde_json::Value = server
+        .post("/api/ai/benchmark")
+        .json(&json!({ "models": ["hung-model", "fast-model"] }))
+        .await
+        .json();
+    assert_eq!(body["partial"], true);
+    assert_eq!(body["pending"], json!(["hung-model"]));
+    assert_eq!(body["results"].as_array().unwrap().len(), 1);
+    assert_eq!(body["results"][0]["model"], "fast-model");
+}
+
+#[tokio::test]
+async fn test_ai_burst_queues_or_sheds_per_key() {
+    let backend = || Arc::new(MockBackend::new().with_delay("slow-model", Duration::from_millis(300)));
+    let request = json!({ "prompt": "Say something", "model": "slow-model" });
+
+    // Queued: the second request waits for the first's slot instead of failing
+    let server = setup_test_server_with(backend(), |config| {
+        config.ai_max_concurrent_per_key = 1;
+        config.ai_queue_wait_ms = Some(2_000);
+    })
+    .await;
+    let started = std::time::Instant::now();
+    let (first, second) = futures::join!(
+        server.post("/api/ai/complete").json(&request).into_future(),
+        server.post("/api/ai/complete").json(&request).into_future(),
+    );
+    first.assert_status_ok();
+    second.assert_status_ok();
+    assert!(started.elapsed() >= Duration::from_millis(600));
+
+    // Shed: the second request is turned away at once
+    let server = setup_test_server_with(backend(), |config| {
+        config.ai_max_concurrent_per_key = 1;
+        config.ai_queue_wait_ms = None;
+    })
+    .await;
+    let (first, second) = futures::join!(
+        server.post("/api/ai/complete").json(&request).into_future(),
+        server.post("/api/ai/complete").json(&request).expect_failure().into_future(),
+    );
+    first.assert_status_ok();
+    second.assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(second.json::<serde_json::Value>()["code"], "too_many_ai_requests");
+}
+
+#[tokio::test]
+async fn test_transcribe_handler_with_mock() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_transcription("mocked speech"))).await;
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+
+    let response = server
+        .post("/api/stt/transcribe")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["text"], "mocked speech");
+}
+
+#[tokio::test]
+async fn test_transcribe_accepts_uploads_over_default_body_limit() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_transcription("mocked speech"))).await;
+
+    // Past axum's 2MB default, within MAX_AUDIO_BYTES
+    let mut body = b"--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.mp3\"\r\n\
+        Content-Type: audio/mpeg\r\n\r\nID3"
+        .to_vec();
+    body.extend(vec![0u8; 3 * 1024 * 1024]);
+    body.extend(b"\r\n--BOUNDARY--\r\n");
+
+    let response = server
+        .post("/api/stt/transcribe")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.into())
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["text"], "mocked speech");
+}
+
+#[tokio::test]
+async fn test_transcription_is_added_to_session_as_role() {
+    let server = setup_test_server(Arc::new(MockBackend::new().with_transcription("mocked speech"))).await;
+    let session: serde_json::Value = server.post("/api/session").json(&json!({})).await.json();
+    let session_id = session["id"].as_str().unwrap();
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+
+    let response: serde_json::Value = server
+        .post("/api/stt/transcribe")
+        .add_query_param("session_id", session_id)
+        .add_query_param("add_as", "assistant")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await
+        .json();
+    assert_eq!(response["session_message_count"], 1);
+
+    let stored: serde_json::Value = server.get(&format!("/api/session/{}", session_id)).await.json();
+    let message = &stored["messages"][0];
+    assert_eq!(message["role"], "assistant");
+    assert_eq!(message["content"], "mocked speech");
+
+    server.delete(&format!("/api/session/{}?cascade=true", session_id)).await;
+}
+
+#[tokio::test]
+async fn test_quick_answer_with_mock() {
+    let backend = Arc::new(
+        MockBackend::new()
+            .with_transcription("what is a closure")
+            .with_completion("A function that captures its environment."),
+    );
+    let server = setup_test_server(backend.clone()).await;
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+
+    let response = server
+        .post("/api/stt/quick-answer")
+        .add_query_param("suggestion_type", "code")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["transcription"], "what is a closure");
+    assert_eq!(body["answer"], "A function that captures its environment.");
+    assert!(body.get("id").is_none());
+    assert!(backend.requests()[0].messages.iter().any(|m| m.content.contains("what is a closure")));
+}
+
+#[tokio::test]
+async fn test_streamed_transcribe_and_respond() {
+    let backend = Arc::new(
+        MockBackend::new()
+            .with_transcription("what is a closure")
+            .with_completion("A function that captures its environment."),
+    );
+    let server = setup_test_server(backend).await;
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+
+    let text = server
+        .post("/api/stt/transcribe-ai/stream")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await
+        .text();
+
+    let transcription_at = text.find("event: transcription").unwrap();
+    let delta_at = text.find("event: delta").unwrap();
+    let done_at = text.find("event: done").unwrap();
+    assert!(transcription_at < delta_at && delta_at < done_at);
+
+    let done = text[done_at..].lines().find_map(|l| l.strip_prefix("data: ")).unwrap();
+    let done: serde_json::Value = serde_json::from_str(done).unwrap();
+    assert_eq!(done["ai_response"], "A function that captures its environment.");
+    assert_eq!(done["low_confidence"], false);
+
+    let id = done["id"].as_str().unwrap();
+    let saved: serde_json::Value = server.get(&format!("/api/stt/transcription/{}", id)).await.json();
+    assert_eq!(saved["text"], "what is a closure");
+    server.delete(&format!("/api/stt/transcription/{}", id)).await;
+}
+
+fn segment(text: &str, avg_logprob: f32, no_speech_prob: f32) -> SttSegment {
+    SttSegment {
+        id: 0,
+        start: 0.0,
+        end: 1.0,
+        text: text.to_string(),
+        avg_logprob: Some(avg_logprob),
+        no_speech_prob: Some(no_speech_prob),
+    }
+}
+
+#[tokio::test]
+async fn test_low_confidence_transcription_is_flagged() {
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+    let mumbled = || {
+        Arc::new(
+            MockBackend::new()
+                .with_transcription("wot is a closher")
+                .with_completion("A closure captures its environment.")
+                .with_segments(vec![segment("wot is a closher", -1.5, 0.4)]),
+        )
+    };
+
+    // Caveat: still answered, with the model told the text may be misheard
+    let backend = mumbled();
+    let server = setup_test_server_with(backend.clone(), |config| {
+        config.stt_min_answer_confidence = Some(0.5);
+    })
+    .await;
+    let response: serde_json::Value = server
+        .post("/api/stt/transcribe-ai")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await
+        .json();
+    assert_eq!(response["low_confidence"], true);
+    assert!(response["confidence"].as_f64().unwrap() < 0.5);
+    assert_eq!(response["ai_response"], "A closure captures its environment.");
+    let requests = backend.requests();
+    assert!(requests[0].messages.last().unwrap().content.contains("may be misheard"));
+    server.delete(&format!("/api/stt/transcription/{}", response["id"].as_str().unwrap())).await;
+
+    // Skip: no answer and no model call
+    let backend = mumbled();
+    let server = setup_test_server_with(backend.clone(), |config| {
+        config.stt_min_answer_confidence = Some(0.5);
+        config.stt_low_confidence_action = "skip".to_string();
+    })
+    .await;
+    let response: serde_json::Value = server
+        .post("/api/stt/transcribe-ai")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await
+        .json();
+    assert_eq!(response["low_confidence"], true);
+    assert_eq!(response["ai_response"], "");
+    assert!(backend.requests().is_empty());
+    server.delete(&format!("/api/stt/transcription/{}", response["id"].as_str().unwrap())).await;
+
+    // The streamed variant decides the same way and reports it in `done`
+    let text = server
+        .post("/api/stt/transcribe-ai/stream")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await
+        .text();
+    assert!(!text.contains("event: delta"));
+    let done_at = text.find("event: done").unwrap();
+    let done = text[done_at..].lines().find_map(|l| l.strip_prefix("data: ")).unwrap();
+    let done: serde_json::Value = serde_json::from_str(done).unwrap();
+    assert_eq!(done["low_confidence"], true);
+    assert_eq!(done["ai_response"], "");
+    assert!(backend.requests().is_empty());
+    server.delete(&format!("/api/stt/transcription/{}", done["id"].as_str().unwrap())).await;
+}
+
+#[tokio::test]
+async fn test_streamed_transcription_is_persisted() {
+    let backend = Arc::new(MockBackend::new().with_transcription("streamed speech"));
+    let server = setup_test_server_with(backend, |config| config.audio_url_allow_private = true).await;
+
+    let audio = Router::new().route("/clip.wav", axum::routing::get(|| async { vec![0u8; 32_000] }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, audio).await.unwrap() });
+
+    let response = server
+        .post("/api/stt/transcribe-url/stream")
+        .json(&json!({ "url": format!("http://{}/clip.wav", addr) }))
+        .await;
+    let body = response.text();
+
+    assert!(body.contains("event: started"));
+    assert!(body.contains("event: segment"));
+    assert!(body.contains("event: transcription"));
+
+    let id = body
+        .lines()
+        .skip_while(|l| *l != "event: started")
+        .find_map(|l| l.strip_prefix("data: "))
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+        .and_then(|v| v["id"].as_str().map(str::to_string))
+        .unwrap();
+
+    let stored: serde_json::Value = server.get(&format!("/api/stt/transcription/{}", id)).await.json();
+    assert_eq!(stored["status"], "done");
+    assert_eq!(stored["text"], "streamed speech");
+
+    server.delete(&format!("/api/stt/transcription/{}", id)).await;
+}
@@ -0,0 +1,16 @@
+use cleuly::pagination::{Page, Paginated, DEFAULT_PER_PAGE, MAX_PER_PAGE};
+
+#[test]
+fn test_page_defaults_and_bounds() {
+    assert_eq!(Page::new(None, None), Page { page: 1, per_page: DEFAULT_PER_PAGE });
+    assert_eq!(Page::new(Some(0), Some(0)), Page { page: 1, per_page: DEFAULT_PER_PAGE });
+    assert_eq!(Page::new(Some(3), Some(1000)).per_page, MAX_PER_PAGE);
+    assert_eq!(Page::new(Some(3), Some(10)).skip(), 20);
+}
+
+#[test]
+fn test_total_pages() {
+    let page = Page::new(Some(1), Some(10));
+    assert_eq!(Paginated::new(vec![1, 2], page, 21).total_pages, 3);
+    assert_eq!(Paginated::<u8>::new(Vec::new(), page, 0).total_pages, 0);
+}
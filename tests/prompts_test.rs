@@ -0,0 +1,43 @@
+use cleuly::services::prompts::{render, render_named, TemplateError};
+use std::collections::BTreeMap;
+
+fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_render_substitutes_variables() {
+    let variables = vars(&[("name", "Ada"), ("topic_1", "Rust")]);
+    let rendered = render("Hi {name}, about {topic_1}.", &variables, false);
+    assert_eq!(rendered.unwrap(), "Hi Ada, about Rust.");
+}
+
+#[test]
+fn test_render_keeps_literal_braces() {
+    let template = r#"Reply as {"answer": "..."} for {q}, {not a var} {}"#;
+    let rendered = render(template, &vars(&[("q", "x")]), true).unwrap();
+    assert_eq!(rendered, r#"Reply as {"answer": "..."} for x, {not a var} {}"#);
+
+    let rendered = render("{{name}} is {name}", &vars(&[("name", "Ada")]), true).unwrap();
+    assert_eq!(rendered, "{name} is Ada");
+}
+
+#[test]
+fn test_render_missing_variable() {
+    assert_eq!(render("a{gone}b", &vars(&[]), false).unwrap(), "ab");
+    assert_eq!(
+        render("a{gone}b", &vars(&[]), true),
+        Err(TemplateError::MissingVariable("gone".to_string()))
+    );
+}
+
+#[test]
+fn test_render_named() {
+    let templates = vars(&[("greet", "Hello {name}")]);
+    let rendered = render_named(&templates, "greet", &vars(&[("name", "Ada")]), true);
+    assert_eq!(rendered.unwrap(), "Hello Ada");
+    assert_eq!(
+        render_named(&templates, "other", &vars(&[]), true),
+        Err(TemplateError::UnknownTemplate("other".to_string()))
+    );
+}
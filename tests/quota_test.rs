@@ -0,0 +1,22 @@
+use chrono::{TimeZone, Utc};
+use cleuly::services::quota::QuotaPeriod;
+
+#[test]
+fn test_monthly_quota_resets_at_start_of_next_month() {
+    let now = Utc.with_ymd_and_hms(2026, 12, 18, 15, 30, 0).unwrap();
+
+    assert_eq!(
+        QuotaPeriod::Monthly.resets_at(now),
+        Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_daily_quota_resets_at_midnight() {
+    let now = Utc.with_ymd_and_hms(2026, 2, 28, 23, 59, 59).unwrap();
+
+    assert_eq!(
+        QuotaPeriod::Daily.resets_at(now),
+        Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap()
+    );
+}
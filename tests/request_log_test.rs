@@ -0,0 +1,38 @@
+use axum::http::{Method, StatusCode};
+use cleuly::request_log::slow_request_line;
+use cleuly::timing::LlmCall;
+use std::time::Duration;
+
+#[test]
+fn test_slow_request_line_includes_model_and_tokens() {
+    let call = LlmCall {
+        model: "llama-3.1-8b-instant".to_string(),
+        tokens: Some(412),
+    };
+
+    let line = slow_request_line(
+        &Method::POST,
+        "/api/ai/complete",
+        StatusCode::OK,
+        Duration::from_millis(2350),
+        Some(&call),
+    );
+
+    assert_eq!(
+        line,
+        "Slow request: POST /api/ai/complete 200 in 2350ms (model llama-3.1-8b-instant, 412 tokens)"
+    );
+}
+
+#[test]
+fn test_slow_request_line_without_llm_call() {
+    let line = slow_request_line(
+        &Method::GET,
+        "/api/session/{id}",
+        StatusCode::NOT_FOUND,
+        Duration::from_millis(900),
+        None,
+    );
+
+    assert_eq!(line, "Slow request: GET /api/session/{id} 404 in 900ms");
+}
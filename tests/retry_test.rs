@@ -0,0 +1,196 @@
+use axum::{extract::State, http::StatusCode as AxumStatus, routing::post, Router};
+use cleuly::config::{Config, ProviderConfig};
+use cleuly::services::stt::{reuploaded_bytes_total, SttClient, SttError};
+use cleuly::services::{is_transient, is_transient_status};
+use cleuly::util::retry::{backoff_ceiling, jittered_delay, retry_with_backoff, retry_with_sleep};
+use reqwest::StatusCode;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const BASE: Duration = Duration::from_millis(100);
+const MAX: Duration = Duration::from_millis(500);
+
+#[test]
+fn test_backoff_ceiling_doubles_up_to_max() {
+    let ceilings: Vec<_> = (0..5).map(|retry| backoff_ceiling(retry, BASE, MAX)).collect();
+    assert_eq!(
+        ceilings,
+        [100, 200, 400, 500, 500].map(Duration::from_millis).to_vec()
+    );
+
+    // No overflow for absurd retry counts
+    assert_eq!(backoff_ceiling(64, BASE, MAX), MAX);
+}
+
+#[test]
+fn test_full_jitter_stays_under_ceiling() {
+    assert_eq!(jittered_delay(2, BASE, MAX, 0.0), Duration::ZERO);
+    assert_eq!(jittered_delay(2, BASE, MAX, 0.5), Duration::from_millis(200));
+    assert_eq!(jittered_delay(2, BASE, MAX, 7.0), Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn test_retries_until_success_on_fake_clock() {
+    let calls = Cell::new(0);
+    let slept = RefCell::new(Vec::new());
+
+    let result: Result<u32, &str> = retry_with_sleep(
+        5,
+        |_| true,
+        || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move { if call < 3 { Err("busy") } else { Ok(call) } }
+        },
+        |retry| {
+            slept.borrow_mut().push(backoff_ceiling(retry, BASE, MAX));
+            async {}
+        },
+    )
+    .await;
+
+    assert_eq!(result, Ok(3));
+    assert_eq!(*slept.borrow(), [BASE, BASE * 2]);
+}
+
+#[tokio::test]
+async fn test_stops_at_attempts_or_non_retryable_error() {
+    let calls = Cell::new(0);
+    let result: Result<(), &str> = retry_with_sleep(
+        3,
+        |_| true,
+        || {
+            calls.set(calls.get() + 1);
+            async { Err("busy") }
+        },
+        |_| async {},
+    )
+    .await;
+    assert_eq!(result, Err("busy"));
+    assert_eq!(calls.get(), 3);
+
+    calls.set(0);
+    let result: Result<(), &str> = retry_with_sleep(
+        3,
+        |e: &&str| *e == "busy",
+        || {
+            calls.set(calls.get() + 1);
+            async { Err("invalid") }
+        },
+        |_| async {},
+    )
+    .await;
+    assert_eq!(result, Err("invalid"));
+    assert_eq!(calls.get(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_with_backoff_sleeps_between_attempts() {
+    let calls = Cell::new(0);
+    let result: Result<u32, &str> = retry_with_backoff(
+        0,
+        Duration::from_millis(1),
+        Duration::from_millis(5),
+        |_| true,
+        || {
+            calls.set(calls.get() + 1);
+            async { Err("busy") }
+        },
+    )
+    .await;
+
+    // Zero attempts still runs once
+    assert_eq!(result, Err("busy"));
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn test_transient_statuses() {
+    assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+    assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+    assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+
+    assert!(!is_transient_status(StatusCode::BAD_REQUEST));
+    assert!(!is_transient_status(StatusCode::UNAUTHORIZED));
+    assert!(!is_transient_status(StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn test_connection_refused_is_transient() {
+    // Nothing listens on port 1, so this fails at connect time
+    let error = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+    assert!(is_transient(&error));
+}
+
+#[tokio::test]
+async fn test_invalid_url_is_not_transient() {
+    let error = reqwest::get("not a url").await.unwrap_err();
+    assert!(!is_transient(&error));
+}
+
+/// Transcription endpoint that returns 503 for the first `failures` calls
+async fn flaky_stt(failures: usize, config: &mut Config) -> Arc<AtomicUsize> {
+    let hits = Arc::new(AtomicUsize::new(0));
+
+    let app = Router::new()
+        .route(
+            "/audio/transcriptions",
+            post(|State((hits, failures)): State<(Arc<AtomicUsize>, usize)>| async move {
+                if hits.fetch_add(1, Ordering::SeqCst) < failures {
+                    (AxumStatus::SERVICE_UNAVAILABLE, "overloaded".to_string())
+                } else {
+                    (AxumStatus::OK, r#"{"text":"hello","language":"en"}"#.to_string())
+                }
+            }),
+        )
+        .with_state((hits.clone(), failures));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    config.providers.insert(
+        "groq",
+        ProviderConfig {
+            name: "groq",
+            base_url: format!("http://{}", addr),
+            api_key: "test-key".to_string(),
+            default_model: "llama-3.1-8b-instant".to_string(),
+        },
+    );
+    hits
+}
+
+#[tokio::test]
+async fn test_small_upload_retries_until_success() {
+    let mut config = Config::default();
+    let hits = flaky_stt(2, &mut config).await;
+    let before = reuploaded_bytes_total();
+
+    let stt = SttClient::new(&config).unwrap();
+    let result = stt.transcribe(vec![0; 16], "clip.wav", None).await.unwrap();
+
+    assert_eq!(result.text, "hello");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+    assert!(reuploaded_bytes_total() >= before + 32);
+}
+
+#[tokio::test]
+async fn test_large_upload_gets_fewer_attempts() {
+    let mut config = Config {
+        stt_large_file_bytes: 1024,
+        stt_max_attempts_large: 1,
+        ..Default::default()
+    };
+    let hits = flaky_stt(2, &mut config).await;
+
+    let stt = SttClient::new(&config).unwrap();
+    assert_eq!(stt.max_attempts_for(2048), 1);
+    assert_eq!(stt.max_attempts_for(16), 3);
+
+    let result = stt.transcribe(vec![0; 2048], "clip.wav", None).await;
+
+    assert!(matches!(result, Err(SttError::ApiError(_))));
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
@@ -1,20 +1,33 @@
 use axum::http::StatusCode;
-use axum::Router;
 use axum_test::TestServer;
-use cleuly::{config, modules, AppState};
+use cleuly::router::build_router;
+use cleuly::config::Config;
+use cleuly::modules::session::controller::{
+    check_metadata_limits, limit_message_content, search_terms, snippet, validate_metadata,
+    validate_metadata_key,
+};
+use cleuly::modules::session::crud::{MessageEmbeddingCrud, SessionCrud, SessionEmbeddingCrud};
+use cleuly::modules::session::model::{Message, MessageEmbedding, Session, SessionEmbedding};
+use cleuly::services::cache;
+use cleuly::{config, AppState};
 use serde_json::json;
 
 async fn setup_test_server() -> TestServer {
+    setup_test_server_with(|_| {}).await
+}
+
+async fn setup_test_server_with(configure: impl FnOnce(&mut Config)) -> TestServer {
     dotenvy::dotenv().ok();
 
-    let db = config::database::connect().await;
-    let redis = config::redis::connect().await;
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    configure(&mut settings);
 
-    let state = AppState { db, redis };
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
 
-    let app = Router::new()
-        .merge(modules::session::routes::routes())
-        .with_state(state);
+    let state = AppState::new(db, redis, settings.clone());
+
+    let app = build_router(state, &settings);
 
     TestServer::new(app).unwrap()
 }
@@ -55,6 +68,101 @@ async fn test_create_session_with_defaults() {
     assert_eq!(body["session_type"], "general");
 }
 
+#[tokio::test]
+async fn test_session_limit_per_key() {
+    let server = setup_test_server_with(|config| config.max_sessions_per_key = Some(2)).await;
+    let key = format!("limit-{}", uuid::Uuid::new_v4());
+    let other_key = format!("limit-{}", uuid::Uuid::new_v4());
+
+    let mut ids = Vec::new();
+    for api_key in [&key, &key, &other_key] {
+        let response = server
+            .post("/api/session")
+            .add_header("x-api-key", api_key.as_str())
+            .json(&json!({ "title": "Limited" }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        ids.push(response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string());
+    }
+
+    let response = server
+        .post("/api/session")
+        .add_header("x-api-key", key.as_str())
+        .json(&json!({ "title": "One too many" }))
+        .await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "session_limit_reached");
+
+    for id in ids {
+        server.delete(&format!("/api/session/{}", id)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_sessions_are_scoped_to_their_owner() {
+    let (owner, other) = ("owner-key", "other-key");
+    let server = setup_test_server_with(|config| {
+        config.api_keys = vec![owner.to_string(), other.to_string()];
+    })
+    .await;
+
+    let response = server
+        .post("/api/session")
+        .add_header("x-api-key", owner)
+        .json(&json!({ "title": "Private" }))
+        .await;
+    let id = response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    let path = format!("/api/session/{}", id);
+
+    server.get(&path).add_header("x-api-key", owner).await.assert_status_ok();
+    server
+        .get(&path)
+        .add_header("x-api-key", other)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .post(&format!("{}/message", path))
+        .add_header("x-api-key", other)
+        .json(&json!({ "role": "user", "content": "hi" }))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    for (key, visible) in [(owner, true), (other, false)] {
+        let body: serde_json::Value = server.get("/api/sessions").add_header("x-api-key", key).await.json();
+        let listed = body["data"].as_array().unwrap().iter().any(|s| s["id"] == id.as_str());
+        assert_eq!(listed, visible);
+    }
+
+    server
+        .delete(&path)
+        .add_header("x-api-key", other)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server.delete(&path).add_header("x-api-key", owner).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_session_bundle() {
+    let server = setup_test_server().await;
+    let response = server
+        .post("/api/session")
+        .json(&json!({ "title": "Archive me" }))
+        .await;
+    let id = response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let response = server.get(&format!("/api/session/{}/bundle", id)).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-type"), "application/zip");
+    // Local file header signature
+    assert!(response.as_bytes().starts_with(b"PK\x03\x04"));
+
+    server.delete(&format!("/api/session/{}", id)).await;
+    server
+        .get(&format!("/api/session/{}/bundle", id))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_get_session() {
     let server = setup_test_server().await;
@@ -78,6 +186,49 @@ async fn test_get_session() {
     let fetched: serde_json::Value = get_response.json();
     assert_eq!(fetched["id"], id);
     assert_eq!(fetched["title"], "Get Test Session");
+    assert_eq!(fetched["stale"], false);
+}
+
+#[tokio::test]
+async fn test_get_session_not_modified() {
+    let server = setup_test_server().await;
+
+    let create_response = server
+        .post("/api/session")
+        .json(&json!({
+            "title": "ETag Session"
+        }))
+        .await;
+
+    let created: serde_json::Value = create_response.json();
+    let id = created["id"].as_str().unwrap();
+
+    let first = server.get(&format!("/api/session/{}", id)).await;
+    first.assert_status(StatusCode::OK);
+    let etag = first.header("etag");
+
+    // Unchanged session returns 304
+    let second = server
+        .get(&format!("/api/session/{}", id))
+        .add_header("if-none-match", etag.clone())
+        .await;
+    second.assert_status(StatusCode::NOT_MODIFIED);
+
+    // Adding a message changes the ETag
+    server
+        .post(&format!("/api/session/{}/message", id))
+        .json(&json!({
+            "role": "user",
+            "content": "New message"
+        }))
+        .await
+        .assert_status(StatusCode::OK);
+
+    let third = server
+        .get(&format!("/api/session/{}", id))
+        .add_header("if-none-match", etag)
+        .await;
+    third.assert_status(StatusCode::OK);
 }
 
 #[tokio::test]
@@ -111,6 +262,43 @@ async fn test_list_sessions() {
     assert!(body["total"].is_number());
 }
 
+#[tokio::test]
+async fn test_pinned_sessions_are_listed_first() {
+    let server = setup_test_server().await;
+
+    let older: serde_json::Value = server
+        .post("/api/session")
+        .json(&json!({ "title": "Pin me" }))
+        .await
+        .json();
+    let id = older["id"].as_str().unwrap();
+    server.post("/api/session").json(&json!({ "title": "Newer" })).await;
+
+    let response = server.post(&format!("/api/session/{}/pin", id)).await;
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.json::<serde_json::Value>()["pinned"], true);
+
+    let list: serde_json::Value = server.get("/api/sessions").await.json();
+    assert_eq!(list["data"][0]["id"], id);
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["pinned"], true);
+
+    let response = server.post(&format!("/api/session/{}/unpin", id)).await;
+    assert_eq!(response.json::<serde_json::Value>()["pinned"], false);
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[tokio::test]
+async fn test_pin_missing_session() {
+    let server = setup_test_server().await;
+
+    let response = server.post("/api/session/507f1f77bcf86cd799439011/pin").await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_delete_session() {
     let server = setup_test_server().await;
@@ -136,6 +324,26 @@ async fn test_delete_session() {
     get_response.assert_status(StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn test_delete_session_cascade() {
+    let server = setup_test_server().await;
+
+    let create_response = server
+        .post("/api/session")
+        .json(&json!({ "title": "Cascade" }))
+        .await;
+    let id = create_response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let delete_response = server
+        .delete(&format!("/api/session/{}", id))
+        .add_query_param("cascade", "true")
+        .await;
+
+    delete_response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = delete_response.json();
+    assert_eq!(body["deleted_transcriptions"], 0);
+}
+
 #[tokio::test]
 async fn test_add_message_to_session() {
     let server = setup_test_server().await;
@@ -228,6 +436,11 @@ async fn test_chat_with_session() {
     assert_eq!(chat["message"]["role"], "user");
     assert_eq!(chat["response"]["role"], "assistant");
     assert!(!chat["response"]["content"].as_str().unwrap().is_empty());
+    assert!(chat["message"]["meta"].is_null());
+    assert_eq!(chat["persisted"], true);
+    assert_eq!(chat["response"]["meta"]["model"], chat["model"]);
+    assert_eq!(chat["response"]["meta"]["max_tokens"], 1000);
+    assert!(chat["response"]["meta"]["latency_ms"].is_u64());
 }
 
 #[tokio::test]
@@ -308,3 +521,478 @@ async fn test_multi_turn_conversation() {
     let session_data: serde_json::Value = session.json();
     assert_eq!(session_data["message_count"], 4);
 }
+
+#[tokio::test]
+async fn test_create_session_title_too_long_returns_field_errors() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/session")
+        .json(&json!({
+            "title": "x".repeat(101)
+        }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["fields"]["title"][0], "Title too long");
+}
+
+#[tokio::test]
+async fn test_bulk_add_messages_preserves_order_and_timestamps() {
+    let server = setup_test_server().await;
+
+    let create_response = server
+        .post("/api/session")
+        .json(&json!({}))
+        .await;
+
+    let created: serde_json::Value = create_response.json();
+    let id = created["id"].as_str().unwrap();
+
+    let bulk_response = server
+        .post(&format!("/api/session/{}/messages/bulk", id))
+        .json(&json!({
+            "messages": [
+                { "role": "user", "content": "Tell me about yourself", "timestamp": "2026-01-31T09:30:00Z" },
+                { "role": "assistant", "content": "I'm a backend engineer" }
+            ]
+        }))
+        .await;
+
+    bulk_response.assert_status(StatusCode::OK);
+    let body: serde_json::Value = bulk_response.json();
+    assert_eq!(body["added"], 2);
+    assert_eq!(body["message_count"], 2);
+
+    let session: serde_json::Value = server.get(&format!("/api/session/{}", id)).await.json();
+    assert_eq!(session["messages"][0]["content"], "Tell me about yourself");
+    assert!(session["messages"][0]["timestamp"]
+        .as_str()
+        .unwrap()
+        .starts_with("2026-01-31T09:30:00"));
+    assert_eq!(session["messages"][1]["role"], "assistant");
+}
+
+#[tokio::test]
+async fn test_bulk_add_messages_invalid_timestamp_fails() {
+    let server = setup_test_server().await;
+
+    let create_response = server
+        .post("/api/session")
+        .json(&json!({}))
+        .await;
+
+    let created: serde_json::Value = create_response.json();
+    let id = created["id"].as_str().unwrap();
+
+    let bulk_response = server
+        .post(&format!("/api/session/{}/messages/bulk", id))
+        .json(&json!({
+            "messages": [
+                { "role": "user", "content": "Hello", "timestamp": "yesterday" }
+            ]
+        }))
+        .await;
+
+    bulk_response.assert_status(StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = bulk_response.json();
+    assert!(body["fields"]["messages[0].timestamp"].is_array());
+}
+
+#[tokio::test]
+async fn test_latest_message_by_role() {
+    let server = setup_test_server().await;
+
+    let create_response = server
+        .post("/api/session")
+        .json(&json!({}))
+        .await;
+
+    let created: serde_json::Value = create_response.json();
+    let id = created["id"].as_str().unwrap();
+
+    // Empty session has no latest message
+    server
+        .get(&format!("/api/session/{}/latest", id))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    server
+        .post(&format!("/api/session/{}/messages/bulk", id))
+        .json(&json!({
+            "messages": [
+                { "role": "user", "content": "First question" },
+                { "role": "assistant", "content": "First answer" },
+                { "role": "user", "content": "Second question" }
+            ]
+        }))
+        .await
+        .assert_status_ok();
+
+    let latest: serde_json::Value = server
+        .get(&format!("/api/session/{}/latest", id))
+        .await
+        .json();
+    assert_eq!(latest["content"], "Second question");
+
+    let latest_assistant: serde_json::Value = server
+        .get(&format!("/api/session/{}/latest", id))
+        .add_query_param("role", "assistant")
+        .await
+        .json();
+    assert_eq!(latest_assistant["content"], "First answer");
+
+    server
+        .get(&format!("/api/session/{}/latest", id))
+        .add_query_param("role", "system")
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_search_messages_by_time() {
+    let server = setup_test_server().await;
+
+    let from = chrono::Utc::now() - chrono::Duration::seconds(1);
+
+    let response = server
+        .post("/api/session")
+        .json(&json!({ "title": "Search Test", "session_type": "meeting" }))
+        .await;
+    let session_id = response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/session/{}/message", session_id))
+        .json(&json!({ "role": "user", "content": "What was discussed?" }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/search/messages")
+        .add_query_param("from", from.to_rfc3339())
+        .add_query_param("role", "user")
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|m| m["session_id"] == session_id.as_str() && m["snippet"] == "What was discussed?"));
+    assert_eq!(body["page"], 1);
+
+    server.delete(&format!("/api/session/{}", session_id)).await;
+}
+
+#[tokio::test]
+async fn test_search_messages_full_text() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+    SessionCrud::new(&db, redis).ensure_indexes().await.unwrap();
+
+    let server = setup_test_server().await;
+    let response = server
+        .post("/api/session")
+        .json(&json!({ "title": "Text Search", "session_type": "meeting" }))
+        .await;
+    let session_id = response.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let word = format!("zebra{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    for content in [
+        "Welcome everyone.".to_string(),
+        format!("Today we review the {} migration. It went well.", word),
+    ] {
+        server
+            .post(&format!("/api/session/{}/message", session_id))
+            .json(&json!({ "role": "user", "content": content }))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/search/messages")
+        .add_query_param("q", &word)
+        .add_query_param("per_page", 5)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["per_page"], 5);
+    let hit = &body["data"][0];
+    assert!(hit["snippet"].as_str().unwrap().contains(&word));
+    assert!(hit["score"].as_f64().unwrap() > 0.0);
+
+    // A page far past the end is empty rather than an overflowed skip
+    let response = server
+        .get("/api/search/messages")
+        .add_query_param("q", &word)
+        .add_query_param("page", u64::MAX)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total"], 1);
+    assert!(body["data"].as_array().unwrap().is_empty());
+
+    server.delete(&format!("/api/session/{}", session_id)).await;
+}
+
+#[test]
+fn test_search_snippets() {
+    assert_eq!(search_terms(r#"budget "q3" -draft"#), vec!["budget", "q3"]);
+
+    let content = "Hi all. First item is hiring. The budget for Q3 is tight! Let's discuss. Any questions?";
+    assert_eq!(
+        snippet(content, &search_terms("budget")),
+        "…First item is hiring. The budget for Q3 is tight! Let's discuss.…"
+    );
+
+    // No match: the start of the message
+    assert_eq!(snippet("Short note.", &search_terms("budget")), "Short note.");
+
+    // One long sentence is cut around the term
+    let long = format!("{} budget {}", "word ".repeat(200), "word ".repeat(200));
+    let cut = snippet(&long, &search_terms("budget"));
+    assert!(cut.starts_with('…') && cut.ends_with('…'));
+    assert!(cut.contains("budget"));
+    assert!(cut.chars().count() <= 242);
+}
+
+#[tokio::test]
+async fn test_search_messages_rejects_inverted_range() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .get("/api/search/messages")
+        .add_query_param("from", "2026-01-02T00:00:00Z")
+        .add_query_param("to", "2026-01-01T00:00:00Z")
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[test]
+fn test_validate_metadata_against_schema() {
+    let schema = jsonschema::validator_for(&json!({
+        "type": "object",
+        "properties": {
+            "customer": {
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"]
+            }
+        }
+    }))
+    .unwrap();
+
+    assert!(validate_metadata(&schema, &json!({ "customer": { "id": "c-1" } })).is_ok());
+
+    let error = validate_metadata(&schema, &json!({ "customer": { "id": 42 } })).unwrap_err();
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.body.code, "invalid_metadata");
+    assert!(error.body.fields.unwrap().contains_key("metadata/customer/id"));
+}
+
+#[test]
+fn test_metadata_limits() {
+    let metadata = json!({ "config": { "theme": "dark", "tags": ["a", "b"] } });
+    assert!(check_metadata_limits(&metadata, 1024, 3).is_ok());
+
+    let error = check_metadata_limits(&metadata, 1024, 2).unwrap_err();
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.body.code, "metadata_too_large");
+
+    let error = check_metadata_limits(&json!({ "notes": "x".repeat(100) }), 64, 16).unwrap_err();
+    assert_eq!(error.body.code, "metadata_too_large");
+    assert!(error.body.message.contains("the limit is 64"));
+}
+
+#[test]
+fn test_limit_message_content() {
+    let (content, truncated) = limit_message_content("short".to_string(), 10, "reject").unwrap();
+    assert_eq!(content, "short");
+    assert!(!truncated);
+
+    let error = limit_message_content("x".repeat(11), 10, "reject").unwrap_err();
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.body.code, "message_too_long");
+
+    // Counted and cut in characters, not bytes
+    let (content, truncated) = limit_message_content("héllo wörld".to_string(), 7, "truncate").unwrap();
+    assert_eq!(content, "héllo w");
+    assert!(truncated);
+
+    let stored = bson::to_document(&Message::user(content).with_truncated(true)).unwrap();
+    assert_eq!(stored.get_bool("truncated"), Ok(true));
+}
+
+#[test]
+fn test_incomplete_flag_only_stored_when_set() {
+    let finished = bson::to_document(&Message::assistant("Done".to_string())).unwrap();
+    assert!(!finished.contains_key("incomplete"));
+
+    let partial = Message::assistant("Half".to_string()).with_incomplete(true);
+    let stored = bson::to_document(&partial).unwrap();
+    assert_eq!(stored.get_bool("incomplete"), Ok(true));
+    assert!(bson::from_document::<Message>(stored).unwrap().incomplete);
+}
+
+#[tokio::test]
+async fn test_get_metadata_by_key() {
+    let server = setup_test_server().await;
+
+    let created: serde_json::Value = server
+        .post("/api/session")
+        .json(&json!({ "metadata": { "config": { "theme": "dark", "size": 3 } } }))
+        .await
+        .json();
+    let id = created["id"].as_str().unwrap();
+
+    let all: serde_json::Value = server.get(&format!("/api/session/{}/metadata", id)).await.json();
+    assert_eq!(all["value"]["config"]["size"], 3);
+    assert!(all.get("key").is_none());
+
+    let theme: serde_json::Value = server
+        .get(&format!("/api/session/{}/metadata", id))
+        .add_query_param("key", "config.theme")
+        .await
+        .json();
+    assert_eq!(theme, json!({ "key": "config.theme", "value": "dark" }));
+
+    server
+        .get(&format!("/api/session/{}/metadata", id))
+        .add_query_param("key", "config.missing")
+        .await
+        .assert_status_not_found();
+
+    server.delete(&format!("/api/session/{}", id)).await;
+}
+
+#[test]
+fn test_validate_metadata_key() {
+    assert!(validate_metadata_key("theme").is_ok());
+    assert!(validate_metadata_key("config.theme").is_ok());
+
+    assert!(validate_metadata_key("").is_err());
+    assert!(validate_metadata_key("config..theme").is_err());
+    assert!(validate_metadata_key("config.").is_err());
+    assert!(validate_metadata_key("$where").is_err());
+}
+
+#[tokio::test]
+async fn test_read_racing_a_write_does_not_cache_stale_session() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+    let crud = SessionCrud::new(&db, redis.clone());
+
+    let id = crud.create(Session::new(None, None, None)).await.unwrap();
+    let cache_key = format!("session:{}", id.to_hex());
+    let generation_key = format!("session:generation:{}", id.to_hex());
+
+    // A reader takes the generation and reads the session from Mongo...
+    let generation = cache::generation(&redis, &generation_key).await.unwrap();
+    let before = crud.find_by_id(&id).await.unwrap().unwrap();
+    cache::del(&redis, &cache_key).await;
+
+    // ...a message is added and the cache invalidated...
+    crud.add_message(&id, Message::user("hello".to_string())).await.unwrap();
+
+    // ...and only then does the reader try to cache what it read
+    let cached = cache::set_ex_if_generation(
+        &redis,
+        &cache_key,
+        serde_json::to_string(&before).unwrap(),
+        60,
+        &generation_key,
+        generation,
+    )
+    .await;
+    assert!(!cached);
+
+    let session = crud.find_by_id(&id).await.unwrap().unwrap();
+    assert_eq!(session.messages.len(), 1);
+    // Now cached, and current
+    let session = crud.find_by_id(&id).await.unwrap().unwrap();
+    assert_eq!(session.messages.len(), 1);
+
+    crud.delete(&id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stale_embeddings_select_sessions_to_reembed() {
+    use futures::TryStreamExt;
+
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let messages = MessageEmbeddingCrud::new(&db);
+    let sessions = SessionEmbeddingCrud::new(&db);
+
+    let model = format!("embed-{}", uuid::Uuid::new_v4());
+    let (current, stale) = (bson::oid::ObjectId::new(), bson::oid::ObjectId::new());
+    let vector = vec![1.0, 0.0];
+    let embedding = MessageEmbedding::new(current, 0, model.clone(), vector.clone());
+    messages.upsert_many(&[embedding]).await.unwrap();
+    messages
+        .upsert_many(&[
+            MessageEmbedding::new(stale, 0, model.clone(), vector.clone()),
+            MessageEmbedding::new(stale, 1, "retired-model".to_string(), vector.clone()),
+        ])
+        .await
+        .unwrap();
+    let retired = "retired-model".to_string();
+    sessions.upsert(&SessionEmbedding::new(current, None, model.clone(), vector.clone())).await.unwrap();
+    sessions.upsert(&SessionEmbedding::new(stale, None, retired, vector)).await.unwrap();
+
+    let from_messages: Vec<_> =
+        messages.stale_session_ids(&model).await.unwrap().try_collect().await.unwrap();
+    assert!(from_messages.contains(&stale));
+    assert!(!from_messages.contains(&current));
+
+    let from_sessions: Vec<_> =
+        sessions.stale_session_ids(&model).await.unwrap().try_collect().await.unwrap();
+    assert!(from_sessions.contains(&stale));
+    assert!(!from_sessions.contains(&current));
+
+    for id in [current, stale] {
+        messages.delete_by_session(&id).await.unwrap();
+        sessions.delete_by_session(&id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_message_embeddings_upsert_by_index() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let messages = MessageEmbeddingCrud::new(&db);
+    messages.ensure_indexes().await.unwrap();
+
+    let session = bson::oid::ObjectId::new();
+    let model = "embed-model".to_string();
+    let first = [
+        MessageEmbedding::new(session, 0, model.clone(), vec![1.0, 0.0]),
+        MessageEmbedding::new(session, 1, model.clone(), vec![0.0, 1.0]),
+    ];
+    messages.upsert_many(&first).await.unwrap();
+    // Storing message 1 again replaces its vector rather than adding one
+    let again = MessageEmbedding::new(session, 1, model.clone(), vec![1.0, 1.0]);
+    messages.upsert_many(&[again]).await.unwrap();
+
+    let stored = messages.find_by_session(&session).await.unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[1].vector, vec![1.0, 1.0]);
+
+    assert_eq!(messages.truncate(&session, 1).await.unwrap(), 1);
+    assert_eq!(messages.find_by_session(&session).await.unwrap().len(), 1);
+
+    messages.delete_by_session(&session).await.unwrap();
+}
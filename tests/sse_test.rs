@@ -0,0 +1,42 @@
+use axum::response::sse::Event;
+use axum::{routing::get, Router};
+use axum_test::TestServer;
+use cleuly::sse::{cancel_on_drop, sse_response_with_interval};
+use futures::{stream, StreamExt};
+use std::convert::Infallible;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_idle_stream_emits_keepalive_comments() {
+    let app: Router = Router::new().route(
+        "/events",
+        get(|| async {
+            let events = stream::unfold(0, |n| async move {
+                if n == 2 {
+                    return None;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Some((Ok::<_, Infallible>(Event::default().data(n.to_string())), n + 1))
+            });
+            sse_response_with_interval(events, Duration::from_millis(20))
+        }),
+    );
+
+    let server = TestServer::new(app).unwrap();
+    let body = server.get("/events").await.text();
+
+    assert!(body.contains(": keepalive"));
+    assert!(body.contains("data: 0"));
+    assert!(body.contains("data: 1"));
+}
+
+#[tokio::test]
+async fn test_dropped_stream_cancels_its_token() {
+    let token = tokio_util::sync::CancellationToken::new();
+    let mut events = Box::pin(cancel_on_drop(stream::iter([1, 2]), token.clone()));
+
+    assert_eq!(events.next().await, Some(1));
+    assert!(!token.is_cancelled());
+    drop(events);
+    assert!(token.is_cancelled());
+}
@@ -1,19 +1,37 @@
 use axum::http::StatusCode;
 use axum::Router;
 use axum_test::TestServer;
-use cleuly::{config, modules, AppState};
+use cleuly::router::build_router;
+use cleuly::auth::ApiKey;
+use cleuly::modules::session::crud::{text_phrase, SessionCrud};
+use cleuly::modules::session::model::{Message, Session};
+use cleuly::modules::stt::controller::read_audio_upload;
+use cleuly::modules::stt::crud::SttCrud;
+use cleuly::modules::stt::model::SttTranscription;
+use cleuly::modules::stt::schema::{FacetValue, LanguageCount};
+use cleuly::services::stt::{
+    download_audio, is_public_ip, parse_transcription, provider_error, SttClient, SttError,
+    SttResponse, SttSegment,
+};
+use cleuly::config::Config;
+use cleuly::{config, AppState};
 
 async fn setup_test_server() -> TestServer {
+    setup_test_server_with(|_| {}).await
+}
+
+async fn setup_test_server_with(configure: impl FnOnce(&mut Config)) -> TestServer {
     dotenvy::dotenv().ok();
 
-    let db = config::database::connect().await;
-    let redis = config::redis::connect().await;
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    configure(&mut settings);
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
 
-    let state = AppState { db, redis };
+    let state = AppState::new(db, redis, settings.clone());
 
-    let app = Router::new()
-        .merge(modules::stt::routes::routes())
-        .with_state(state);
+    let app = build_router(state, &settings);
 
     TestServer::new(app).unwrap()
 }
@@ -32,6 +50,16 @@ async fn test_supported_formats() {
     assert!(formats.contains(&"webm".to_string()));
 }
 
+#[tokio::test]
+async fn test_responses_are_compressed_on_request() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/api/stt/formats").add_header("accept-encoding", "gzip").await;
+
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.header("content-encoding"), "gzip");
+}
+
 #[tokio::test]
 async fn test_transcribe_no_file() {
     let server = setup_test_server().await;
@@ -57,6 +85,36 @@ async fn test_list_transcriptions() {
     assert!(body["total"].is_number());
 }
 
+#[tokio::test]
+async fn test_transcriptions_are_scoped_to_their_owner() {
+    let (owner, other) = (ApiKey(Some("owner-key".to_string())), "other-key");
+    let server = setup_test_server_with(|config| {
+        config.api_keys = vec!["owner-key".to_string(), other.to_string()];
+    })
+    .await;
+
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let transcription =
+        SttTranscription::new("mine".to_string(), None, None, "whisper".to_string(), None, None, None)
+            .with_owner(owner.id());
+    let id = SttCrud::new(&db).create(transcription).await.unwrap();
+    let path = format!("/api/stt/transcription/{}", id.to_hex());
+
+    server.get(&path).add_header("x-api-key", "owner-key").await.assert_status_ok();
+    server
+        .get(&path)
+        .add_header("x-api-key", other)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .delete(&path)
+        .add_header("x-api-key", other)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server.delete(&path).add_header("x-api-key", "owner-key").await.assert_status_ok();
+}
+
 #[tokio::test]
 async fn test_get_transcription_not_found() {
     let server = setup_test_server().await;
@@ -84,7 +142,614 @@ async fn test_delete_transcription_not_found() {
     response.assert_status(StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn test_transcription_facets() {
+    let server = setup_test_server().await;
+
+    let response = server.get("/api/stt/facets").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["languages"].is_array());
+    assert!(body["models"].is_array());
+}
+
+#[tokio::test]
+async fn test_language_stats() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let crud = SttCrud::new(&db);
+
+    // A random millisecond in the 1990s keeps this test's records apart from any others
+    let millis = 631_152_000_000 + (uuid::Uuid::new_v4().as_u128() % 315_360_000_000) as i64;
+    let created_at = bson::DateTime::from_millis(millis);
+    let mut ids = Vec::new();
+    for language in [Some("fr"), Some("en"), Some("fr"), None] {
+        let mut transcription = SttTranscription::new(
+            "bonjour".to_string(),
+            language.map(str::to_string),
+            None,
+            "whisper-large-v3-turbo".to_string(),
+            None,
+            None,
+            None,
+        );
+        transcription.created_at = created_at;
+        ids.push(crud.create(transcription).await.unwrap());
+    }
+
+    let groups = crud
+        .language_counts(Some(created_at), Some(bson::DateTime::from_millis(millis + 1)))
+        .await
+        .unwrap();
+    let counts: Vec<LanguageCount> =
+        groups.into_iter().map(|g| bson::from_document(g).unwrap()).collect();
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[0].language.as_deref(), Some("fr"));
+    assert_eq!(counts[0].count, 2);
+    assert_eq!(counts.iter().map(|c| c.count).sum::<u64>(), 4);
+
+    for id in ids {
+        crud.delete(&id).await.unwrap();
+    }
+
+    let server = setup_test_server().await;
+    server
+        .get("/api/stt/languages/stats")
+        .add_query_param("from", "2024-02-01T00:00:00Z")
+        .add_query_param("to", "2024-01-01T00:00:00Z")
+        .await
+        .assert_status_bad_request();
+    let response = server.get("/api/stt/languages/stats").await;
+    response.assert_status_ok();
+    assert!(response.json::<serde_json::Value>()["languages"].is_array());
+}
+
+#[tokio::test]
+async fn test_related_sessions() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
+    let sessions = SessionCrud::new(&db, redis);
+    sessions.ensure_indexes().await.unwrap();
+
+    let word = format!("walrus{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let text = format!("Ship the {} release on Friday", word);
+    let transcription = SttCrud::new(&db)
+        .create(SttTranscription::new(text.clone(), None, None, "whisper".to_string(), None, None, None))
+        .await
+        .unwrap();
+
+    let mut session = Session::new(None, Some("meeting".to_string()), None);
+    session.messages.push(Message::new("user".to_string(), format!("Notes: {}.", text)));
+    let related = sessions.create(session).await.unwrap();
+    let mut session = Session::new(None, Some("meeting".to_string()), None);
+    session.messages.push(Message::new("user".to_string(), format!("The {} release slipped", word)));
+    let unrelated = sessions.create(session).await.unwrap();
+
+    let server = setup_test_server().await;
+    let response = server
+        .get(&format!("/api/stt/transcription/{}/related-sessions", transcription.to_hex()))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["data"][0]["id"], related.to_hex());
+
+    server
+        .get("/api/stt/transcription/507f1f77bcf86cd799439011/related-sessions")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    sessions.delete(&related).await.unwrap();
+    sessions.delete(&unrelated).await.unwrap();
+    SttCrud::new(&db).delete(&transcription).await.unwrap();
+}
+
+#[test]
+fn test_text_phrase() {
+    assert_eq!(text_phrase("  say \"hi\"\n there ").as_deref(), Some("\"say hi there\""));
+    assert_eq!(text_phrase(" \"\" "), None);
+}
+
+#[test]
+fn test_facet_value_from_group() {
+    let value: FacetValue = bson::from_document(bson::doc! { "_id": "en", "count": 3 }).unwrap();
+    assert_eq!(value.value.as_deref(), Some("en"));
+    assert_eq!(value.count, 3);
+
+    let value: FacetValue =
+        bson::from_document(bson::doc! { "_id": bson::Bson::Null, "count": 1 }).unwrap();
+    assert!(value.value.is_none());
+    assert_eq!(serde_json::to_value(&value).unwrap()["value"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn test_same_timestamp_listing_is_stable() {
+    dotenvy::dotenv().ok();
+    let settings = Config::from_env().expect("Invalid configuration");
+    let db = config::database::connect(&settings).await;
+    let crud = SttCrud::new(&db);
+
+    // Bulk inserts commonly share a timestamp; a unique model keeps this test's
+    // records apart from any others
+    let model = format!("tie-break-{}", uuid::Uuid::new_v4());
+    let created_at = bson::DateTime::now();
+    for i in 0..6 {
+        let mut transcription =
+            SttTranscription::new(format!("clip {}", i), None, None, model.clone(), None, None, None);
+        transcription.created_at = created_at;
+        crud.create(transcription).await.unwrap();
+    }
+
+    let filter = bson::doc! { "model": &model };
+    let ids = |items: Vec<SttTranscription>| items.into_iter().map(|t| t.id.unwrap()).collect::<Vec<_>>();
+
+    let all = ids(crud.find_all(filter.clone(), 6).await.unwrap());
+    let first_page = ids(crud.find_all(filter.clone(), 3).await.unwrap());
+
+    assert_eq!(all.len(), 6);
+    assert_eq!(first_page, all[..3]);
+    // Ties fall back to newest `_id` first
+    assert!(all.windows(2).all(|w| w[0] > w[1]));
+
+    assert_eq!(crud.count(filter).await.unwrap(), 6);
+    for id in all {
+        crud.delete(&id).await.unwrap();
+    }
+}
+
+#[test]
+fn test_processing_transcription_round_trip() {
+    let record = SttTranscription::processing("whisper-large-v3-turbo".to_string(), None, None);
+    assert_eq!(record.status.as_deref(), Some("processing"));
+
+    // Records written before streaming persistence have neither field
+    let mut doc = bson::to_document(&SttTranscription::new(
+        "hi".to_string(),
+        None,
+        None,
+        "whisper-large-v3-turbo".to_string(),
+        None,
+        None,
+        None,
+    ))
+    .unwrap();
+    assert!(!doc.contains_key("status") && !doc.contains_key("segments"));
+    doc.insert("segments", vec!["hello", "there"]);
+    let legacy: SttTranscription = bson::from_document(doc).unwrap();
+    assert_eq!(legacy.segments, vec!["hello", "there"]);
+    assert!(legacy.status.is_none());
+}
+
+#[tokio::test]
+async fn test_delete_session_transcriptions() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .delete("/api/stt/sessions/507f1f77bcf86cd799439011/transcriptions")
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["deleted"], 0);
+
+    let response = server.delete("/api/stt/sessions/not-an-id/transcriptions").await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_transcribe_invalid_add_as_role() {
+    let server = setup_test_server().await;
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n";
+
+    let response = server
+        .post("/api/stt/transcribe?session_id=507f1f77bcf86cd799439011&add_as=narrator")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(response.text().contains("add_as"));
+}
+
+fn segment(text: &str, no_speech_prob: f32) -> SttSegment {
+    SttSegment {
+        id: 0,
+        start: 0.0,
+        end: 1.0,
+        text: text.to_string(),
+        avg_logprob: None,
+        no_speech_prob: Some(no_speech_prob),
+    }
+}
+
+#[test]
+fn test_filter_low_confidence_segments() {
+    let mut response = SttResponse {
+        text: " Hello there. Thanks for watching! How are you?".to_string(),
+        language: Some("en".to_string()),
+        duration: Some(3.0),
+        model: "whisper-large-v3-turbo".to_string(),
+        segments: vec![
+            segment(" Hello there.", 0.05),
+            segment(" Thanks for watching!", 0.9),
+            segment(" How are you?", 0.1),
+        ],
+    };
+
+    let filtered = response.filter_low_confidence(0.5);
+
+    assert_eq!(filtered, 1);
+    assert_eq!(response.segments.len(), 2);
+    assert_eq!(response.text, "Hello there. How are you?");
+}
+
+#[test]
+fn test_average_confidence() {
+    let mut response = SttResponse {
+        text: "Hello there. How are you?".to_string(),
+        language: Some("en".to_string()),
+        duration: Some(2.0),
+        model: "whisper-large-v3-turbo".to_string(),
+        segments: vec![segment(" Hello there.", 0.0), segment(" How are you?", 0.2)],
+    };
+    assert!((response.average_confidence().unwrap() - 0.9).abs() < 1e-6);
+
+    // Token log-probabilities scale it down further
+    response.segments[0].avg_logprob = Some(-std::f32::consts::LN_2);
+    assert!((response.average_confidence().unwrap() - 0.65).abs() < 1e-6);
+
+    response.segments.clear();
+    assert_eq!(response.average_confidence(), None);
+}
+
+#[test]
+fn test_provider_error_detects_unavailable_model() {
+    let body = r#"{"error":{"message":"The model `whisper-large-v3-turbo` does not exist or you do not have access to it.","type":"invalid_request_error","code":"model_not_found"}}"#;
+    match provider_error(body, "whisper-large-v3-turbo") {
+        SttError::ModelUnavailable { model, suggestion } => {
+            assert_eq!(model, "whisper-large-v3-turbo");
+            assert_eq!(suggestion.as_deref(), Some("whisper-large-v3"));
+        }
+        other => panic!("expected ModelUnavailable, got {:?}", other),
+    }
+
+    let body = r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#;
+    assert!(matches!(provider_error(body, "whisper-large-v3"), SttError::ApiError(_)));
+    assert!(matches!(provider_error("upstream down", "whisper-large-v3"), SttError::ApiError(_)));
+
+    // Markers without a code only count when the message names the model
+    let body = r#"{"error":{"message":"The model `whisper-large-v3` has been decommissioned","type":"invalid_request_error"}}"#;
+    assert!(matches!(provider_error(body, "whisper-large-v3"), SttError::ModelUnavailable { .. }));
+    let body = r#"{"error":{"message":"language xx is not supported by this model","type":"invalid_request_error"}}"#;
+    assert!(matches!(provider_error(body, "whisper-large-v3"), SttError::ApiError(_)));
+    let body = r#"{"error":{"message":"The model `other-model` does not exist","type":"invalid_request_error"}}"#;
+    assert!(matches!(provider_error(body, "whisper-large-v3"), SttError::ApiError(_)));
+}
+
+#[tokio::test]
+async fn test_transcribe_invalid_min_confidence() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/stt/transcribe?min_confidence=1.5")
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
 // Note: Full transcription tests require:
 // 1. GROQ_API_KEY to be set
 // 2. Actual audio file to upload
 // These are integration tests that should be run manually
+
+#[tokio::test]
+async fn test_download_audio_reports_progress() {
+    let app: Router = Router::new().route(
+        "/clip.wav",
+        axum::routing::get(|| async { vec![0u8; 200 * 1024] }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let mut reports = Vec::new();
+    let data = download_audio(&format!("http://{}/clip.wav", addr), true, |p| reports.push(p))
+        .await
+        .unwrap();
+
+    assert_eq!(data.len(), 200 * 1024);
+    assert!(reports.len() >= 2);
+    let last = reports.last().unwrap();
+    assert_eq!(last.downloaded, 200 * 1024);
+    assert_eq!(last.total, Some(200 * 1024));
+}
+
+#[test]
+fn test_only_public_addresses_are_public() {
+    for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+        assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in [
+        "127.0.0.1",
+        "10.0.0.1",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "255.255.255.255",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+}
+
+#[tokio::test]
+async fn test_download_audio_refuses_private_addresses() {
+    let app: Router = Router::new().route(
+        "/clip.wav",
+        axum::routing::get(|| async { vec![0u8; 1024] }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    for url in [
+        format!("http://{}/clip.wav", addr),
+        format!("http://localhost:{}/clip.wav", addr.port()),
+        "http://169.254.169.254/latest/meta-data".to_string(),
+        "http://[::1]/clip.wav".to_string(),
+    ] {
+        let result = download_audio(&url, false, |_| {}).await;
+        assert!(matches!(result, Err(SttError::DownloadFailed(_))), "{}: {:?}", url, result);
+    }
+}
+
+#[tokio::test]
+async fn test_download_audio_follows_redirects() {
+    let app: Router = Router::new()
+        .route(
+            "/old.wav",
+            axum::routing::get(|| async { axum::response::Redirect::temporary("/clip.wav") }),
+        )
+        .route("/clip.wav", axum::routing::get(|| async { vec![0u8; 1024] }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let data = download_audio(&format!("http://{}/old.wav", addr), true, |_| {})
+        .await
+        .unwrap();
+    assert_eq!(data.len(), 1024);
+}
+
+#[tokio::test]
+async fn test_transcribe_url_rejects_non_http_url() {
+    let server = setup_test_server().await;
+
+    let response = server
+        .post("/api/stt/transcribe-url")
+        .json(&serde_json::json!({ "url": "ftp://example.com/clip.wav" }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_truncated_upload_is_interrupted() {
+    let app: Router = Router::new().route(
+        "/upload",
+        axum::routing::post(|multipart: axum::extract::Multipart| async move {
+            read_audio_upload(&Config::default(), multipart)
+                .await
+                .map(|upload| upload.data.len().to_string())
+        }),
+    );
+    let server = TestServer::new(app).unwrap();
+
+    // The closing boundary never arrives, as when the client disconnects
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF partial audio";
+
+    let response = server
+        .post("/upload")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .expect_failure()
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error: serde_json::Value = response.json();
+    assert_eq!(error["code"], "upload_interrupted");
+}
+
+#[tokio::test]
+async fn test_upload_over_body_limit_is_payload_too_large() {
+    let app: Router = Router::new()
+        .route(
+            "/upload",
+            axum::routing::post(|multipart: axum::extract::Multipart| async move {
+                read_audio_upload(&Config::default(), multipart)
+                    .await
+                    .map(|upload| upload.data.len().to_string())
+            }),
+        )
+        .layer(axum::extract::DefaultBodyLimit::max(1024));
+    let server = TestServer::new(app).unwrap();
+
+    let mut body = b"--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\nRIFF"
+        .to_vec();
+    body.extend(vec![0u8; 4096]);
+    body.extend(b"\r\n--BOUNDARY--\r\n");
+
+    let response = server
+        .post("/upload")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.into())
+        .expect_failure()
+        .await;
+
+    response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    let error: serde_json::Value = response.json();
+    assert_eq!(error["code"], "payload_too_large");
+}
+
+fn upload_server() -> TestServer {
+    let app: Router = Router::new().route(
+        "/upload",
+        axum::routing::post(|multipart: axum::extract::Multipart| async move {
+            read_audio_upload(&Config::default(), multipart)
+                .await
+                .map(|upload| axum::Json(serde_json::to_value(upload.metadata).unwrap()))
+        }),
+    );
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn test_unsupported_upload_is_rejected() {
+    let server = upload_server();
+
+    let uploads = [("notes.txt", "RIFF fake audio"), ("clip.mp3", "not audio at all")];
+    for (file_name, content) in uploads {
+        let body = format!(
+            "--BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\r\n\
+            {}\r\n\
+            --BOUNDARY--\r\n",
+            file_name, content
+        );
+
+        let response = server
+            .post("/upload")
+            .content_type("multipart/form-data; boundary=BOUNDARY")
+            .bytes(body.into_bytes().into())
+            .expect_failure()
+            .await;
+
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["code"], "unsupported_audio_format", "{}", file_name);
+    }
+}
+
+#[tokio::test]
+async fn test_upload_metadata_fields() {
+    let server = upload_server();
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        Standup \r\n\
+        --BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+        daily, team,,daily\r\n\
+        --BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\r\n\
+        q3\r\n\
+        --BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"unknown\"\r\n\r\n\
+        ignored\r\n\
+        --BOUNDARY--\r\n";
+
+    let response = server
+        .post("/upload")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.as_bytes().to_vec().into())
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    let metadata: serde_json::Value = response.json();
+    assert_eq!(metadata["title"], "Standup");
+    assert!(metadata.get("speaker").is_none());
+    assert_eq!(metadata["tags"], serde_json::json!(["daily", "team", "q3"]));
+}
+
+#[tokio::test]
+async fn test_upload_metadata_too_long() {
+    let server = upload_server();
+
+    let body = format!(
+        "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"speaker\"\r\n\r\n\
+        {}\r\n\
+        --BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"clip.wav\"\r\n\r\n\
+        RIFF fake audio\r\n\
+        --BOUNDARY--\r\n",
+        "x".repeat(65)
+    );
+
+    let response = server
+        .post("/upload")
+        .content_type("multipart/form-data; boundary=BOUNDARY")
+        .bytes(body.into_bytes().into())
+        .expect_failure()
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_every_supported_format_has_a_mime_type() {
+    let config = Config::default();
+    let formats = SttClient::supported_formats(&config);
+
+    assert!(formats.contains(&"mp4"));
+    for format in &formats {
+        let mime = SttClient::mime_type(&config, &format!("clip.{}", format)).unwrap();
+        assert!(mime.starts_with("audio/"), "{} maps to {}", format, mime);
+    }
+
+    let unique: std::collections::BTreeSet<_> = formats.iter().collect();
+    assert_eq!(unique.len(), formats.len());
+    assert_eq!(SttClient::mime_type(&config, "CLIP.WAV"), Some("audio/wav"));
+    assert_eq!(SttClient::mime_type(&config, "notes.txt"), None);
+}
+
+#[test]
+fn test_parse_text_transcription() {
+    let response = parse_transcription(" Hello there.\n", "text", "groq", "whisper-large-v3-turbo").unwrap();
+
+    assert_eq!(response.text, "Hello there.");
+    assert_eq!(response.duration, None);
+    assert_eq!(response.language, None);
+    assert!(response.segments.is_empty());
+}
+
+#[test]
+fn test_parse_verbose_json_transcription() {
+    let body = r#"{"text":"Hi","language":"english","duration":1.5,"segments":[{"id":0,"start":0.0,"end":1.5,"text":"Hi"}]}"#;
+    let response = parse_transcription(body, "verbose_json", "groq", "whisper-large-v3-turbo").unwrap();
+    assert_eq!(response.duration, Some(1.5));
+    assert_eq!(response.language.as_deref(), Some("english"));
+    assert_eq!(response.segments.len(), 1);
+
+    // Missing fields are kept as unknown rather than failing the request
+    let response = parse_transcription(r#"{"text":"Hi"}"#, "verbose_json", "groq", "whisper").unwrap();
+    assert_eq!(response.duration, None);
+
+    let result = parse_transcription("Hi", "json", "groq", "whisper");
+    let Err(SttError::InvalidResponse(message)) = result else {
+        panic!("expected InvalidResponse");
+    };
+    assert!(message.starts_with("groq returned"));
+}
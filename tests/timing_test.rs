@@ -0,0 +1,51 @@
+use axum::{response::IntoResponse, Json};
+use cleuly::timing::{LlmCall, ServerTiming, SERVER_TIMING};
+use std::time::Duration;
+
+#[test]
+fn test_header_value_sums_repeated_phases() {
+    let mut timing = ServerTiming::new();
+    timing.record("db", Duration::from_millis(3));
+    timing.record("llm", Duration::from_millis(512));
+    timing.record("db", Duration::from_millis(5));
+
+    assert_eq!(timing.header_value(), "db;dur=8, llm;dur=512");
+}
+
+#[tokio::test]
+async fn test_measure_records_phase() {
+    let mut timing = ServerTiming::new();
+    let value = timing
+        .measure("llm", async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            42
+        })
+        .await;
+
+    assert_eq!(value, 42);
+    let header = timing.header_value();
+    let millis: u64 = header.strip_prefix("llm;dur=").unwrap().parse().unwrap();
+    assert!(millis >= 20);
+}
+
+#[test]
+fn test_header_only_when_measured() {
+    let response = (ServerTiming::new(), Json("ok")).into_response();
+    assert!(response.headers().get(SERVER_TIMING).is_none());
+
+    let mut timing = ServerTiming::new();
+    timing.record("stt", Duration::from_millis(40));
+    let response = (timing, Json("ok")).into_response();
+    assert_eq!(response.headers()[SERVER_TIMING], "stt;dur=40");
+}
+
+#[test]
+fn test_llm_call_added_to_extensions() {
+    let mut timing = ServerTiming::new();
+    timing.llm_call("model-a", Some(120));
+    let response = (timing, Json("ok")).into_response();
+
+    let call = response.extensions().get::<LlmCall>().unwrap();
+    assert_eq!(call.model, "model-a");
+    assert_eq!(call.tokens, Some(120));
+}
@@ -0,0 +1,29 @@
+use cleuly::services::tokens::{count_tokens, estimate_tokens, has_tokenizer};
+
+#[test]
+fn test_estimate_rounds_up() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("abc"), 1);
+    assert_eq!(estimate_tokens("abcdefgh"), 2);
+    // Characters, not bytes
+    assert_eq!(estimate_tokens("日本語の"), 1);
+}
+
+#[test]
+fn test_unknown_models_fall_back_to_estimate() {
+    let text = "fn main() { println!(\"hello\"); }";
+
+    assert!(!has_tokenizer("llama-3.1-8b-instant"));
+    assert_eq!(count_tokens(text, "llama-3.1-8b-instant"), estimate_tokens(text));
+}
+
+#[cfg(feature = "tokenizer")]
+#[test]
+fn test_openai_models_are_counted_exactly() {
+    assert!(has_tokenizer("gpt-4o"));
+    assert!(has_tokenizer("openai/gpt-4o-mini"));
+    assert!(has_tokenizer("gpt-3.5-turbo"));
+
+    assert_eq!(count_tokens("hello world", "gpt-4o"), 2);
+    assert_eq!(count_tokens("hello world", "openai/gpt-3.5-turbo"), 2);
+}
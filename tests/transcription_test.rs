@@ -1,20 +1,26 @@
 use axum::http::StatusCode;
-use axum::Router;
 use axum_test::TestServer;
-use cleuly::{config, modules, AppState};
+use cleuly::router::build_router;
+use cleuly::config::Config;
+use cleuly::{config, AppState};
 use serde_json::json;
 
 async fn setup_test_server() -> TestServer {
+    setup_test_server_with(|_| {}).await
+}
+
+async fn setup_test_server_with(configure: impl FnOnce(&mut Config)) -> TestServer {
     dotenvy::dotenv().ok();
 
-    let db = config::database::connect().await;
-    let redis = config::redis::connect().await;
+    let mut settings = Config::from_env().expect("Invalid configuration");
+    configure(&mut settings);
+
+    let db = config::database::connect(&settings).await;
+    let redis = config::redis::connect(&settings).await;
 
-    let state = AppState { db, redis };
+    let state = AppState::new(db, redis, settings.clone());
 
-    let app = Router::new()
-        .merge(modules::transcription::routes::routes())
-        .with_state(state);
+    let app = build_router(state, &settings);
 
     TestServer::new(app).unwrap()
 }
@@ -138,6 +144,35 @@ async fn test_delete_transcription() {
     get_response.assert_status(StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn test_transcriptions_are_scoped_to_their_owner() {
+    let (owner, other) = ("owner-key", "other-key");
+    let server = setup_test_server_with(|config| {
+        config.api_keys = vec![owner.to_string(), other.to_string()];
+    })
+    .await;
+
+    let created: serde_json::Value = server
+        .post("/api/transcription")
+        .add_header("x-api-key", owner)
+        .json(&json!({ "text": "Owner only", "source": "test" }))
+        .await
+        .json();
+    let id = created["id"].as_str().unwrap();
+    let path = format!("/api/transcription/{}", id);
+
+    server.get(&path).add_header("x-api-key", other).await.assert_status(StatusCode::NOT_FOUND);
+    server.delete(&path).add_header("x-api-key", other).await.assert_status(StatusCode::NOT_FOUND);
+
+    for (key, visible) in [(owner, true), (other, false)] {
+        let body: serde_json::Value = server.get("/api/transcriptions").add_header("x-api-key", key).await.json();
+        let listed = body["data"].as_array().unwrap().iter().any(|t| t["id"] == id);
+        assert_eq!(listed, visible);
+    }
+
+    server.delete(&path).add_header("x-api-key", owner).await.assert_status_ok();
+}
+
 #[tokio::test]
 async fn test_delete_transcription_not_found() {
     let server = setup_test_server().await;
@@ -146,3 +181,43 @@ async fn test_delete_transcription_not_found() {
 
     response.assert_status(StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_create_transcription_dedupe() {
+    let server = setup_test_server().await;
+    let text = format!("Retried note {}", uuid::Uuid::new_v4());
+    let payload = json!({ "text": text, "source": "notes" });
+
+    let first = server.post("/api/transcription").json(&payload).await;
+    first.assert_status(StatusCode::CREATED);
+    let id = first.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    // A retry with dedupe gets the same transcription back
+    let retry = server
+        .post("/api/transcription")
+        .add_query_param("dedupe", true)
+        .json(&payload)
+        .await;
+    retry.assert_status(StatusCode::OK);
+    assert_eq!(retry.json::<serde_json::Value>()["id"], id);
+
+    // A different source is not a duplicate
+    let other = server
+        .post("/api/transcription")
+        .add_query_param("dedupe", true)
+        .json(&json!({ "text": text, "source": "microphone" }))
+        .await;
+    other.assert_status(StatusCode::CREATED);
+    let other_id = other.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    assert_ne!(other_id, id);
+
+    // Without dedupe, a copy is stored as before
+    let copy = server.post("/api/transcription").json(&payload).await;
+    copy.assert_status(StatusCode::CREATED);
+    let copy_id = copy.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    assert_ne!(copy_id, id);
+
+    for id in [id, other_id, copy_id] {
+        server.delete(&format!("/api/transcription/{}", id)).await;
+    }
+}